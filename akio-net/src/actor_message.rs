@@ -0,0 +1,69 @@
+use super::errors::*;
+use super::rpc;
+use akio::Headers;
+use akio::remote::RemoteMessage;
+use akio::serialization::SerializedMessage;
+use std::time::{Duration, UNIX_EPOCH};
+use uuid::Uuid;
+
+pub(crate) fn encode_message(message: &RemoteMessage) -> rpc::Request_oneof_payload {
+    let mut encoded = rpc::ActorMessage::new();
+    encoded.set_actor_id(message.actor_id.as_bytes().as_ref().into());
+    encoded.set_message_type_id(message.message.message_type_id.clone().into());
+    encoded.set_payload(message.message.payload.clone().into());
+    rpc::Request_oneof_payload::actor_message(encoded)
+}
+
+pub(crate) fn decode_message(message: &rpc::ActorMessage) -> Result<(Uuid, SerializedMessage)> {
+    let actor_id = Uuid::from_bytes(message.get_actor_id())
+        .map_err(|e| ErrorKind::InvalidActorId(format!("{}", e)))?;
+    let serialized = SerializedMessage {
+        message_type_id: message.get_message_type_id().to_string(),
+        payload: message.get_payload().to_vec(),
+    };
+    Ok((actor_id, serialized))
+}
+
+pub(crate) fn encode_headers(headers: &Headers) -> rpc::Headers {
+    let mut encoded = rpc::Headers::new();
+    encoded.set_correlation_id(headers.correlation_id.as_bytes().as_ref().into());
+    if let Some(sender) = headers.sender {
+        encoded.set_sender(sender.as_bytes().as_ref().into());
+    }
+    let timestamp = headers
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_secs(0));
+    encoded.set_timestamp_millis(
+        timestamp.as_secs() * 1000 + u64::from(timestamp.subsec_nanos() / 1_000_000),
+    );
+    if let Some(ref trace_context) = headers.trace_context {
+        encoded.set_trace_context(trace_context.clone().into());
+    }
+    for (key, value) in &headers.extra {
+        let mut header = rpc::Header::new();
+        header.set_key(key.clone().into());
+        header.set_value(value.clone().into());
+        encoded.mut_extra().push(header);
+    }
+    encoded
+}
+
+pub(crate) fn decode_headers(headers: &rpc::Headers) -> Headers {
+    let mut decoded = Headers::new();
+    if let Ok(correlation_id) = Uuid::from_bytes(headers.get_correlation_id()) {
+        decoded.correlation_id = correlation_id;
+    }
+    decoded.sender = Uuid::from_bytes(headers.get_sender()).ok();
+    decoded.timestamp = UNIX_EPOCH + Duration::from_millis(headers.get_timestamp_millis());
+    decoded.trace_context = match headers.get_trace_context() {
+        "" => None,
+        trace_context => Some(trace_context.to_string()),
+    };
+    decoded.extra = headers
+        .get_extra()
+        .iter()
+        .map(|header| (header.get_key().to_string(), header.get_value().to_string()))
+        .collect();
+    decoded
+}
@@ -1,17 +1,19 @@
+extern crate akio;
 extern crate akio_net;
 extern crate env_logger;
-extern crate futures;
-extern crate tokio_core;
 
+use akio::ActorSystem;
+use akio::context;
 use akio_net::*;
-use futures::future;
-use tokio_core::reactor::Core;
 
 
 pub fn main() {
     env_logger::init().unwrap();
-    let mut core = Core::new().unwrap();
-    let node = RemoteNode::new(&core.handle(), &"127.0.0.1:6667".parse().unwrap()).unwrap();
-    node.connect(&"127.0.0.1:6666".parse().unwrap());
-    core.run(future::empty::<(), ()>()).unwrap();
+    let mut system = ActorSystem::new();
+    system.on_startup(|| {
+        let listen_address = "127.0.0.1:6667".parse().unwrap();
+        let node = RemoteNode::new(&context::system(), &context::handle(), &listen_address).unwrap();
+        node.connect(&"127.0.0.1:6666".parse().unwrap());
+    });
+    system.start();
 }
@@ -1,11 +1,20 @@
+use super::client_state::ClientTxState;
 use super::ipc::*;
 use super::protocol::initialize_tx_stream;
+use futures::future::{self, Either, Loop};
 use futures::prelude::*;
 use futures::sync::mpsc;
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio_core::net::TcpStream;
-use tokio_core::reactor::Handle;
+use tokio_core::reactor::{Handle, Timeout};
 
+/// Attempts made to connect to a node before giving up.
+const CONNECT_ATTEMPTS: u32 = 3;
+const RETRY_DELAY_MILLIS: u64 = 200;
+
+/// Connects to `target`, retrying a few times, and sends `TxConnectFailed`
+/// if it can't.
 pub(crate) fn create_client(
     server_id: ClientId,
     listener_addr: &SocketAddr,
@@ -15,17 +24,56 @@ pub(crate) fn create_client(
 ) {
     let handle = in_handle.clone();
     let listener_addr = *listener_addr;
-    let client_stream = TcpStream::connect(target, in_handle)
+    let target_addr = *target;
+    let failure_sender = client_event_sender.clone();
+    let client_stream = future::loop_fn(1, move |attempt| {
+        let retry_handle = handle.clone();
+        connect(
+            server_id,
+            listener_addr,
+            target_addr,
+            handle.clone(),
+            client_event_sender.clone(),
+        ).then(move |result| match result {
+            Ok(_) => Either::A(future::ok(Loop::Break(()))),
+            Err(_) if attempt < CONNECT_ATTEMPTS => {
+                warn!("Failed to connect to '{}', retrying", target_addr);
+                let delay = Duration::from_millis(RETRY_DELAY_MILLIS * u64::from(attempt));
+                let retry = Timeout::new(delay, &retry_handle)
+                    .expect("failed to create timeout")
+                    .map(move |_| Loop::Continue(attempt + 1))
+                    .map_err(|_| ());
+                Either::B(retry)
+            }
+            Err(_) => Either::A(future::err(())),
+        })
+    }).or_else(move |_| {
+        error!("Failed to connect to '{}'", target_addr);
+        failure_sender
+            .send(ClientEvent::TxConnectFailed(target_addr))
+            .map(|_| ())
+            .map_err(|_| ())
+    });
+    in_handle.spawn(client_stream);
+}
+
+fn connect(
+    server_id: ClientId,
+    listener_addr: SocketAddr,
+    target_addr: SocketAddr,
+    handle: Handle,
+    client_event_sender: mpsc::Sender<ClientEvent>,
+) -> impl Future<Item = ClientTxState, Error = ()> {
+    TcpStream::connect(&target_addr, &handle)
         .map_err(|_| ())
         .and_then(move |stream| {
             initialize_tx_stream(
                 server_id,
                 &listener_addr,
+                &target_addr,
                 stream,
                 handle,
-                client_event_sender.clone(),
+                client_event_sender,
             )
         })
-        .map(|_| ());
-    in_handle.spawn(client_stream);
 }
@@ -99,7 +99,33 @@ impl ClientRxState {
     }
 
     pub fn respond(&self, id: u64, result: Result<()>) -> impl Future<Item = (), Error = ()> {
-        let response = Self::make_response_to_parts(id, result);
+        self.send_response(Self::make_response_to_parts(id, result))
+    }
+
+    /// Answers actor message `id` with the encoded response, or an error
+    /// code if it could not be delivered.
+    pub fn respond_to_actor_message(
+        &self,
+        id: u64,
+        result: Result<Vec<u8>>,
+    ) -> impl Future<Item = (), Error = ()> {
+        let mut response = Self::make_response_to_parts(id, Ok(()));
+        match result {
+            Ok(payload) => {
+                let mut actor_response = rpc::ActorResponse::new();
+                actor_response.set_payload(payload.into());
+                response.payload =
+                    Some(rpc::Response_oneof_payload::actor_response(actor_response));
+            }
+            Err(e) => {
+                warn!("[{}] Failed to deliver actor message '{}': {}", self.id(), id, e);
+                response.set_error(rpc::ErrorCode::ACTOR_MESSAGE_FAILED);
+            }
+        }
+        self.send_response(response)
+    }
+
+    fn send_response(&self, response: rpc::Response) -> impl Future<Item = (), Error = ()> {
         self.inner
             .tx
             .clone()
@@ -113,6 +139,7 @@ impl ClientRxState {
 #[derive(Debug)]
 struct ClientTxStateInner {
     id: ClientId,
+    addr: SocketAddr,
     tx: mpsc::Sender<rpc::Request>,
 }
 
@@ -124,11 +151,13 @@ pub(crate) struct ClientTxState {
 impl ClientState for ClientTxState {
     type Receive = rpc::Response;
     type Send = rpc::Request;
-    type Params = ClientId;
+    type Params = (ClientId, SocketAddr);
 
     fn new(params: Self::Params, tx: mpsc::Sender<Self::Send>) -> Self {
+        let (id, addr) = params;
         let inner = ClientTxStateInner {
-            id: params,
+            id: id,
+            addr: addr,
             tx: tx,
         };
         Self {
@@ -154,6 +183,10 @@ impl ClientState for ClientTxState {
 }
 
 impl ClientTxState {
+    pub fn addr(&self) -> SocketAddr {
+        self.inner.addr
+    }
+
     pub fn make_request(payload: rpc::Request_oneof_payload) -> rpc::Request {
         Self::make_request_raw(Some(payload))
     }
@@ -165,6 +198,15 @@ impl ClientTxState {
         request
     }
 
+    fn make_request_with_headers(
+        payload: rpc::Request_oneof_payload,
+        headers: rpc::Headers,
+    ) -> rpc::Request {
+        let mut request = Self::make_request(payload);
+        request.set_headers(headers);
+        request
+    }
+
     pub fn request(
         &self,
         payload: rpc::Request_oneof_payload,
@@ -176,7 +218,19 @@ impl ClientTxState {
         &self,
         payload: Option<rpc::Request_oneof_payload>,
     ) -> (u64, impl Future<Item = (), Error = ()>) {
-        let request = Self::make_request_raw(payload);
+        self.send_request(Self::make_request_raw(payload))
+    }
+
+    /// Sends `payload` with the headers of the actor message it carries.
+    pub fn request_with_headers(
+        &self,
+        payload: rpc::Request_oneof_payload,
+        headers: rpc::Headers,
+    ) -> (u64, impl Future<Item = (), Error = ()>) {
+        self.send_request(Self::make_request_with_headers(payload, headers))
+    }
+
+    fn send_request(&self, request: rpc::Request) -> (u64, impl Future<Item = (), Error = ()>) {
         (
             request.id,
             self.inner
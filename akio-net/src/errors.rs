@@ -1,5 +1,13 @@
 error_chain!{
     errors {
+        InvalidActorId(reason: String) {
+            description("invalid actor id")
+            display("invalid actor id: {}", reason)
+        }
+    }
+
+    links {
+        Akio(::akio::errors::Error, ::akio::errors::ErrorKind);
     }
 
     foreign_links {
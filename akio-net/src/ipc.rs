@@ -1,5 +1,6 @@
 use super::client_state::{ClientRxState, ClientTxState};
 use super::rpc;
+use std::net::SocketAddr;
 use uuid::Uuid;
 
 pub(crate) type ClientId = Uuid;
@@ -14,6 +15,8 @@ pub(crate) enum ClientEvent {
     RxDisconnected(ClientId),
     TxConnected(ClientId, ClientTxState),
     TxDisconnected(ClientId),
+    /// No connection could be made to the node listening on the address.
+    TxConnectFailed(SocketAddr),
     RequestReceived(ClientId, rpc::Request),
     ResponseReceived(ClientId, rpc::Response),
 }
@@ -1,5 +1,6 @@
 #![feature(conservative_impl_trait)]
 
+extern crate akio;
 extern crate bytes;
 #[macro_use]
 extern crate error_chain;
@@ -15,6 +16,7 @@ extern crate tokio_io;
 extern crate tokio_timer;
 extern crate uuid;
 
+mod actor_message;
 mod client;
 mod client_state;
 mod errors;
@@ -1,9 +1,13 @@
+use super::actor_message::*;
 use super::client::create_client;
 use super::client_state::*;
 use super::errors::*;
 use super::ipc::*;
 use super::rpc;
 use super::server::{start_server, Server};
+use akio::{Actor, ActorRef, ActorSystem, Headers};
+use akio::remote::{RemoteActor, RemoteMessage};
+use futures::future::{self, Either};
 use futures::prelude::*;
 use futures::sync::{mpsc, oneshot};
 use parking_lot::Mutex;
@@ -11,8 +15,13 @@ use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio_core::reactor::Handle;
+use tokio_core::reactor::{Handle, Timeout};
 use tokio_timer;
+use uuid::Uuid;
+
+/// How long a remote actor has to respond, unless changed with
+/// `RemoteNode::set_request_timeout`.
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 10;
 
 #[derive(Clone)]
 pub struct RemoteNode {
@@ -20,16 +29,26 @@ pub struct RemoteNode {
 }
 
 impl RemoteNode {
-    pub fn new(handle: &Handle, listen_address: &SocketAddr) -> Result<Self> {
+    /// Listens on `listen_address` for other nodes and delivers the actor
+    /// messages they send to actors in `system`. `handle` should belong to
+    /// one of `system`'s threads, e.g. `context::handle()` in `on_startup`,
+    /// so that delivered messages can activate actors.
+    pub fn new(
+        system: &ActorSystem,
+        handle: &Handle,
+        listen_address: &SocketAddr,
+    ) -> Result<Self> {
         let (client_event_sender, client_event_receiver) = mpsc::channel(100);
+        let (outbox, outbox_receiver) = mpsc::unbounded();
         let my_id = new_client_id();
         let server = start_server(my_id, handle, listen_address, client_event_sender.clone())?;
         info!("Listening on '{}'", listen_address);
-        let inner = RemoteNodeInner::new(server, client_event_sender, handle);
+        let inner = RemoteNodeInner::new(server, system, outbox, client_event_sender, handle);
         let node = RemoteNode {
             inner: Arc::new(inner),
         };
         node.start_hearbeat_task();
+        node.start_outbox(outbox_receiver);
         node.clone().start_event_handler(client_event_receiver);
         Ok(node)
     }
@@ -38,6 +57,36 @@ impl RemoteNode {
         self.inner.connect_tx(addr)
     }
 
+    /// Requests to remote actors that haven't been answered within
+    /// `timeout` fail.
+    pub fn set_request_timeout(&self, timeout: Duration) {
+        *self.inner.request_timeout.lock() = timeout;
+    }
+
+    /// A ref to actor `id` on the node listening on `node`. It is used like
+    /// a local ref: calls to its `#[actor_api]` methods are sent over the
+    /// network, and the node is connected to on the first message. Only
+    /// messages registered with both systems, e.g. with the
+    /// `register_messages` function generated by
+    /// `#[actor_impl(serializable)]`, can be sent.
+    pub fn actor_ref<A>(&self, node: &SocketAddr, id: Uuid) -> ActorRef<A>
+    where
+        A: Actor,
+    {
+        ActorRef::remote(RemoteActor::new(
+            *node,
+            id,
+            self.inner.outbox.clone(),
+            self.inner.system.serializers(),
+        ))
+    }
+
+    fn start_outbox(&self, outbox: mpsc::UnboundedReceiver<RemoteMessage>) {
+        let inner = Arc::clone(&self.inner);
+        let sender = outbox.for_each(move |message| Ok(inner.send_actor_message(message)));
+        self.inner.handle.spawn(sender);
+    }
+
     fn start_event_handler(self, server_event_stream: mpsc::Receiver<ClientEvent>) {
         let handle = self.inner.handle.clone();
         let event_handler_f =
@@ -55,6 +104,7 @@ impl RemoteNode {
                 self.inner.client_tx_connected(client_id, state)
             }
             ClientEvent::TxDisconnected(client_id) => self.inner.client_tx_disconnected(client_id),
+            ClientEvent::TxConnectFailed(addr) => self.inner.client_tx_connect_failed(&addr),
             ClientEvent::RequestReceived(client_id, message) => {
                 self.inner.handle_request(client_id, message)
             }
@@ -138,6 +188,36 @@ impl RemoteConnection {
         })
     }
 
+    /// Forgets requests whose caller has stopped waiting for the response.
+    pub fn prune_requests(&mut self) {
+        self.pending_requests.retain(|_, promise| !promise.is_canceled());
+    }
+
+    /// Like `request`, for actor messages, whose headers travel with them.
+    pub fn request_with_headers(
+        &mut self,
+        payload: rpc::Request_oneof_payload,
+        headers: rpc::Headers,
+    ) -> Option<oneshot::Receiver<rpc::Response>> {
+        let maybe_id = self.tx.state().map(|client| {
+            let (id, f) = client.request_with_headers(payload, headers);
+            self.handle.spawn(f);
+            id
+        });
+        if maybe_id.is_none() {
+            error!("[{}] Failed to send request", self.client_id);
+        }
+        maybe_id.map(|id| {
+            let (promise, future) = oneshot::channel();
+            self.pending_requests.insert(id, promise);
+            future
+        })
+    }
+
+    pub fn is_tx_connected(&self) -> bool {
+        !self.tx.is_disconnected()
+    }
+
     pub fn request_oneway(&self, payload: rpc::Request_oneof_payload) -> Option<u64> {
         let maybe_id = self.request_raw(Some(payload));
         if maybe_id.is_none() {
@@ -165,7 +245,15 @@ impl RemoteConnection {
 
 struct RemoteNodeInner {
     server: Server,
+    system: ActorSystem,
     client_states: Mutex<HashMap<ClientId, RemoteConnection>>,
+    /// Nodes we have connected to, by the address they listen on.
+    client_ids: Mutex<HashMap<SocketAddr, ClientId>>,
+    /// Actor messages waiting for a connection to their node. A node has
+    /// an entry while it is being connected to.
+    pending_messages: Mutex<HashMap<SocketAddr, Vec<RemoteMessage>>>,
+    request_timeout: Mutex<Duration>,
+    outbox: mpsc::UnboundedSender<RemoteMessage>,
     client_event_sender: mpsc::Sender<ClientEvent>,
     handle: Handle,
 }
@@ -173,12 +261,19 @@ struct RemoteNodeInner {
 impl RemoteNodeInner {
     pub fn new(
         server: Server,
+        system: &ActorSystem,
+        outbox: mpsc::UnboundedSender<RemoteMessage>,
         client_event_sender: mpsc::Sender<ClientEvent>,
         handle: &Handle,
     ) -> Self {
         Self {
             server: server,
+            system: system.clone(),
             client_states: Mutex::new(HashMap::new()),
+            client_ids: Mutex::new(HashMap::new()),
+            pending_messages: Mutex::new(HashMap::new()),
+            request_timeout: Mutex::new(Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS)),
+            outbox: outbox,
             client_event_sender: client_event_sender,
             handle: handle.clone(),
         }
@@ -219,9 +314,15 @@ impl RemoteNodeInner {
 
     pub fn client_tx_connected(&self, client_id: ClientId, state: ClientTxState) {
         info!("[{}] tx connnected", client_id);
+        let addr = state.addr();
         self.with_client(client_id, |client| {
             client.tx = ConnectionState::Connected(state)
         });
+        self.client_ids.lock().insert(addr, client_id);
+        let pending = self.pending_messages.lock().remove(&addr);
+        for message in pending.unwrap_or_else(Vec::new) {
+            self.send_actor_message(message);
+        }
     }
 
     pub fn client_tx_disconnected(&self, client_id: ClientId) {
@@ -232,6 +333,16 @@ impl RemoteNodeInner {
         )
     }
 
+    /// Drops the messages waiting for the node, which fails their
+    /// requests. The next message to the node connects again.
+    pub fn client_tx_connect_failed(&self, addr: &SocketAddr) {
+        let pending = self.pending_messages.lock().remove(addr);
+        let dropped = pending.map_or(0, |messages| messages.len());
+        if dropped > 0 {
+            error!("Dropped {} messages to unreachable node '{}'", dropped, addr);
+        }
+    }
+
     pub fn with_client<F, R>(&self, client_id: ClientId, f: F) -> R
     where
         F: FnOnce(&mut RemoteConnection) -> R,
@@ -243,25 +354,103 @@ impl RemoteNodeInner {
         f(&mut client)
     }
 
-    pub fn handle_request(&self, client_id: ClientId, request: rpc::Request) {
+    pub fn handle_request(&self, client_id: ClientId, mut request: rpc::Request) {
         if request.payload.is_none() {
             self.with_client(client_id, |client| client.respond_ok(request.id));
         } else {
             debug!("[{}] received: {:?}", client_id, request);
-            match request.payload.unwrap() {
+            let headers = decode_headers(request.get_headers());
+            match request.payload.take().unwrap() {
+                rpc::Request_oneof_payload::actor_message(message) => {
+                    self.deliver_actor_message(client_id, request.id, headers, &message)
+                }
                 _ => (),
             };
         }
     }
 
+    fn deliver_actor_message(
+        &self,
+        client_id: ClientId,
+        id: u64,
+        headers: Headers,
+        message: &rpc::ActorMessage,
+    ) {
+        let client = match self.with_client(client_id, |client| client.rx.state().cloned()) {
+            Some(client) => client,
+            None => {
+                error!("[{}] Failed to respond to '{}'", client_id, id);
+                return;
+            }
+        };
+        let delivered: Box<Future<Item = Vec<u8>, Error = Error>> =
+            match decode_message(message) {
+                Ok((actor_id, message)) => Box::new(
+                    self.system
+                        .deliver_serialized(actor_id, &message, headers)
+                        .map_err(Error::from),
+                ),
+                Err(e) => Box::new(future::err(e)),
+            };
+        let response = delivered.then(move |result| client.respond_to_actor_message(id, result));
+        self.handle.spawn(response);
+    }
+
+    /// Sends `message` to its node, connecting to the node first if needed.
+    pub fn send_actor_message(&self, message: RemoteMessage) {
+        let client_id = self.client_ids.lock().get(&message.node).cloned();
+        let connected = client_id.map_or(false, |client_id| {
+            self.with_client(client_id, |client| client.is_tx_connected())
+        });
+        if !connected {
+            let mut pending = self.pending_messages.lock();
+            if !pending.contains_key(&message.node) {
+                self.connect_tx(&message.node);
+            }
+            pending
+                .entry(message.node)
+                .or_insert_with(Vec::new)
+                .push(message);
+            return;
+        }
+        let payload = encode_message(&message);
+        let headers = encode_headers(&message.headers);
+        let response = self.with_client(client_id.unwrap(), |client| {
+            client.request_with_headers(payload, headers)
+        });
+        if let (Some(response), Some(promise)) = (response, message.response) {
+            let timer = Timeout::new(*self.request_timeout.lock(), &self.handle)
+                .expect("failed to create timeout");
+            let (node, actor_id) = (message.node, message.actor_id);
+            // Dropping the promise fails the request.
+            let forward = response.select2(timer).then(move |result| {
+                match result {
+                    Ok(Either::A((ref response, _)))
+                        if response.error == rpc::ErrorCode::NONE
+                            && response.has_actor_response() =>
+                    {
+                        let _ = promise.send(response.get_actor_response().get_payload().to_vec());
+                    }
+                    Ok(Either::B(_)) => {
+                        warn!("Request to actor '{}' on '{}' timed out", actor_id, node)
+                    }
+                    _ => (),
+                }
+                Ok(())
+            });
+            self.handle.spawn(forward);
+        }
+    }
+
     pub fn handle_response(&self, client_id: ClientId, response: rpc::Response) {
         debug!("[{}] response: {:?}", client_id, response);
         self.with_client(client_id, |client| client.handle_response(response));
     }
 
     pub fn send_heartbeats(&self) {
-        let clients = self.client_states.lock();
-        clients.iter().for_each(|(id, client)| {
+        let mut clients = self.client_states.lock();
+        clients.iter_mut().for_each(|(id, client)| {
+            client.prune_requests();
             if client.request_raw(None).is_none() {
                 warn!("[{}] Heartbeat failed", id);
             }
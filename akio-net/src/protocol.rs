@@ -179,15 +179,23 @@ pub(crate) fn initialize_rx_stream(
 pub(crate) fn initialize_tx_stream(
     my_id: ClientId,
     listen_addr: &SocketAddr,
+    target: &SocketAddr,
     stream: TcpStream,
     handle: Handle,
     client_event_sender: mpsc::Sender<ClientEvent>,
 ) -> impl Future<Item = ClientTxState, Error = ()> {
     let listen_addr = *listen_addr;
+    let target = *target;
     let (sender, receiver) = bind_client::<ClientTxState, TcpStream>(stream);
     do_handshake(my_id, &listen_addr, sender, receiver).map(
         move |(client_id, sender2, receiver2)| {
-            when_connected(client_id, sender2, receiver2, &handle, client_event_sender)
+            when_connected(
+                (client_id, target),
+                sender2,
+                receiver2,
+                &handle,
+                client_event_sender,
+            )
         },
     )
 }
@@ -0,0 +1,177 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+extern crate akio_net;
+extern crate futures;
+extern crate uuid;
+
+use akio::prelude::*;
+use akio_net::RemoteNode;
+use futures::sync::oneshot;
+use std::net::SocketAddr;
+use std::time::Duration;
+use uuid::Uuid;
+
+pub struct Counter {
+    count: u64,
+}
+
+#[actor_impl(serializable)]
+impl Counter {
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+        }
+    }
+
+    #[actor_api]
+    pub fn add(&mut self, amount: u64) -> u64 {
+        self.count += amount;
+        self.respond(self.count)
+    }
+
+    #[actor_api]
+    pub fn count(&mut self) -> u64 {
+        self.respond(self.count)
+    }
+
+    #[actor_api]
+    pub fn stall(&mut self) -> u64 {
+        self.respond_async(future::empty::<(), ()>(), |counter, _| {
+            counter.respond(counter.count)
+        })
+    }
+}
+
+/// Adds to a counter on another node.
+pub struct Forwarder {
+    counter: ActorRef<Counter>,
+}
+
+#[actor_impl(serializable)]
+impl Forwarder {
+    #[actor_api]
+    pub fn forward_add(&mut self, amount: u64) -> u64 {
+        self.respond_fut(self.counter.add(amount))
+    }
+}
+
+fn address(port: u16) -> SocketAddr {
+    format!("127.0.0.1:{}", port).parse().unwrap()
+}
+
+fn register_messages(system: &ActorSystem) {
+    Counter::register_messages(system).unwrap();
+    Forwarder::register_messages(system).unwrap();
+}
+
+/// The other system in a test.
+pub struct Peer {
+    address: SocketAddr,
+    /// A `Counter` the peer started.
+    counter: Uuid,
+}
+
+/// Starts a system whose node listens on `port`. Its "forwarder" grains add
+/// to the "counter" grain of the node listening on `peer_port`.
+fn start_peer(port: u16, peer_port: u16) -> (ActorSystem, Peer) {
+    let mut system = ActorSystem::new();
+    let system_clone = system.clone();
+    let (sender, receiver) = oneshot::channel();
+    system.on_startup(move || {
+        register_messages(&system_clone);
+        let node = RemoteNode::new(&system_clone, &context::handle(), &address(port)).unwrap();
+        let counter = node.actor_ref::<Counter>(&address(peer_port), "counter".grain_id());
+        system_clone.register_factory(move |_| Forwarder {
+            counter: counter.clone(),
+        });
+        sender.send(Counter::new().start().id()).unwrap();
+    });
+    let counter = receiver.wait().unwrap();
+    let peer = Peer {
+        address: address(port),
+        counter: counter,
+    };
+    (system, peer)
+}
+
+/// Runs `f` in a system whose node listens on `ports.0`, next to a peer
+/// system listening on `ports.1`. The systems only share the messages they
+/// have both registered. The first system's "counter" grains are `Counter`s.
+fn with_remote_nodes<F, U, R>(ports: (u16, u16), f: F) -> R
+where
+    F: FnOnce(ActorSystem, RemoteNode, Peer) -> U + Send + 'static,
+    U: Future<Item = R, Error = ()> + 'static,
+    R: Send + 'static,
+{
+    let (peer_system, peer) = start_peer(ports.1, ports.0);
+    let mut system = ActorSystem::new();
+    let system_clone = system.clone();
+    let (sender, receiver) = oneshot::channel();
+    system.on_startup(move || {
+        register_messages(&system_clone);
+        system_clone.register_factory(|_| Counter::new());
+        let node = RemoteNode::new(&system_clone, &context::handle(), &address(ports.0)).unwrap();
+        let fut = f(system_clone, node, peer).then(|result| {
+            Ok(sender.send(result).ok().unwrap())
+        });
+        context::handle().spawn(fut);
+    });
+    let result = receiver.wait();
+    system.stop();
+    peer_system.stop();
+    result.unwrap().unwrap()
+}
+
+#[test]
+fn test_remote_ref_calls_actor_api() {
+    let counts = with_remote_nodes((16601, 16602), |_, node, peer| {
+        let counter = node.actor_ref::<Counter>(&peer.address, peer.counter);
+        assert!(counter.is_remote());
+        counter.send_add(1);
+        counter.add(2).join(counter.count())
+    });
+    assert_eq!(counts, (3, 3));
+}
+
+#[test]
+fn test_nodes_call_each_other() {
+    let counts = with_remote_nodes((16603, 16604), |system, node, peer| {
+        let counter = system.grain::<Counter>(&"counter");
+        node.actor_ref::<Forwarder>(&peer.address, "forwarder".grain_id())
+            .forward_add(5)
+            .and_then(move |forwarded| counter.count().map(move |count| (forwarded, count)))
+    });
+    assert_eq!(counts, (5, 5));
+}
+
+#[test]
+fn test_remote_request_to_missing_actor_fails() {
+    let result = with_remote_nodes((16605, 16606), |_, node, peer| {
+        node.actor_ref::<Counter>(&peer.address, Uuid::new_v4())
+            .count()
+            .then(|result| Ok(result.is_err()))
+    });
+    assert!(result);
+}
+
+#[test]
+fn test_remote_request_times_out() {
+    let result = with_remote_nodes((16607, 16608), |_, node, peer| {
+        node.set_request_timeout(Duration::from_millis(100));
+        node.actor_ref::<Counter>(&peer.address, peer.counter)
+            .stall()
+            .then(|result| Ok(result.is_err()))
+    });
+    assert!(result);
+}
+
+#[test]
+fn test_request_to_unreachable_node_fails() {
+    let result = with_remote_nodes((16609, 16610), |_, node, _| {
+        node.actor_ref::<Counter>(&address(16611), Uuid::new_v4())
+            .count()
+            .then(|result| Ok(result.is_err()))
+    });
+    assert!(result);
+}
@@ -1,20 +1,120 @@
+use actor_trait::codegen_actor_trait_impl;
+use diagnostic::{Diagnostic, Result};
 use inflector::Inflector;
 use quote;
 use std::collections::HashMap;
 use syn;
 
-struct ActorMessageMethod {
+pub struct ActorMessageMethod {
     method: syn::ImplItem,
+    attribute: &'static str,
 }
 
 impl ActorMessageMethod {
-    pub fn new(item: syn::ImplItem) -> Self {
-        Self {
+    /// Wraps a method annotated with `attribute`, checking that its signature
+    /// is one we can generate a message for.
+    pub fn new(item: syn::ImplItem, attribute: &'static str) -> Result<Self> {
+        let message_method = Self {
             method: item,
+            attribute: attribute,
+        };
+        message_method.check_signature()?;
+        Ok(message_method)
+    }
+
+    fn error(&self, problem: &str) -> Diagnostic {
+        Diagnostic::new(format!(
+            "#[{}] method `{}` {}",
+            self.attribute,
+            self.method.ident,
+            problem
+        ))
+    }
+
+    fn check_signature(&self) -> Result<()> {
+        let sig = match self.method.node {
+            syn::ImplItemKind::Method(ref sig, _) => sig,
+            _ => {
+                return Err(Diagnostic::new(format!(
+                    "#[{}] must decorate a method, but `{}` is not one",
+                    self.attribute,
+                    self.method.ident
+                )))
+            }
+        };
+        if !sig.generics.lifetimes.is_empty() || !sig.generics.ty_params.is_empty() {
+            return Err(self.error("can't be generic; its arguments become a message struct"));
+        }
+        match sig.decl.inputs.first() {
+            Some(&syn::FnArg::SelfRef(..)) => (),
+            _ => return Err(self.error("must take `&mut self`")),
+        }
+        let unsupported_pattern = sig.decl.inputs.iter().any(|input| {
+            match *input {
+                syn::FnArg::Captured(syn::Pat::Ident(_, _, None), _) |
+                syn::FnArg::Captured(syn::Pat::Wild, _) => false,
+                syn::FnArg::Captured(..) => true,
+                _ => false,
+            }
+        });
+        if unsupported_pattern {
+            return Err(self.error(
+                "uses an unsupported argument pattern; bind the argument to a name \
+                 and destructure it in the method body",
+            ));
+        }
+        if let syn::FunctionRetTy::Ty(syn::Ty::Path(_, ref path)) = sig.decl.output {
+            if path.segments.last().unwrap().ident == "ActorResponse" {
+                return Err(self.error(
+                    "should declare the response type itself, e.g. `-> u64`; \
+                     the generated method returns `ActorResponse<u64>`",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Message handlers get `&mut self`; only hooks may get by with `&self`.
+    pub fn check_mut_receiver(&self) -> Result<()> {
+        match self.get_signature().decl.inputs.first() {
+            Some(&syn::FnArg::SelfRef(_, syn::Mutability::Mutable)) => Ok(()),
+            _ => Err(self.error("must take `&mut self`")),
+        }
+    }
+
+    pub fn check_no_arguments(&self) -> Result<()> {
+        if self.fields().is_empty() {
+            Ok(())
+        } else {
+            Err(self.error("can't take arguments besides `self`"))
         }
     }
 
-    fn message_name(&self, actor_name: &syn::Ident) -> syn::Ident {
+    /// The body has to evaluate to an `ActorResponse`, which in practice
+    /// means ending in an expression like `self.respond(..)` or
+    /// `self.done()` rather than a statement.
+    pub fn check_body(&self) -> Result<()> {
+        let returns_response = match self.get_block().stmts.last() {
+            Some(&syn::Stmt::Expr(_)) | Some(&syn::Stmt::Mac(_)) => true,
+            Some(&syn::Stmt::Semi(ref expr)) => {
+                match expr.node {
+                    syn::ExprKind::Ret(_) => true,
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+        if returns_response {
+            Ok(())
+        } else {
+            Err(self.error(
+                "must return an `ActorResponse`, e.g. end with `self.respond(value)` \
+                 or `self.done()`",
+            ))
+        }
+    }
+
+    pub fn message_name(&self, actor_name: &syn::Ident) -> syn::Ident {
         syn::Ident::from(format!(
             "{}Message{}",
             actor_name,
@@ -22,26 +122,159 @@ impl ActorMessageMethod {
         ))
     }
 
-    fn method_name(&self) -> syn::Ident {
+    pub fn method_name(&self) -> syn::Ident {
         self.method.ident.clone()
     }
 
-    pub fn message_struct(&self, actor_name: &syn::Ident) -> quote::Tokens {
+    pub fn message_struct(
+        &self,
+        actor_name: &syn::Ident,
+        generics: &syn::Generics,
+    ) -> quote::Tokens {
         let name = self.message_name(&actor_name);
-        let fields = self.fields();
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        let phantom = phantom_type(generics)
+            .map(|phantom| quote!{ pub #phantom, })
+            .unwrap_or_else(quote::Tokens::new);
+        let response_type = self.inner_return_type();
+        let fields = self.fields()
+            .into_iter()
+            .map(|field| {
+                syn::Field {
+                    vis: syn::Visibility::Public,
+                    ..field
+                }
+            })
+            .collect::<Vec<syn::Field>>();
         quote! {
-            pub struct #name(#(#fields,)*);
+            pub struct #name #generics (#(#fields,)* #phantom) #where_clause;
+
+            impl #impl_generics ActorMessage for #name #ty_generics #where_clause {
+                type Response = #response_type;
+            }
         }
     }
 
-    pub fn method(&self) -> quote::Tokens {
+    /// Serde impls and a message type id for the message struct, used by
+    /// `#[actor_impl(serializable)]`. Only called for non-generic actors.
+    pub fn serialization_impls(&self, actor_name: &syn::Ident) -> quote::Tokens {
+        let name = self.message_name(&actor_name);
+        let field_types = &self.fields()
+            .into_iter()
+            .map(|field| field.ty)
+            .collect::<Vec<_>>();
+        let indices = (0..field_types.len())
+            .map(|index| syn::Ident::from(index.to_string()))
+            .collect::<Vec<_>>();
+        let bindings = &(0..field_types.len())
+            .map(|index| syn::Ident::from(format!("field_{}", index)))
+            .collect::<Vec<_>>();
+        let positions = 0..field_types.len();
+        let field_count = field_types.len();
+        let response_type = self.inner_return_type();
+        let message_type_id = format!("{}.{}", actor_name, self.method_name());
+        let expecting = format!("the arguments of '{}'", message_type_id);
+        let assert_name = syn::Ident::from(format!(
+            "_assert_{}_serializable",
+            self.method_name()
+        ));
+        // Encoded as a tuple of the arguments. Their encoding goes through
+        // `MessageField` so that an argument serde can't encode is only
+        // reported by the assertion.
+        quote! {
+            impl akio_serde::Serialize for #name {
+                fn serialize<AkioSerializer>(
+                    &self,
+                    serializer: AkioSerializer,
+                ) -> ::std::result::Result<AkioSerializer::Ok, AkioSerializer::Error>
+                where
+                    AkioSerializer: akio_serde::Serializer,
+                {
+                    let mut tuple = serializer.serialize_tuple(#field_count)?;
+                    #(
+                        (&MessageField::<#field_types>::new())
+                            .encode_field(&mut tuple, &self.#indices)?;
+                    )*
+                    akio_serde::ser::SerializeTuple::end(tuple)
+                }
+            }
+
+            impl<'de> akio_serde::Deserialize<'de> for #name {
+                fn deserialize<AkioDeserializer>(
+                    deserializer: AkioDeserializer,
+                ) -> ::std::result::Result<Self, AkioDeserializer::Error>
+                where
+                    AkioDeserializer: akio_serde::Deserializer<'de>,
+                {
+                    struct AkioVisitor;
+
+                    impl<'de> akio_serde::de::Visitor<'de> for AkioVisitor {
+                        type Value = #name;
+
+                        fn expecting(
+                            &self,
+                            formatter: &mut ::std::fmt::Formatter,
+                        ) -> ::std::fmt::Result {
+                            formatter.write_str(#expecting)
+                        }
+
+                        fn visit_seq<AkioSeq>(
+                            self,
+                            mut seq: AkioSeq,
+                        ) -> ::std::result::Result<#name, AkioSeq::Error>
+                        where
+                            AkioSeq: akio_serde::de::SeqAccess<'de>,
+                        {
+                            #(
+                                let #bindings = match (&MessageField::<#field_types>::new())
+                                    .decode_field(&mut seq)?
+                                {
+                                    Some(value) => value,
+                                    None => {
+                                        return Err(akio_serde::de::Error::invalid_length(
+                                            #positions,
+                                            &self,
+                                        ))
+                                    }
+                                };
+                            )*
+                            Ok(#name(#(#bindings,)*))
+                        }
+                    }
+
+                    deserializer.deserialize_tuple(#field_count, AkioVisitor)
+                }
+            }
+
+            impl SerializableMessage for #name {
+                fn message_type_id() -> &'static str {
+                    #message_type_id
+                }
+            }
+
+            #[allow(dead_code)]
+            fn #assert_name() {
+                #(assert_serializable_field((&MessageField::<#field_types>::new()).check_field());)*
+                assert_serializable_field((&MessageField::<#response_type>::new()).check_field());
+            }
+        }
+    }
+
+    pub fn signature(&self) -> quote::Tokens {
         let method_name = self.method_name();
         let inner_return_type = self.inner_return_type();
         let return_type = quote!{ ActorResponse<#inner_return_type> };
         let inputs = self.get_signature().decl.inputs.clone();
+        quote! {
+            fn #method_name(#(#inputs,)*) -> #return_type
+        }
+    }
+
+    pub fn method(&self) -> quote::Tokens {
+        let signature = self.signature();
         let block = self.get_block();
         quote! {
-            fn #method_name(#(#inputs,)*) -> #return_type #block
+            #signature #block
         }
     }
 
@@ -79,29 +312,225 @@ impl ActorMessageMethod {
         }
     }
 
-    pub fn handler_impl(&self, actor_name: syn::Ident) -> quote::Tokens {
-        let message_name = self.message_name(&actor_name);
-        let response_type = self.inner_return_type();
-        let message_unpackers = self.fields()
+    pub fn message_unpackers(&self) -> Vec<syn::Ident> {
+        self.fields()
             .iter()
             .enumerate()
             .map(|(i, _)| syn::Ident::from(format!("{}", i)))
-            .collect::<Vec<syn::Ident>>();
+            .collect()
+    }
+
+    pub fn ref_arg_names(&self) -> Vec<syn::Ident> {
+        self.fields()
+            .iter()
+            .enumerate()
+            .map(|(i, _)| format!("arg_{}", i))
+            .map(syn::Ident::from)
+            .collect()
+    }
+
+    pub fn ref_args(&self) -> Vec<syn::BareFnArg> {
+        self.ref_arg_names()
+            .into_iter()
+            .zip(self.fields())
+            .map(|(arg_name, field)| {
+                syn::BareFnArg {
+                    name: Some(arg_name),
+                    ty: field.ty,
+                }
+            })
+            .collect()
+    }
+
+    pub fn handler_impl(
+        &self,
+        actor_name: syn::Ident,
+        tipe: &syn::Ty,
+        generics: &syn::Generics,
+    ) -> quote::Tokens {
+        let message_name = self.message_name(&actor_name);
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        let response_type = self.inner_return_type();
+        let message_unpackers = self.message_unpackers();
         let method_name = self.method_name();
+        let reentrant = if self.is_reentrant() {
+            quote!{ .reentrant() }
+        } else {
+            quote::Tokens::new()
+        };
         quote! {
-            impl MessageHandler<#message_name> for #actor_name {
+            impl #impl_generics MessageHandler<#message_name #ty_generics> for #tipe
+                #where_clause
+            {
                 type Response = #response_type;
 
                 #[allow(unused_variables)]
-                fn handle(&mut self, message: #message_name)
+                fn handle(&mut self, message: #message_name #ty_generics)
                     -> ActorResponse<Self::Response>
                 {
-                    self.#method_name(#(message.#message_unpackers,)*)
+                    self.#method_name(#(message.#message_unpackers,)*) #reentrant
+                }
+            }
+        }
+    }
+
+    /// The arguments given to the method's attribute, e.g. `reentrant` in
+    /// `#[actor_api(reentrant)]`.
+    fn attribute_arguments(&self) -> Vec<syn::NestedMetaItem> {
+        self.method
+            .attrs
+            .iter()
+            .filter(|attr| attr.value.name() == self.attribute)
+            .flat_map(|attr| {
+                match attr.value {
+                    syn::MetaItem::List(_, ref arguments) => arguments.clone(),
+                    _ => Vec::new(),
                 }
+            })
+            .collect()
+    }
+
+    fn has_attribute_word(&self, name: &str) -> bool {
+        self.attribute_arguments().iter().any(|argument| {
+            match *argument {
+                syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref word)) => *word == name,
+                _ => false,
             }
+        })
+    }
+
+    /// Handlers marked `#[actor_api(reentrant)]` let other messages
+    /// interleave while their continuations wait.
+    pub fn is_reentrant(&self) -> bool {
+        self.has_attribute_word("reentrant")
+    }
+
+    /// Messages of methods marked `#[actor_api(clone)]` implement `Clone`,
+    /// which `Broadcast` routers need.
+    fn is_cloneable(&self) -> bool {
+        self.has_attribute_word("clone")
+    }
+
+    /// The argument named by `#[actor_api(hash_key = "arg")]`, which
+    /// `ConsistentHash` routers route the message by.
+    fn hash_key(&self) -> Option<String> {
+        self.attribute_arguments()
+            .into_iter()
+            .filter_map(|argument| {
+                match argument {
+                    syn::NestedMetaItem::MetaItem(
+                        syn::MetaItem::NameValue(ref name, syn::Lit::Str(ref value, _)),
+                    ) if *name == "hash_key" => Some(value.clone()),
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    /// The position of the argument named `name` among the message fields.
+    fn field_position(&self, name: &str) -> Option<usize> {
+        self.get_signature()
+            .decl
+            .inputs
+            .iter()
+            .filter(|input| match **input {
+                syn::FnArg::Captured(..) | syn::FnArg::Ignored(_) => true,
+                _ => false,
+            })
+            .position(|input| match *input {
+                syn::FnArg::Captured(syn::Pat::Ident(_, ref ident, _), _) => *ident == name,
+                _ => false,
+            })
+    }
+
+    pub fn check_attribute_arguments(&self) -> Result<()> {
+        let unsupported = self.attribute_arguments()
+            .into_iter()
+            .filter(|argument| {
+                match *argument {
+                    syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref word)) => {
+                        *word != "reentrant" && *word != "clone"
+                    }
+                    syn::NestedMetaItem::MetaItem(
+                        syn::MetaItem::NameValue(ref name, syn::Lit::Str(..)),
+                    ) => *name != "hash_key",
+                    _ => true,
+                }
+            })
+            .count();
+        if unsupported != 0 {
+            return Err(self.error(
+                "has an unsupported argument; expected `reentrant`, `clone` or \
+                 `hash_key = \"<argument>\"`",
+            ));
+        }
+        match self.hash_key() {
+            Some(ref key) if self.field_position(key).is_none() => Err(self.error(&format!(
+                "has no argument named `{}` to use as its hash key",
+                key
+            ))),
+            _ => Ok(()),
         }
     }
 
+    /// `Clone` and `ConsistentHashKey` impls for the message struct, as
+    /// requested by the method's attribute, so that it can be sent through
+    /// `Broadcast` and `ConsistentHash` routers.
+    pub fn routing_impls(
+        &self,
+        actor_name: &syn::Ident,
+        generics: &syn::Generics,
+    ) -> quote::Tokens {
+        let name = self.message_name(&actor_name);
+        let (impl_generics, ty_generics, _) = generics.split_for_impl();
+        let where_predicates = &generics.where_clause.predicates;
+        let field_types = &self.fields()
+            .into_iter()
+            .map(|field| field.ty)
+            .collect::<Vec<_>>();
+        let mut impls = Vec::new();
+        if self.is_cloneable() {
+            let indices = (0..field_types.len())
+                .map(|index| syn::Ident::from(index.to_string()))
+                .collect::<Vec<_>>();
+            let phantom = phantom_type(generics)
+                .map(|_| quote!{ ::std::marker::PhantomData })
+                .unwrap_or_else(quote::Tokens::new);
+            impls.push(quote! {
+                impl #impl_generics Clone for #name #ty_generics
+                where
+                    #(#field_types: Clone,)*
+                    #(#where_predicates,)*
+                {
+                    fn clone(&self) -> Self {
+                        #name(#(self.#indices.clone(),)* #phantom)
+                    }
+                }
+            });
+        }
+        if let Some(key) = self.hash_key() {
+            let position = self.field_position(&key).expect("checked when parsed");
+            let key_type = &field_types[position];
+            let index = syn::Ident::from(position.to_string());
+            impls.push(quote! {
+                impl #impl_generics ConsistentHashKey for #name #ty_generics
+                where
+                    #key_type: ::std::hash::Hash,
+                    #(#where_predicates,)*
+                {
+                    type Key = u64;
+
+                    fn hash_key(&self) -> u64 {
+                        let mut hasher = ::std::collections::hash_map::DefaultHasher::new();
+                        ::std::hash::Hash::hash(&self.#index, &mut hasher);
+                        ::std::hash::Hasher::finish(&hasher)
+                    }
+                }
+            });
+        }
+        quote!{ #(#impls)* }
+    }
+
     pub fn future_return_type(&self) -> quote::Tokens {
         let return_type = self.inner_return_type();
         quote! {
@@ -109,6 +538,46 @@ impl ActorMessageMethod {
         }
     }
 
+    /// The item type of a method that answers with an `ActorStream<T>`.
+    pub fn stream_item_type(&self) -> Option<syn::Ty> {
+        let path = match self.get_signature().decl.output {
+            syn::FunctionRetTy::Ty(syn::Ty::Path(_, path)) => path,
+            _ => return None,
+        };
+        let segment = path.segments.last().unwrap();
+        if segment.ident != "ActorStream" {
+            return None;
+        }
+        match segment.parameters {
+            syn::PathParameters::AngleBracketed(ref parameters) => {
+                parameters.types.first().cloned()
+            }
+            _ => None,
+        }
+    }
+
+    /// What the `XxxRef` method returns: a stream of items for streaming
+    /// methods, otherwise a future of the response.
+    pub fn ref_return_type(&self) -> quote::Tokens {
+        match self.stream_item_type() {
+            Some(item_type) => {
+                quote! {
+                    Box<Stream<Item = #item_type, Error = ()> + Send>
+                }
+            }
+            None => self.future_return_type(),
+        }
+    }
+
+    /// Adapts `response`, a future of the method's response, to
+    /// `ref_return_type`.
+    pub fn ref_response(&self, response: quote::Tokens) -> quote::Tokens {
+        match self.stream_item_type() {
+            Some(_) => quote!{ Box::new(#response.flatten_stream()) },
+            None => quote!{ Box::new(#response) },
+        }
+    }
+
     pub fn inner_return_type(&self) -> quote::Tokens {
         match self.get_signature().decl.output {
             syn::FunctionRetTy::Default => quote!{ () },
@@ -119,7 +588,7 @@ impl ActorMessageMethod {
     pub fn ref_method_signatures(&self) -> quote::Tokens {
         let method_name = self.method_name();
         let send_method_name = syn::Ident::from(format!("send_{}", method_name.as_ref()));
-        let return_type = self.future_return_type();
+        let return_type = self.ref_return_type();
         let arg_names = &self.fields()
             .iter()
             .enumerate()
@@ -143,10 +612,14 @@ impl ActorMessageMethod {
         }
     }
 
-    pub fn ref_methods(&self, actor_name: &syn::Ident) -> quote::Tokens {
+    pub fn ref_methods(
+        &self,
+        actor_name: &syn::Ident,
+        generics: &syn::Generics,
+    ) -> quote::Tokens {
         let method_name = self.method_name();
         let send_method_name = syn::Ident::from(format!("send_{}", method_name.as_ref()));
-        let return_type = self.future_return_type();
+        let return_type = self.ref_return_type();
         let arg_names = &self.fields()
             .iter()
             .enumerate()
@@ -165,14 +638,20 @@ impl ActorMessageMethod {
             })
             .collect::<Vec<syn::BareFnArg>>();
         let message_name = self.message_name(actor_name);
+        let phantom = phantom_type(generics)
+            .map(|_| quote!{ ::std::marker::PhantomData })
+            .unwrap_or_else(quote::Tokens::new);
+        let response = self.ref_response(quote! {
+            self.request(#message_name(#(#arg_names,)* #phantom)).flatten()
+        });
         quote! {
             fn #method_name(&self, #(#args,)*) -> #return_type
             {
-                Box::new(self.request(#message_name(#(#arg_names,)*)).flatten())
+                #response
             }
 
             fn #send_method_name(&self, #(#args,)*) {
-                self.send(#message_name(#(#arg_names,)*))
+                self.send(#message_name(#(#arg_names,)* #phantom))
             }
         }
     }
@@ -199,17 +678,17 @@ impl ActorMessageMethod {
             .collect()
     }
 
-    fn get_signature(&self) -> syn::MethodSig {
+    pub fn get_signature(&self) -> syn::MethodSig {
         match self.method.node {
             syn::ImplItemKind::Method(ref sig, _) => sig.clone(),
-            _ => panic!("[actor_api] must decorate a method"),
+            _ => unreachable!("checked in ActorMessageMethod::new"),
         }
     }
 
     fn get_block(&self) -> syn::Block {
         match self.method.node {
             syn::ImplItemKind::Method(_, ref block) => block.clone(),
-            _ => panic!("[actor_api] must decorate a method"),
+            _ => unreachable!("checked in ActorMessageMethod::new"),
         }
     }
 }
@@ -221,10 +700,6 @@ enum HookType {
 }
 
 impl HookType {
-    pub fn has_marker(impl_item: &syn::ImplItem) -> bool {
-        Self::get(impl_item).is_some()
-    }
-
     pub fn get(impl_item: &syn::ImplItem) -> Option<Self> {
         if has_marker(impl_item, "on_start") {
             Some(HookType::OnStart)
@@ -236,19 +711,73 @@ impl HookType {
     }
 
     pub fn method_name(&self) -> syn::Ident {
+        syn::Ident::from(self.attribute())
+    }
+
+    pub fn all() -> [HookType; 2] {
+        [HookType::OnStart, HookType::OnStop]
+    }
+
+    /// The inherent method through which a part exposes this hook to the
+    /// primary block.
+    pub fn part_method_name(&self, part: &syn::Ident) -> syn::Ident {
+        syn::Ident::from(format!(
+            "_part_{}_{}",
+            part.as_ref().to_snake_case(),
+            self.attribute()
+        ))
+    }
+
+    pub fn attribute(&self) -> &'static str {
         match *self {
-            HookType::OnStart => syn::Ident::from("on_start"),
-            HookType::OnStop => syn::Ident::from("on_stop"),
+            HookType::OnStart => "on_start",
+            HookType::OnStop => "on_stop",
         }
     }
 }
 
+/// Message structs don't necessarily mention every type parameter of the
+/// actor, so generic actors get an extra marker field. `fn() -> T` keeps the
+/// message `Send` regardless of `T`.
+pub fn phantom_type(generics: &syn::Generics) -> Option<quote::Tokens> {
+    if generics.ty_params.is_empty() {
+        return None;
+    }
+    let params = generics.ty_params.iter().map(|param| &param.ident);
+    Some(quote!{ ::std::marker::PhantomData<fn() -> (#(#params,)*)> })
+}
+
+/// Actors and their messages cross threads and outlive the caller, so every
+/// type parameter picks up `Send + 'static` on top of the declared bounds.
+fn actor_generics(mut generics: syn::Generics) -> Result<syn::Generics> {
+    if !generics.lifetimes.is_empty() {
+        return Err(Diagnostic::new(
+            "#[actor_impl] does not support lifetime parameters; actors must be 'static",
+        ));
+    }
+    for param in &mut generics.ty_params {
+        param.bounds.push(syn::TyParamBound::Trait(
+            syn::PolyTraitRef {
+                bound_lifetimes: Vec::new(),
+                trait_ref: syn::Path::from("Send"),
+            },
+            syn::TraitBoundModifier::None,
+        ));
+        param
+            .bounds
+            .push(syn::TyParamBound::Region(syn::Lifetime::new("'static")));
+    }
+    Ok(generics)
+}
+
 struct ActorImpl {
-    // TODO: ignoring generics for now
-    _generics: syn::Generics,
+    block: ActorBlock,
+    serializable: bool,
+    name: syn::Ident,
+    generics: syn::Generics,
     tipe: syn::Ty,
     message_methods: Vec<ActorMessageMethod>,
-    hook_methods: HashMap<HookType, Vec<ActorMessageMethod>>,
+    hook_methods: HashMap<HookType, ActorMessageMethod>,
     rest: Vec<syn::ImplItem>,
 }
 
@@ -260,48 +789,134 @@ fn has_marker(impl_item: &syn::ImplItem, name: &str) -> bool {
 }
 
 impl ActorImpl {
-    fn new(generics: syn::Generics, tipe: syn::Ty, impl_items: Vec<syn::ImplItem>) -> Self {
+    fn new(
+        arguments: ActorArguments,
+        generics: syn::Generics,
+        tipe: syn::Ty,
+        impl_items: Vec<syn::ImplItem>,
+    ) -> Result<Self> {
+        let name = match tipe {
+            syn::Ty::Path(_, ref path) => path.segments.last().unwrap().ident.clone(),
+            _ => {
+                return Err(Diagnostic::new(
+                    "#[actor_impl] must be used on an impl block for a named type",
+                ))
+            }
+        };
+        if arguments.serializable && !generics.ty_params.is_empty() {
+            return Err(Diagnostic::new(format!(
+                "#[actor_impl(serializable)] can't be used on generic actor `{}`; its \
+                 messages need a single stable type id",
+                name
+            )));
+        }
         let mut message_methods = Vec::new();
         let mut hook_methods = HashMap::new();
         let mut rest = Vec::new();
-        impl_items.into_iter().for_each(|mut item| {
+        for item in impl_items {
             if has_marker(&item, "actor_api") {
-                message_methods.push(ActorMessageMethod::new(item));
-            } else if HookType::has_marker(&item) {
-                item.ident = syn::Ident::from(format!("_hook_{}", item.ident.as_ref()));
-
-                hook_methods
-                    .entry(HookType::get(&item).unwrap())
-                    .or_insert(Vec::new())
-                    .push(ActorMessageMethod::new(item))
+                let message_method = ActorMessageMethod::new(item, "actor_api")?;
+                message_method.check_attribute_arguments()?;
+                message_method.check_mut_receiver()?;
+                message_method.check_body()?;
+                if arguments.serializable && message_method.stream_item_type().is_some() {
+                    return Err(message_method.error(
+                        "returns a stream, which #[actor_impl(serializable)] can't encode",
+                    ));
+                }
+                message_methods.push(message_method);
+            } else if let Some(hook_type) = HookType::get(&item) {
+                if hook_methods.contains_key(&hook_type) {
+                    return Err(Diagnostic::new(format!(
+                        "duplicate #[{}] hook `{}` for `{}`; only one is allowed per \
+                         #[actor_impl] block",
+                        hook_type.attribute(),
+                        item.ident,
+                        name
+                    )));
+                }
+                let mut hook = ActorMessageMethod::new(item, hook_type.attribute())?;
+                hook.check_no_arguments()?;
+                hook.check_body()?;
+                hook.method.ident =
+                    syn::Ident::from(format!("_hook_{}", hook.method.ident.as_ref()));
+                hook_methods.insert(hook_type, hook);
             } else {
                 rest.push(item);
             }
-        });
-        Self {
-            _generics: generics,
+        }
+        Ok(Self {
+            block: arguments.block,
+            serializable: arguments.serializable,
+            name: name,
+            generics: actor_generics(generics)?,
             tipe: tipe,
             message_methods: message_methods,
             hook_methods: hook_methods,
             rest: rest,
-        }
+        })
     }
 
     fn name(&self) -> syn::Ident {
-        match self.tipe {
-            syn::Ty::Path(_, ref path) => path.segments.last().unwrap().ident.clone(),
-            _ => panic!("Invalid actor name"),
-        }
+        self.name.clone()
     }
 
     fn ref_name(&self) -> syn::Ident {
-        syn::Ident::from(format!("{}Ref", self.name().as_ref()))
+        match self.block {
+            ActorBlock::Primary(_) => syn::Ident::from(format!("{}Ref", self.name)),
+            ActorBlock::Part(ref part) => {
+                syn::Ident::from(format!(
+                    "{}{}Ref",
+                    self.name,
+                    part.as_ref().to_pascal_case()
+                ))
+            }
+        }
+    }
+
+    fn mod_name(&self) -> syn::Ident {
+        let actor_name = self.name.as_ref().to_snake_case();
+        match self.block {
+            ActorBlock::Primary(_) => syn::Ident::from(format!("impl_module_{}", actor_name)),
+            ActorBlock::Part(ref part) => {
+                syn::Ident::from(format!(
+                    "impl_module_{}_{}",
+                    actor_name,
+                    part.as_ref().to_snake_case()
+                ))
+            }
+        }
+    }
+
+    fn message_names(&self) -> Vec<syn::Ident> {
+        self.message_methods
+            .iter()
+            .map(|message_method| message_method.message_name(&self.name()))
+            .collect()
+    }
+
+    fn response_types(&self) -> Vec<quote::Tokens> {
+        self.message_methods
+            .iter()
+            .map(ActorMessageMethod::inner_return_type)
+            .collect()
     }
 
     fn messages(&self) -> Vec<quote::Tokens> {
         self.message_methods
             .iter()
-            .map(|message_method| message_method.message_struct(&self.name()))
+            .map(|message_method| {
+                message_method.message_struct(&self.name(), &self.generics)
+            })
+            .collect()
+    }
+
+    fn routing_impls(&self) -> Vec<quote::Tokens> {
+        self.message_methods
+            .iter()
+            .map(|message_method| {
+                message_method.routing_impls(&self.name(), &self.generics)
+            })
             .collect()
     }
 
@@ -315,99 +930,404 @@ impl ActorImpl {
     fn ref_methods(&self) -> Vec<quote::Tokens> {
         self.message_methods
             .iter()
-            .map(|message_method| message_method.ref_methods(&self.name()))
+            .map(|message_method| {
+                message_method.ref_methods(&self.name(), &self.generics)
+            })
+            .collect()
+    }
+
+    fn serialization_impls(&self) -> Vec<quote::Tokens> {
+        if !self.serializable {
+            return Vec::new();
+        }
+        self.message_methods
+            .iter()
+            .map(|message_method| message_method.serialization_impls(&self.name()))
             .collect()
     }
 
+    /// `register_messages`, or `register_<part>_messages` for a part, which
+    /// registers the block's messages with an actor system so that other
+    /// nodes can send them to the actor.
+    fn register_messages_method(&self) -> quote::Tokens {
+        if !self.serializable {
+            return quote::Tokens::new();
+        }
+        let method_name = match self.block {
+            ActorBlock::Primary(_) => syn::Ident::from("register_messages"),
+            ActorBlock::Part(ref part) => {
+                syn::Ident::from(format!("register_{}_messages", part.as_ref().to_snake_case()))
+            }
+        };
+        let message_names = self.message_names();
+        quote! {
+            pub fn #method_name(system: &ActorSystem) -> Result<()> {
+                #(system.register_actor_message::<Self, #message_names>()?;)*
+                Ok(())
+            }
+        }
+    }
+
     fn message_handler_impls(&self) -> Vec<quote::Tokens> {
         self.message_methods
             .iter()
-            .map(|m| m.handler_impl(self.name()))
+            .map(|m| m.handler_impl(self.name(), &self.tipe, &self.generics))
             .collect()
     }
 
     fn actor_impl(&self) -> Vec<quote::Tokens> {
         self.message_methods
             .iter()
-            .chain(self.hook_methods.iter().flat_map(|(_, methods)| methods))
+            .chain(self.hook_methods.values())
             .map(ActorMessageMethod::method)
             .chain(self.rest.iter().map(|x| quote!(#x)))
             .collect()
     }
 
+    /// Generics for the blanket `XxxRef` impl: the actor's own parameters
+    /// plus one for the referenced actor type.
+    fn ref_generics(&self) -> syn::Generics {
+        add_type_params(self.generics.clone(), &["AkioActor"])
+    }
+
+    /// Generics for the `XxxRef` impl on routers, which also take the
+    /// routing logic's type.
+    fn router_generics(&self) -> syn::Generics {
+        add_type_params(self.generics.clone(), &["AkioActor", "AkioRouting"])
+    }
+
+    /// For the primary block these are the `Actor` hooks, which run the
+    /// block's own hook and then those of its parts; `on_stop` runs them in
+    /// reverse. A part only contributes inherent methods for the primary
+    /// block to call.
     fn hook_methods(&self) -> Vec<quote::Tokens> {
-        self.hook_methods
+        HookType::all()
             .iter()
-            .map(|(hook_type, methods)| {
-                let hook_name = hook_type.method_name();
-                let method_calls = methods.iter().map(ActorMessageMethod::method_call);
-                quote! {
-                    fn #hook_name(&mut self) {
-                        #(#method_calls;)*
+            .filter_map(|hook_type| {
+                let own_call = self.hook_methods
+                    .get(hook_type)
+                    .map(|method| {
+                        let method_call = method.method_call();
+                        quote!{ #method_call; }
+                    })
+                    .unwrap_or_else(quote::Tokens::new);
+                match self.block {
+                    ActorBlock::Primary(ref parts) => {
+                        if !self.hook_methods.contains_key(hook_type) && parts.is_empty() {
+                            return None;
+                        }
+                        let hook_name = hook_type.method_name();
+                        let mut part_hooks = parts
+                            .iter()
+                            .map(|part| hook_type.part_method_name(part))
+                            .collect::<Vec<syn::Ident>>();
+                        let body = match *hook_type {
+                            HookType::OnStart => {
+                                quote! {
+                                    #own_call
+                                    #(self.#part_hooks();)*
+                                }
+                            }
+                            HookType::OnStop => {
+                                part_hooks.reverse();
+                                quote! {
+                                    #(self.#part_hooks();)*
+                                    #own_call
+                                }
+                            }
+                        };
+                        Some(quote! {
+                            fn #hook_name(&mut self) {
+                                #body
+                            }
+                        })
+                    }
+                    ActorBlock::Part(ref part) => {
+                        let hook_name = hook_type.part_method_name(part);
+                        Some(quote! {
+                            pub(crate) fn #hook_name(&mut self) {
+                                #own_call
+                            }
+                        })
                     }
                 }
             })
             .collect()
     }
+
+    /// The primary block defines a macro that accepts the parts it lists and
+    /// fails to compile for any other, which each part invokes, so that a
+    /// part whose hooks the primary block would never run is reported.
+    fn parts_check(&self) -> quote::Tokens {
+        let macro_name = parts_macro_name(&self.name());
+        match self.block {
+            ActorBlock::Primary(ref parts) => {
+                quote! {
+                    #[allow(unused_macros)]
+                    macro_rules! #macro_name {
+                        #((#parts, $($error:tt)*) => {};)*
+                        ($part:ident, $($error:tt)*) => { $($error)* };
+                    }
+                }
+            }
+            ActorBlock::Part(ref part) => {
+                let message = format!(
+                    "part `{}` of `{}` must be listed in the primary block's \
+                     #[actor_impl(parts(..))]",
+                    part,
+                    self.name()
+                );
+                // The error is passed in so that it's reported at the part.
+                quote! {
+                    #macro_name!(#part, compile_error!(#message););
+                }
+            }
+        }
+    }
+}
+
+fn parts_macro_name(actor_name: &syn::Ident) -> syn::Ident {
+    syn::Ident::from(format!(
+        "__akio_{}_parts",
+        actor_name.as_ref().to_snake_case()
+    ))
+}
+
+/// Which role an `#[actor_impl]` block plays for its actor.
+pub enum ActorBlock {
+    /// The block that implements `Actor`, along with the parts whose hooks
+    /// it runs. Written `#[actor_impl]` or `#[actor_impl(parts(admin, ..))]`.
+    Primary(Vec<syn::Ident>),
+    /// An extension block, written `#[actor_impl(part(admin))]`. It adds
+    /// messages and an `XxxAdminRef` trait but leaves `Actor` alone. The
+    /// primary block must list it in `parts(..)`.
+    Part(syn::Ident),
+}
+
+/// What `#[actor_impl(..)]` was given.
+pub struct ActorArguments {
+    pub block: ActorBlock,
+    /// Written `#[actor_impl(serializable)]`. The block's messages and
+    /// responses get byte encodings and stable message type ids.
+    pub serializable: bool,
+}
+
+impl ActorArguments {
+    pub fn parse(attribute: &str) -> Result<Self> {
+        let attribute = attribute.trim();
+        if attribute.is_empty() {
+            return Ok(Self {
+                block: ActorBlock::Primary(Vec::new()),
+                serializable: false,
+            });
+        }
+        let source = if attribute.starts_with('(') {
+            format!("#[actor_impl{}]", attribute)
+        } else {
+            format!("#[actor_impl({})]", attribute)
+        };
+        let arguments = match syn::parse_outer_attr(&source)?.value {
+            syn::MetaItem::List(_, arguments) => arguments,
+            _ => Vec::new(),
+        };
+        let mut parts = Vec::new();
+        let mut part = None;
+        let mut serializable = false;
+        for argument in arguments {
+            match argument {
+                syn::NestedMetaItem::MetaItem(syn::MetaItem::List(ref name, ref values))
+                    if *name == "parts" =>
+                {
+                    parts.extend(ActorBlock::words(values)?);
+                }
+                syn::NestedMetaItem::MetaItem(syn::MetaItem::List(ref name, ref values))
+                    if *name == "part" && values.len() == 1 =>
+                {
+                    part = ActorBlock::words(values)?.pop();
+                }
+                syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref word))
+                    if *word == "serializable" =>
+                {
+                    serializable = true;
+                }
+                _ => {
+                    return Err(Diagnostic::new(
+                        "unsupported #[actor_impl] argument; expected `parts(name, ..)`, \
+                         `part(name)` or `serializable`",
+                    ))
+                }
+            }
+        }
+        let block = match part {
+            Some(_) if !parts.is_empty() => {
+                return Err(Diagnostic::new(
+                    "#[actor_impl] can't take both `part(..)` and `parts(..)`; only the \
+                     primary block lists the actor's parts",
+                ))
+            }
+            Some(part) => ActorBlock::Part(part),
+            None => ActorBlock::Primary(parts),
+        };
+        Ok(Self {
+            block: block,
+            serializable: serializable,
+        })
+    }
 }
 
+impl ActorBlock {
+    fn words(values: &[syn::NestedMetaItem]) -> Result<Vec<syn::Ident>> {
+        values
+            .iter()
+            .map(|value| {
+                match *value {
+                    syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref word)) => {
+                        Ok(word.clone())
+                    }
+                    _ => {
+                        Err(Diagnostic::new(
+                            "#[actor_impl] part names must be plain identifiers",
+                        ))
+                    }
+                }
+            })
+            .collect()
+    }
+}
 
-pub fn codegen_actor_impl(ast: syn::Item) -> quote::Tokens {
+pub fn codegen_actor_impl(arguments: ActorArguments, ast: syn::Item) -> Result<quote::Tokens> {
     let actor = match ast.node {
         syn::ItemKind::Impl(_, _, generics, None, tipe, impl_items) => {
-            ActorImpl::new(generics, *tipe, impl_items)
+            ActorImpl::new(arguments, generics, *tipe, impl_items)?
+        }
+        syn::ItemKind::Impl(_, _, _, Some(ref trait_path), _, _)
+            if trait_path.segments.last().unwrap().ident == "Actor" =>
+        {
+            return Err(Diagnostic::new(
+                "#[actor_impl] generates the `Actor` impl itself; move the lifecycle \
+                 methods into the #[actor_impl] block and mark them #[on_start] or \
+                 #[on_stop]",
+            ))
+        }
+        syn::ItemKind::Impl(_, _, _, Some(trait_path), tipe, impl_items) => {
+            return codegen_actor_trait_impl(trait_path, *tipe, impl_items);
         }
-        syn::ItemKind::Impl(_, _, _, Some(_), _, _) => {
-            panic!("#[actor_impl] should be applied to an `impl ActorStruct {}` block")
+        _ => {
+            return Err(Diagnostic::new(
+                "#[actor_impl] can only be used on `impl` blocks",
+            ))
         }
-        _ => panic!("#[actor_impl] can only be used on `impl` blocks"),
     };
-    let actor_name = actor.name();
     let actor_ref_name = actor.ref_name();
     let messages = actor.messages();
+    let routing_impls = actor.routing_impls();
+    let message_names = &actor.message_names();
+    let response_types = actor.response_types();
     let ref_method_signatures = actor.ref_method_signatures();
-    let ref_methods = actor.ref_methods();
+    let ref_methods = &actor.ref_methods();
     let hook_methods = actor.hook_methods();
+    let parts_check = actor.parts_check();
     let message_handler_impls = actor.message_handler_impls();
     let actor_impl = actor.actor_impl();
-    let mod_name = syn::Ident::from(format!(
-        "impl_module_{}",
-        actor_name.as_ref().to_snake_case()
-    ));
-    quote!{
+    let serialization_impls = actor.serialization_impls();
+    let register_messages_method = actor.register_messages_method();
+    let tipe = &actor.tipe;
+    let generics = &actor.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let where_predicates = &generics.where_clause.predicates;
+    let handler_bounds = &message_names
+        .iter()
+        .zip(response_types)
+        .map(|(message_name, response_type)| {
+            quote!{ MessageHandler<#message_name #ty_generics, Response = #response_type> }
+        })
+        .collect::<Vec<_>>();
+    let routing_bounds = message_names
+        .iter()
+        .map(|message_name| quote!{ RoutingLogic<#message_name #ty_generics> })
+        .collect::<Vec<_>>();
+    let ref_generics = actor.ref_generics();
+    let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+    let router_generics = actor.router_generics();
+    let (router_impl_generics, _, _) = router_generics.split_for_impl();
+    let mod_name = actor.mod_name();
+    let (actor_trait_impl, part_hooks) = match actor.block {
+        ActorBlock::Primary(_) => {
+            let actor_trait_impl = quote! {
+                impl #impl_generics Actor for #tipe #where_clause {
+                    #(#hook_methods)*
+                }
+            };
+            (actor_trait_impl, Vec::new())
+        }
+        ActorBlock::Part(_) => (quote::Tokens::new(), hook_methods),
+    };
+    Ok(quote!{
+        #parts_check
+
         mod #mod_name {
-            pub trait #actor_ref_name {
+            use super::*;
+
+            pub trait #actor_ref_name #generics #where_clause {
                 #(#ref_method_signatures)*
             }
 
-            impl #actor_ref_name for ActorRef<#actor_name> {
+            impl #ref_impl_generics #actor_ref_name #ty_generics for ActorRef<AkioActor>
+            where
+                AkioActor: Actor #(+ #handler_bounds)*,
+                #(#where_predicates,)*
+            {
+                #(#ref_methods)*
+            }
+
+            impl #router_impl_generics #actor_ref_name #ty_generics
+                for Router<AkioActor, AkioRouting>
+            where
+                AkioActor: Actor #(+ #handler_bounds)*,
+                AkioRouting: 'static #(+ #routing_bounds)*,
+                #(#where_predicates,)*
+            {
                 #(#ref_methods)*
             }
 
             #(#messages)*
 
-            impl Actor for #actor_name {
-                #(#hook_methods)*
-            }
+            #(#routing_impls)*
+
+            #(#serialization_impls)*
+
+            #actor_trait_impl
 
-            impl #actor_name {
+            impl #impl_generics #tipe #where_clause {
                 #(#actor_impl)*
+                #(#part_hooks)*
+                #register_messages_method
             }
 
             #(#message_handler_impls)*
         }
-        pub use self::#mod_name::#actor_ref_name;
-    }
+        pub use self::#mod_name::{#actor_ref_name, #(#message_names),*};
+    })
 }
 
-pub fn codegen_actor_api(ast: syn::Item) -> quote::Tokens {
-    quote!{ #ast }
-}
-
-pub fn codegen_actor_on_start(ast: syn::Item) -> quote::Tokens {
-    quote!{ #ast }
+fn add_type_params(mut generics: syn::Generics, names: &[&str]) -> syn::Generics {
+    for name in names {
+        generics.ty_params.push(syn::TyParam {
+            attrs: Vec::new(),
+            ident: syn::Ident::from(*name),
+            bounds: Vec::new(),
+            default: None,
+        });
+    }
+    generics
 }
 
-pub fn codegen_actor_on_stop(ast: syn::Item) -> quote::Tokens {
-    quote!{ #ast }
+/// `#[actor_api]`, `#[on_start]` and `#[on_stop]` are markers consumed by the
+/// enclosing `#[actor_impl]`, so reaching their own expansion means they were
+/// used somewhere else.
+pub fn misplaced_marker(attribute: &str) -> Diagnostic {
+    Diagnostic::new(format!(
+        "#[{}] can only be used on methods inside an #[actor_impl] block",
+        attribute
+    ))
 }
@@ -0,0 +1,212 @@
+use actor::ActorMessageMethod;
+use diagnostic::{Diagnostic, Result};
+use quote;
+use syn;
+
+struct ActorTraitMethod {
+    message_method: ActorMessageMethod,
+    has_default: bool,
+}
+
+impl ActorTraitMethod {
+    fn new(item: syn::TraitItem) -> Result<Self> {
+        let (sig, maybe_block) = match item.node {
+            syn::TraitItemKind::Method(sig, maybe_block) => (sig, maybe_block),
+            _ => {
+                return Err(Diagnostic::new(format!(
+                    "#[actor_trait] can only contain methods, but `{}` is not one",
+                    item.ident
+                )))
+            }
+        };
+        let has_default = maybe_block.is_some();
+        let block = maybe_block.unwrap_or(syn::Block {
+            stmts: Vec::new(),
+        });
+        let impl_item = syn::ImplItem {
+            ident: item.ident,
+            vis: syn::Visibility::Inherited,
+            defaultness: syn::Defaultness::Final,
+            attrs: item.attrs,
+            node: syn::ImplItemKind::Method(sig, block),
+        };
+        let message_method = ActorMessageMethod::new(impl_item, "actor_trait")?;
+        message_method.check_mut_receiver()?;
+        if has_default {
+            message_method.check_body()?;
+        }
+        Ok(Self {
+            message_method: message_method,
+            has_default: has_default,
+        })
+    }
+
+    fn trait_method(&self) -> quote::Tokens {
+        if self.has_default {
+            self.message_method.method()
+        } else {
+            let signature = self.message_method.signature();
+            quote! { #signature; }
+        }
+    }
+
+    fn ref_methods(&self, trait_name: &syn::Ident) -> quote::Tokens {
+        let method_name = self.message_method.method_name();
+        let send_method_name = syn::Ident::from(format!("send_{}", method_name.as_ref()));
+        let return_type = self.message_method.ref_return_type();
+        let arg_names = &self.message_method.ref_arg_names();
+        let args = &self.message_method.ref_args();
+        let message_name = self.message_method.message_name(trait_name);
+        let response = self.message_method.ref_response(quote! {
+            self.#method_name.request(#message_name(#(#arg_names,)*))
+        });
+        quote! {
+            pub fn #method_name(&self, #(#args,)*) -> #return_type {
+                #response
+            }
+
+            pub fn #send_method_name(&self, #(#args,)*) {
+                self.#method_name.send(#message_name(#(#arg_names,)*))
+            }
+        }
+    }
+}
+
+/// Generates the messages for an actor interface, the interface trait itself
+/// and a `XxxRef` handle that can address any actor implementing it.
+pub fn codegen_actor_trait(ast: syn::Item) -> Result<quote::Tokens> {
+    let vis = ast.vis;
+    let trait_name = ast.ident;
+    let (supertraits, trait_items) = match ast.node {
+        syn::ItemKind::Trait(_, _, supertraits, trait_items) => (supertraits, trait_items),
+        _ => {
+            return Err(Diagnostic::new(
+                "#[actor_trait] can only be used on traits",
+            ))
+        }
+    };
+    let methods = trait_items
+        .into_iter()
+        .map(ActorTraitMethod::new)
+        .collect::<Result<Vec<ActorTraitMethod>>>()?;
+    let ref_name = syn::Ident::from(format!("{}Ref", trait_name.as_ref()));
+    let messages = methods
+        .iter()
+        .map(|m| {
+            m.message_method
+                .message_struct(&trait_name, &syn::Generics::default())
+        })
+        .collect::<Vec<quote::Tokens>>();
+    let message_names = &methods
+        .iter()
+        .map(|m| m.message_method.message_name(&trait_name))
+        .collect::<Vec<syn::Ident>>();
+    let response_types = &methods
+        .iter()
+        .map(|m| m.message_method.inner_return_type())
+        .collect::<Vec<quote::Tokens>>();
+    let method_names = &methods
+        .iter()
+        .map(|m| m.message_method.method_name())
+        .collect::<Vec<syn::Ident>>();
+    let trait_methods = methods
+        .iter()
+        .map(ActorTraitMethod::trait_method)
+        .collect::<Vec<quote::Tokens>>();
+    let ref_methods = methods
+        .iter()
+        .map(|m| m.ref_methods(&trait_name))
+        .collect::<Vec<quote::Tokens>>();
+    Ok(quote! {
+        #(#messages)*
+
+        #vis trait #trait_name: Actor
+            #(+ #supertraits)*
+            #(+ MessageHandler<#message_names, Response = #response_types>)*
+        {
+            #(#trait_methods)*
+        }
+
+        #[derive(Clone)]
+        #vis struct #ref_name {
+            id: Uuid,
+            #(#method_names: AskRecipient<#message_names, #response_types>,)*
+        }
+
+        impl #ref_name {
+            pub fn id(&self) -> Uuid {
+                self.id
+            }
+
+            #(#ref_methods)*
+        }
+
+        impl<A> From<ActorRef<A>> for #ref_name
+        where
+            A: Actor #(+ MessageHandler<#message_names, Response = #response_types>)*
+        {
+            fn from(actor_ref: ActorRef<A>) -> Self {
+                #ref_name {
+                    id: actor_ref.id(),
+                    #(#method_names: actor_ref.ask_recipient(),)*
+                }
+            }
+        }
+    })
+}
+
+/// Generates an `#[actor_trait]` implementation and the `MessageHandler`
+/// impls that route the trait's messages to it.
+pub fn codegen_actor_trait_impl(
+    trait_path: syn::Path,
+    tipe: syn::Ty,
+    impl_items: Vec<syn::ImplItem>,
+) -> Result<quote::Tokens> {
+    let trait_name = trait_path.segments.last().unwrap().ident.clone();
+    let methods = impl_items
+        .into_iter()
+        .map(|item| {
+            let method = ActorMessageMethod::new(item, "actor_impl")?;
+            method.check_mut_receiver()?;
+            method.check_body()?;
+            Ok(method)
+        })
+        .collect::<Result<Vec<ActorMessageMethod>>>()?;
+    let method_impls = methods
+        .iter()
+        .map(ActorMessageMethod::method)
+        .collect::<Vec<quote::Tokens>>();
+    let handler_impls = methods
+        .iter()
+        .map(|method| {
+            let mut message_path = trait_path.clone();
+            {
+                let segment = message_path.segments.last_mut().unwrap();
+                segment.ident = method.message_name(&trait_name);
+                segment.parameters = syn::PathParameters::none();
+            }
+            let response_type = method.inner_return_type();
+            let method_name = method.method_name();
+            let message_unpackers = method.message_unpackers();
+            quote! {
+                impl MessageHandler<#message_path> for #tipe {
+                    type Response = #response_type;
+
+                    #[allow(unused_variables)]
+                    fn handle(&mut self, message: #message_path)
+                        -> ActorResponse<Self::Response>
+                    {
+                        <Self as #trait_path>::#method_name(self, #(message.#message_unpackers,)*)
+                    }
+                }
+            }
+        })
+        .collect::<Vec<quote::Tokens>>();
+    Ok(quote! {
+        impl #trait_path for #tipe {
+            #(#method_impls)*
+        }
+
+        #(#handler_impls)*
+    })
+}
@@ -0,0 +1,40 @@
+use quote;
+use std::result;
+
+pub type Result<T> = result::Result<T, Diagnostic>;
+
+/// A problem with the input of one of our macros. These are reported to the
+/// user through `compile_error!` rather than by panicking inside the
+/// compiler.
+///
+/// Known limitation: these errors are not span-accurate. The input is
+/// round-tripped through a string for syn 0.11, which drops every span, so
+/// the compiler reports each error at the `#[actor_impl]` or `#[actor_trait]`
+/// attribute, never at the offending method or argument. Messages name the
+/// offending item to make up for it. Pointing at the item itself needs a
+/// span-aware parser (syn 0.12 on a newer compiler).
+#[derive(Debug)]
+pub struct Diagnostic {
+    message: String,
+}
+
+impl Diagnostic {
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+
+    pub fn into_tokens(self) -> quote::Tokens {
+        let message = self.message;
+        quote! {
+            compile_error!(#message);
+        }
+    }
+}
+
+impl From<String> for Diagnostic {
+    fn from(message: String) -> Self {
+        Diagnostic::new(message)
+    }
+}
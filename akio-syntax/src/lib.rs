@@ -1,5 +1,5 @@
 #![feature(proc_macro)]
-#![recursion_limit = "128"]
+#![recursion_limit = "256"]
 
 extern crate inflector;
 extern crate proc_macro;
@@ -9,38 +9,54 @@ extern crate syn;
 extern crate synom;
 
 mod actor;
+mod actor_trait;
+mod diagnostic;
 
+use diagnostic::Diagnostic;
 use proc_macro::TokenStream;
 
-#[proc_macro_attribute]
-pub fn actor_impl(_attribute: TokenStream, item: TokenStream) -> TokenStream {
+fn expand<F>(item: TokenStream, codegen: F) -> TokenStream
+where
+    F: FnOnce(syn::Item) -> diagnostic::Result<quote::Tokens>,
+{
     let source = item.to_string();
-    let impl_ast = syn::parse_item(&source).unwrap();
-    let tokens_out = actor::codegen_actor_impl(impl_ast);
+    let tokens_out = syn::parse_item(&source)
+        .map_err(Diagnostic::from)
+        .and_then(codegen)
+        .unwrap_or_else(Diagnostic::into_tokens);
     //println!("{}", tokens_out);
     tokens_out.parse().unwrap()
 }
 
 #[proc_macro_attribute]
-pub fn actor_api(_attribute: TokenStream, item: TokenStream) -> TokenStream {
-    let source = item.to_string();
-    let impl_ast = syn::parse_item(&source).unwrap();
-    let tokens_out = actor::codegen_actor_api(impl_ast);
-    tokens_out.parse().unwrap()
+pub fn actor_impl(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let arguments = actor::ActorArguments::parse(&attribute.to_string());
+    expand(item, |ast| actor::codegen_actor_impl(arguments?, ast))
 }
 
 #[proc_macro_attribute]
-pub fn on_start(_attribute: TokenStream, item: TokenStream) -> TokenStream {
-    let source = item.to_string();
-    let impl_ast = syn::parse_item(&source).unwrap();
-    let tokens_out = actor::codegen_actor_on_start(impl_ast);
-    tokens_out.parse().unwrap()
+pub fn actor_trait(_attribute: TokenStream, item: TokenStream) -> TokenStream {
+    expand(item, actor_trait::codegen_actor_trait)
 }
 
 #[proc_macro_attribute]
-pub fn on_stop(_attribute: TokenStream, item: TokenStream) -> TokenStream {
-    let source = item.to_string();
-    let impl_ast = syn::parse_item(&source).unwrap();
-    let tokens_out = actor::codegen_actor_on_stop(impl_ast);
-    tokens_out.parse().unwrap()
+pub fn actor_api(_attribute: TokenStream, _item: TokenStream) -> TokenStream {
+    expand_marker("actor_api")
+}
+
+#[proc_macro_attribute]
+pub fn on_start(_attribute: TokenStream, _item: TokenStream) -> TokenStream {
+    expand_marker("on_start")
+}
+
+#[proc_macro_attribute]
+pub fn on_stop(_attribute: TokenStream, _item: TokenStream) -> TokenStream {
+    expand_marker("on_stop")
+}
+
+fn expand_marker(attribute: &str) -> TokenStream {
+    actor::misplaced_marker(attribute)
+        .into_tokens()
+        .parse()
+        .unwrap()
 }
@@ -1,10 +1,43 @@
-use super::{context, create_actor, ActorRef};
-use futures::{Async, Future, IntoFuture, Poll};
+use super::{
+    context,
+    create_actor,
+    ActorRef,
+    ActorStream,
+    Continuation,
+    Headers,
+    PipeTo,
+    Responder,
+    Span,
+    StreamHandler,
+};
+use futures::{Async, Future, IntoFuture, Poll, Stream};
 use uuid::Uuid;
 
 pub enum ActorResponse<T> {
     Normal(Option<T>),
     Async(Box<Future<Item = T, Error = ()> + Send>),
+    /// The reply was taken by a `Responder` and will be sent through it.
+    Deferred,
+    /// The handler continues with access to the actor once a future
+    /// resolves. See `Actor::respond_async`.
+    Continuation(Continuation<T>),
+}
+
+impl<T> ActorResponse<T>
+where
+    T: Send + 'static,
+{
+    /// Lets the actor handle other messages while this response's
+    /// continuation, and any continuation it returns in turn, is waiting on
+    /// its future. Has no effect on other responses.
+    pub fn reentrant(self) -> Self {
+        match self {
+            ActorResponse::Continuation(continuation) => {
+                ActorResponse::Continuation(continuation.reentrant())
+            }
+            response => response,
+        }
+    }
 }
 
 impl<T> Future for ActorResponse<T> {
@@ -15,12 +48,19 @@ impl<T> Future for ActorResponse<T> {
         match *self {
             ActorResponse::Normal(ref mut value) => Ok(Async::Ready(value.take().unwrap())),
             ActorResponse::Async(ref mut f) => f.poll(),
+            ActorResponse::Deferred | ActorResponse::Continuation(_) => Err(()),
         }
     }
 }
 
+/// Implemented for messages generated by `#[actor_api]`, tying each message to
+/// the type its handler responds with.
+pub trait ActorMessage: Send + 'static {
+    type Response: Send + 'static;
+}
+
 pub trait MessageHandler<T> {
-    type Response: Send;
+    type Response: Send + 'static;
 
     fn handle(&mut self, message: T) -> ActorResponse<Self::Response>;
 }
@@ -57,6 +97,45 @@ pub trait Actor: Sized + Send + 'static {
         create_actor(context::system(), Uuid::new_v4(), self)
     }
 
+    /// A reference to this actor. Only available while the actor is
+    /// handling a message or running a hook.
+    fn self_ref(&self) -> ActorRef<Self> {
+        let span = Span::current().expect("self_ref called outside of an actor");
+        context::system()
+            .get_actor(&span.actor_id)
+            .expect("self_ref called outside of this actor")
+    }
+
+    /// Delivers the items of `stream` to this actor through its
+    /// `StreamHandler` impl until the stream ends or the actor stops.
+    fn add_stream<S>(&self, stream: S)
+    where
+        S: Stream + 'static,
+        S::Item: Send + 'static,
+        Self: StreamHandler<S::Item>,
+    {
+        self.self_ref().add_stream(stream)
+    }
+
+    /// Sends `to_message(result)` back to this actor once `future` resolves,
+    /// unless the actor has stopped by then. See `PipeTo::pipe_to`.
+    fn pipe_to_self<F, M, C>(&self, future: F, to_message: C)
+    where
+        F: IntoFuture,
+        F::Future: 'static,
+        Self: MessageHandler<M>,
+        M: Send + 'static,
+        C: FnOnce(Result<F::Item, F::Error>) -> M + 'static,
+    {
+        future.into_future().pipe_to(&self.self_ref(), to_message)
+    }
+
+    /// Headers of the message currently being handled, or `None` if no
+    /// message is being handled on this thread.
+    fn headers(&self) -> Option<Headers> {
+        Headers::current()
+    }
+
     fn done(&self) -> ActorResponse<()> {
         self.respond(())
     }
@@ -65,6 +144,55 @@ pub trait Actor: Sized + Send + 'static {
         ActorResponse::Normal(Some(v))
     }
 
+    /// Takes the obligation to reply to the message being handled, so it can
+    /// be completed later. The handler should return `self.deferred()`.
+    fn responder<T>(&self) -> Responder<T>
+    where
+        T: Send + 'static,
+    {
+        Responder::take_current()
+    }
+
+    fn deferred<T>(&self) -> ActorResponse<T> {
+        ActorResponse::Deferred
+    }
+
+    /// Sends `message` to `target` and lets it answer the original caller.
+    fn forward<B, M>(&self, target: &ActorRef<B>, message: M) -> ActorResponse<B::Response>
+    where
+        B: Actor + MessageHandler<M>,
+        M: Send + 'static,
+    {
+        target.send_with_responder(message, self.responder());
+        self.deferred()
+    }
+
+    /// Waits for `future` without blocking the actor, then runs
+    /// `continuation` with the actor's state and the future's result to
+    /// produce the response. The mailbox is paused until the continuation has
+    /// run; call `reentrant` on the response to let other messages interleave
+    /// instead.
+    fn respond_async<F, C, T>(&self, future: F, continuation: C) -> ActorResponse<T>
+    where
+        F: IntoFuture,
+        F::Future: Send + 'static,
+        F::Item: Send + 'static,
+        F::Error: Send + 'static,
+        C: FnOnce(&mut Self, Result<F::Item, F::Error>) -> ActorResponse<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        ActorResponse::Continuation(Continuation::new(future, continuation))
+    }
+
+    /// Answers with the items of `stream` as they become available.
+    fn respond_stream<S>(&self, stream: S) -> ActorResponse<ActorStream<S::Item>>
+    where
+        S: Stream + 'static,
+        S::Item: Send + 'static,
+    {
+        self.respond(ActorStream::from_stream(stream))
+    }
+
     fn respond_fut<F, T>(&self, v: F) -> ActorResponse<T>
     where
         F: IntoFuture<Item = T, Error = ()> + 'static,
@@ -1,18 +1,29 @@
 use super::{
+    context,
     Actor,
     ActorResponse,
+    ActorStopped,
     ActorSystem,
+    DeadLetter,
+    Envelope,
     Mailbox,
     MailboxMessage,
     MessageHandler,
+    Span,
     SystemMessage,
 };
+use super::actor_factory::activate_actor;
+use super::continuation::ContinuationMessage;
 use super::errors::*;
+use super::mailbox::UserMessageWrapper;
+use super::metrics::ActorMetrics;
+use super::util::type_name;
 use futures::sync::oneshot;
 use parking_lot::Mutex;
 use std::clone::Clone;
-use std::collections::VecDeque;
+use std::result;
 use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -36,15 +47,19 @@ impl ActorStatus {
     //*self == ActorStatus::Suspended
     //}
 
-    //pub fn is_terminated(&self) -> bool {
-    //*self == ActorStatus::Terminated
-    //}
+    pub fn is_terminated(&self) -> bool {
+        *self == ActorStatus::Terminated
+    }
 }
 
 pub(crate) struct ActorCellHandle<A> {
+    id: Uuid,
     // ActorSystem holds the only RCs. When the actor is stopped the pointer
     // will fail to upgrade.
     cell: Weak<ActorCell<A>>,
+    // Whether messages start the actor when it isn't running, as for
+    // grains. Otherwise only passivated actors are re-activated.
+    activates: bool,
 }
 
 impl<A> Clone for ActorCellHandle<A>
@@ -52,7 +67,11 @@ where
     A: Actor,
 {
     fn clone(&self) -> Self {
-        Self::new(self.cell.clone())
+        Self {
+            id: self.id,
+            cell: self.cell.clone(),
+            activates: self.activates,
+        }
     }
 }
 
@@ -60,42 +79,125 @@ impl<A> ActorCellHandle<A>
 where
     A: Actor,
 {
-    pub fn new(p_cell: Weak<ActorCell<A>>) -> Self {
+    pub fn new(id: Uuid, p_cell: Weak<ActorCell<A>>) -> Self {
         Self {
+            id: id,
             cell: p_cell,
+            activates: false,
         }
     }
 
+    /// A handle whose messages start the actor from its factory whenever it
+    /// isn't running.
+    pub fn activating(id: Uuid, p_cell: Weak<ActorCell<A>>) -> Self {
+        Self {
+            activates: true,
+            ..Self::new(id, p_cell)
+        }
+    }
+
+    /// Whether the actor is still running. A stopped actor's cell can
+    /// outlive it briefly while its last batch of messages is processed.
     pub fn exists(&self) -> bool {
-        self.cell.upgrade().is_some()
+        self.with_cell(|cell| !cell.is_terminated())
+            .unwrap_or(false)
     }
 
     pub fn id(&self) -> Uuid {
-        self.with_cell_unwrapped(|cell| cell.id.clone())
+        self.id
     }
 
     pub fn process_messages(&self, max_count: usize) -> usize {
-        self.with_cell_unwrapped(|cell| cell.process_messages(max_count))
+        self.with_cell_unwrapped(|cell| cell.process_messages(self, max_count))
+    }
+
+    pub fn mailbox_len(&self) -> usize {
+        self.with_cell(|cell| cell.mailbox_len()).unwrap_or(0)
     }
 
     pub fn enqueue_message<M>(
         &self,
-        message: M,
+        envelope: Envelope<M>,
         promise: Option<oneshot::Sender<ActorResponse<A::Response>>>,
     ) where
         A: MessageHandler<M>,
         M: Send + 'static,
     {
+        if let Err(message) = self.deliver(UserMessageWrapper::make(envelope, promise)) {
+            debug!("Enqueued dead message '{}' to '{}'", message.message_type(), self.id);
+            let dead_letter = DeadLetter::new(self.id, message.message_type());
+            context::maybe_system().map(|system| system.publish_dead_letter(dead_letter));
+        }
+    }
+
+    pub fn enqueue_user_message(&self, message: UserMessageWrapper<A>) {
+        if let Err(message) = self.deliver(message) {
+            debug!(
+                "Dropped message '{}' for stopped actor '{}'",
+                message.message_type(),
+                self.id
+            );
+        }
+    }
+
+    /// Puts `messages` ahead of those waiting for the actor. They are dropped
+    /// if it has stopped.
+    pub fn enqueue_user_messages_first(&self, messages: Vec<UserMessageWrapper<A>>) {
         let me = self.clone();
-        if let Err(e) = self.with_cell(|cell| cell.enqueue_message(me, message, promise)) {
-            println!("Enqueued dead message: {}", e);
+        if let Err(e) = self.with_cell(|cell| cell.enqueue_user_messages_first(me, messages)) {
+            debug!("Dropped messages for stopped actor '{}': {}", self.id, e);
+        }
+    }
+
+    /// Enqueues `message` for the running actor. A passivated actor, or any
+    /// actor for an activating handle, is re-activated first if a factory is
+    /// registered for its type. Activation uses the actor system of the
+    /// current thread, so messages sent from outside it to an actor that
+    /// isn't running are dead letters.
+    fn deliver(
+        &self,
+        message: UserMessageWrapper<A>,
+    ) -> result::Result<(), UserMessageWrapper<A>> {
+        let message = match self.cell.upgrade() {
+            Some(cell) => match cell.enqueue_user_message(self.clone(), message) {
+                Ok(()) => return Ok(()),
+                Err(message) => message,
+            },
+            None => message,
+        };
+        let system = match context::maybe_system() {
+            Some(system) => system,
+            None => return Err(message),
+        };
+        let cell = if self.activates || system.is_passivated::<A>(&self.id) {
+            activate_actor::<A>(&system, self.id)
+        } else {
+            // Another handle may have re-activated it.
+            system.running_cell::<A>(&self.id)
+        };
+        match cell {
+            Some(cell) => {
+                let handle = Self {
+                    cell: Arc::downgrade(&cell),
+                    ..self.clone()
+                };
+                cell.enqueue_user_message(handle, message)
+            }
+            None => Err(message),
         }
     }
 
     pub fn enqueue_system_message(&self, message: SystemMessage) {
         let me = self.clone();
         if let Err(e) = self.with_cell(|cell| cell.enqueue_system_message(me, message)) {
-            println!("Enqueued dead system message: {}", e);
+            debug!("Enqueued dead system message to '{}': {}", self.id, e);
+        }
+    }
+
+    pub fn enqueue_continuation(&self, continuation: ContinuationMessage<A>) {
+        let me = self.clone();
+        if let Err(e) = self.with_cell(|cell| cell.enqueue_continuation(me, continuation)) {
+            debug!("Dropped continuation for stopped actor '{}': {}", self.id, e);
         }
     }
 
@@ -108,6 +210,14 @@ where
         self.with_cell_unwrapped(|cell| cell.on_start())
     }
 
+    /// Passivates the actor if it has been idle for `timeout`. Returns how
+    /// long to wait before checking again, or `None` once the actor has
+    /// stopped.
+    pub fn passivate_if_idle(&self, timeout: Duration) -> Option<Duration> {
+        self.with_cell(|cell| cell.passivate_if_idle(self, timeout))
+            .unwrap_or(None)
+    }
+
     fn with_cell<F, R>(&self, f: F) -> Result<R>
     where
         F: FnOnce(Arc<ActorCell<A>>) -> R,
@@ -131,6 +241,9 @@ pub(crate) struct ActorCell<A> {
     mailbox: Mutex<Mailbox<A>>,
     status: Mutex<ActorStatus>,
     actor: Mutex<A>,
+    watchers: Mutex<Vec<oneshot::Sender<()>>>,
+    last_active: Mutex<Instant>,
+    metrics: ActorMetrics,
     system: ActorSystem,
 }
 
@@ -140,58 +253,133 @@ where
 {
     pub fn new(system: ActorSystem, id: Uuid, actor: A) -> Arc<ActorCell<A>> {
         let mailbox = Mutex::new(Mailbox::new());
+        let metrics = ActorMetrics::new(&system.metrics(), type_name::<A>());
+        metrics.actors.inc();
         let cell = Self {
             id: id,
             mailbox: mailbox,
+            metrics: metrics,
             system: system,
-            status: Mutex::new(ActorStatus::Idle),
+            // Not dispatched until it has started; messages sent meanwhile
+            // wait in the mailbox.
+            status: Mutex::new(ActorStatus::Scheduled),
             actor: Mutex::new(actor),
+            watchers: Mutex::new(Vec::new()),
+            last_active: Mutex::new(Instant::now()),
         };
         Arc::new(cell)
     }
 
-    pub fn process_messages(&self, max_count: usize) -> usize {
-        let message_batch = self.next_batch_to_process(max_count);
-        let count = message_batch.len();
-        message_batch
-            .into_iter()
-            .for_each(|message| self.process_message(message));
+    pub fn process_messages(&self, me: &ActorCellHandle<A>, max_count: usize) -> usize {
+        // Messages are taken one at a time since handling one may suspend
+        // the mailbox.
+        let mut count = 0;
+        while count < max_count {
+            match self.next_message() {
+                Some(message) => self.process_message(me, message),
+                None => break,
+            }
+            count += 1;
+        }
+        *self.last_active.lock() = Instant::now();
         count
     }
 
-    pub fn next_batch_to_process(&self, count: usize) -> VecDeque<MailboxMessage<A>> {
-        let mut mailbox = self.mailbox.lock();
-        let mut v = VecDeque::with_capacity(count);
-        for _ in 0..count {
-            match mailbox.pop() {
-                Some(message) => v.push_back(message),
-                None => return v,
+    fn next_message(&self) -> Option<MailboxMessage<A>> {
+        let message = self.mailbox.lock().pop();
+        if message.is_some() {
+            self.metrics.mailbox_depth.dec();
+        }
+        message
+    }
+
+    /// Fails, handing back `message`, once the actor has stopped.
+    pub fn enqueue_user_message(
+        &self,
+        me: ActorCellHandle<A>,
+        message: UserMessageWrapper<A>,
+    ) -> result::Result<(), UserMessageWrapper<A>> {
+        {
+            // Stopping marks the actor terminated under the mailbox lock, so
+            // nothing is enqueued after its mailbox has been drained.
+            let mut mailbox = self.mailbox.lock();
+            if self.is_terminated() {
+                return Err(message);
             }
+            mailbox.push_user_message(message);
         }
-        v
+        self.metrics.mailbox_depth.inc();
+        self.dispatch(me);
+        Ok(())
     }
 
-    pub fn enqueue_message<M>(
+    pub fn enqueue_user_messages_first(
         &self,
         me: ActorCellHandle<A>,
-        message: M,
-        promise: Option<oneshot::Sender<ActorResponse<A::Response>>>,
-    ) where
-        A: MessageHandler<M>,
-        M: Send + 'static,
-    {
-        self.mailbox.lock().push(message, promise);
+        messages: Vec<UserMessageWrapper<A>>,
+    ) {
+        let count = messages.len();
+        {
+            let mut mailbox = self.mailbox.lock();
+            if self.is_terminated() {
+                return;
+            }
+            mailbox.push_user_messages_first(messages);
+        }
+        self.metrics.mailbox_depth.add(count as isize);
         self.dispatch(me);
     }
 
     pub fn enqueue_system_message(&self, me: ActorCellHandle<A>, message: SystemMessage) {
         self.mailbox.lock().push_system_message(message);
+        self.metrics.mailbox_depth.inc();
+        self.dispatch(me);
+    }
+
+    pub fn enqueue_continuation(
+        &self,
+        me: ActorCellHandle<A>,
+        continuation: ContinuationMessage<A>,
+    ) {
+        self.mailbox.lock().push_continuation(continuation);
+        self.metrics.mailbox_depth.inc();
         self.dispatch(me);
     }
 
+    pub fn mailbox_len(&self) -> usize {
+        self.mailbox.lock().len()
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.status.lock().is_terminated()
+    }
+
+    fn passivate_if_idle(&self, me: &ActorCellHandle<A>, timeout: Duration) -> Option<Duration> {
+        if self.is_terminated() {
+            return None;
+        }
+        let idle = {
+            let mailbox = self.mailbox.lock();
+            let waiting = mailbox.len() > 0 || mailbox.is_suspended();
+            if waiting || !self.status.lock().is_idle() {
+                Duration::from_secs(0)
+            } else {
+                self.last_active.lock().elapsed()
+            }
+        };
+        if idle < timeout {
+            return Some(timeout - idle);
+        }
+        self.enqueue_system_message(me.clone(), SystemMessage::Passivate);
+        Some(timeout)
+    }
+
     pub fn set_idle_or_dispatch(&self, me: ActorCellHandle<A>) {
         let mailbox = self.mailbox.lock();
-        if mailbox.is_empty() {
+        if self.is_terminated() {
+            return;
+        }
+        if !mailbox.has_ready() {
             self.set_status(ActorStatus::Idle);
         } else {
             self.system.dispatch(me)
@@ -206,29 +394,135 @@ where
         self.system.dispatch(cell);
     }
 
-    fn process_message(&self, message: MailboxMessage<A>) {
+    fn process_message(&self, me: &ActorCellHandle<A>, message: MailboxMessage<A>) {
+        if self.status.lock().is_terminated() {
+            return self.dead_letter(message);
+        }
         match message {
-            MailboxMessage::User(mut inner) => inner.handle(&mut self.actor.lock()),
-            MailboxMessage::System(inner) => self.handle_system_message(inner),
+            MailboxMessage::User(mut inner) => {
+                let _span = self.span(inner.message_type()).enter();
+                let start = Instant::now();
+                if inner.handle(&mut self.actor.lock(), me) {
+                    self.mailbox.lock().suspend();
+                }
+                self.metrics.processing_time.observe(start.elapsed());
+            }
+            MailboxMessage::Continuation(continuation) => {
+                let _span = self.span(continuation.message_type()).enter();
+                if !continuation.is_reentrant() {
+                    self.mailbox.lock().resume();
+                }
+                if continuation.resume(&mut self.actor.lock()) {
+                    self.mailbox.lock().suspend();
+                }
+            }
+            MailboxMessage::System(inner) => {
+                let _span = self.span(inner.name()).enter();
+                self.handle_system_message(inner)
+            }
         }
     }
 
     fn handle_system_message(&self, system_message: SystemMessage) {
         match system_message {
             SystemMessage::Stop(promise) => {
-                self.set_status(ActorStatus::Terminated);
-                self.actor.lock().on_stop();
-                let _ = self.system.deregister_actor::<A>(&self.id);
-                promise.send(()).expect("failed to stop");
+                self.set_status_locked(ActorStatus::Terminated);
+                self.terminate();
+                // The caller may have dropped the `stop()` future.
+                let _ = promise.send(());
+            }
+            SystemMessage::Passivate => {
+                {
+                    // Messages may have arrived since passivation was
+                    // requested.
+                    let mailbox = self.mailbox.lock();
+                    if mailbox.len() > 0 || mailbox.is_suspended() {
+                        return;
+                    }
+                    // Before it stops accepting messages, so that senders
+                    // re-activate it.
+                    self.system.set_passivated::<A>(self.id);
+                    self.set_status(ActorStatus::Terminated);
+                }
+                debug!("Passivating idle actor '{}'", self.id);
+                self.terminate();
+            }
+            SystemMessage::Watch(watcher) => self.watchers.lock().push(watcher),
+        }
+    }
+
+    /// Stops the actor once it has been marked terminated.
+    fn terminate(&self) {
+        self.actor.lock().on_stop();
+        self.system.deregister_actor_cell(&self.id, self);
+        self.metrics.actors.dec();
+        // Before pending responses are dropped, so that messages the actor
+        // didn't finish stay logged.
+        for mailbox in self.system.durable_mailboxes::<A>() {
+            mailbox.forget(&self.id);
+        }
+        let remaining = self.mailbox.lock().drain();
+        self.metrics.mailbox_depth.add(-(remaining.len() as isize));
+        remaining
+            .into_iter()
+            .for_each(|message| self.dead_letter(message));
+        self.watchers
+            .lock()
+            .drain(..)
+            .for_each(|watcher| {
+                let _ = watcher.send(());
+            });
+        self.system.publish(ActorStopped {
+            actor_id: self.id,
+            actor_type: type_name::<A>(),
+        });
+    }
+
+    fn dead_letter(&self, message: MailboxMessage<A>) {
+        if let MailboxMessage::User(inner) = message {
+            let dead_letter = DeadLetter::new(self.id, inner.message_type());
+            self.system.publish_dead_letter(dead_letter);
+        }
+    }
+
+    /// Runs `recover` on the actor before it starts. If it fails, the actor
+    /// is stopped and the messages sent to it so far become dead letters.
+    pub fn recover<F>(&self, recover: F) -> bool
+    where
+        F: FnOnce(&mut A) -> Result<()>,
+    {
+        let result = {
+            let _span = self.span("recover").enter();
+            recover(&mut self.actor.lock())
+        };
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                error!("Failed to recover '{}': {}", self.id, e);
+                self.set_status_locked(ActorStatus::Terminated);
+                self.terminate();
+                false
             }
         }
     }
 
     pub fn on_start(&self) {
+        let _span = self.span("on_start").enter();
         self.actor.lock().on_start();
     }
 
+    fn span(&self, message_type: &'static str) -> Span {
+        Span::new(self.id, type_name::<A>(), message_type)
+    }
+
     pub fn set_status(&self, status: ActorStatus) {
         *self.status.lock() = status;
     }
+
+    /// Sets the status while holding the mailbox lock, which enqueuing
+    /// messages also takes.
+    fn set_status_locked(&self, status: ActorStatus) {
+        let _mailbox = self.mailbox.lock();
+        self.set_status(status);
+    }
 }
@@ -1,14 +1,161 @@
-use super::{Actor, ActorCell, ActorCellHandle, ActorRef, ActorSystem};
+use super::{context, Actor, ActorCell, ActorCellHandle, ActorRef, ActorStarted, ActorSystem};
+use super::durable_mailbox::DurableMailbox;
+use super::errors::*;
+use super::util::type_name;
+use futures::future::{self, Loop};
+use futures::prelude::*;
+use std::collections::HashSet;
+use std::marker::PhantomData;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio_core::reactor::Timeout;
+use typemap::Key;
 use uuid::Uuid;
 
+pub(crate) type Factory<A> = Arc<Fn(Uuid) -> A + Send + Sync>;
+
+pub(crate) struct FactoryKey<A> {
+    _actor: PhantomData<A>,
+}
+
+impl<A> Key for FactoryKey<A>
+where
+    A: Actor,
+{
+    type Value = Factory<A>;
+}
+
+/// Restores an actor's state before it starts.
+pub(crate) type Recovery<A> = Arc<Fn(&mut A, &ActorSystem) -> Result<()> + Send + Sync>;
+
+pub(crate) struct RecoveryKey<A> {
+    _actor: PhantomData<A>,
+}
+
+impl<A> Key for RecoveryKey<A>
+where
+    A: Actor,
+{
+    type Value = Recovery<A>;
+}
+
+pub(crate) struct PassivationKey<A> {
+    _actor: PhantomData<A>,
+}
+
+impl<A> Key for PassivationKey<A>
+where
+    A: Actor,
+{
+    type Value = Duration;
+}
+
+/// Ids of passivated actors, which are re-activated by the next message
+/// sent to them.
+pub(crate) struct PassivatedKey<A> {
+    _actor: PhantomData<A>,
+}
+
+impl<A> Key for PassivatedKey<A>
+where
+    A: Actor,
+{
+    type Value = HashSet<Uuid>;
+}
+
 pub fn create_actor<A>(system: ActorSystem, id: Uuid, actor: A) -> ActorRef<A>
 where
     A: Actor + 'static,
 {
     let actor_cell_p = ActorCell::new(system.clone(), id, actor);
-    let handle = ActorCellHandle::new(Arc::downgrade(&actor_cell_p));
+    // Registered first so that the actor can look itself up from on_start.
+    system.register_actor(id, actor_cell_p.clone());
+    ActorRef::new(start_actor(&system, id, &actor_cell_p))
+}
+
+/// Returns the running actor with `id`, creating it with the factory
+/// registered for `A` if it isn't running. Of several concurrent activations
+/// of the same id, only one actor is started.
+pub(crate) fn activate_actor<A>(system: &ActorSystem, id: Uuid) -> Option<Arc<ActorCell<A>>>
+where
+    A: Actor,
+{
+    if let Some(cell) = system.running_cell::<A>(&id) {
+        return Some(cell);
+    }
+    let factory = match system.factory::<A>() {
+        Some(factory) => factory,
+        None => return None,
+    };
+    let actor = factory(id);
+    let (cell, created) = system.register_actor_if_absent(id, || {
+        ActorCell::new(system.clone(), id, actor)
+    });
+    if created {
+        debug!("Activated '{}' with id '{}'", type_name::<A>(), id);
+        system.clear_passivated::<A>(&id);
+        start_actor(system, id, &cell);
+    }
+    Some(cell)
+}
+
+fn start_actor<A>(system: &ActorSystem, id: Uuid, cell: &Arc<ActorCell<A>>) -> ActorCellHandle<A>
+where
+    A: Actor,
+{
+    let handle = ActorCellHandle::new(id, Arc::downgrade(cell));
+    if let Some(recovery) = system.recovery::<A>() {
+        if !cell.recover(|actor| recovery(actor, system)) {
+            return handle;
+        }
+    }
+    for mailbox in system.durable_mailboxes::<A>() {
+        if let Err(e) = DurableMailbox::replay(&mailbox, &handle) {
+            error!(
+                "Failed to replay '{}' messages to '{}': {}",
+                mailbox.message_type_name(),
+                id,
+                e
+            );
+        }
+    }
     handle.on_start();
-    system.register_actor(id, actor_cell_p);
-    ActorRef::new(handle)
+    system.publish(ActorStarted {
+        actor_id: id,
+        actor_type: type_name::<A>(),
+    });
+    // Messages sent while on_start ran have been waiting in the mailbox.
+    handle.set_idle_or_dispatch();
+    if let Some(timeout) = system.passivation_timeout::<A>() {
+        schedule_passivation(handle.clone(), timeout);
+    }
+    handle
+}
+
+fn schedule_passivation<A>(handle: ActorCellHandle<A>, timeout: Duration)
+where
+    A: Actor,
+{
+    let reactor = match context::maybe_handle() {
+        Some(reactor) => reactor,
+        None => {
+            warn!(
+                "'{}' is only passivated when started within the actor system",
+                type_name::<A>()
+            );
+            return;
+        }
+    };
+    let timer_reactor = reactor.clone();
+    let passivation = future::loop_fn(timeout, move |delay| {
+        let handle = handle.clone();
+        Timeout::new(delay, &timer_reactor)
+            .expect("failed to create timeout")
+            .map_err(|e| error!("Passivation timer failed: {}", e))
+            .map(move |_| match handle.passivate_if_idle(timeout) {
+                Some(remaining) => Loop::Continue(remaining),
+                None => Loop::Break(()),
+            })
+    });
+    reactor.spawn(passivation);
 }
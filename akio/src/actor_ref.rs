@@ -1,11 +1,51 @@
-use super::{Actor, ActorCellHandle, ActorResponse, MessageHandler, SystemMessage};
+use super::{
+    context,
+    Actor,
+    ActorCellHandle,
+    ActorResponse,
+    AskRecipient,
+    Envelope,
+    Headers,
+    MessageHandler,
+    Recipient,
+    Responder,
+    StreamHandler,
+    SystemMessage,
+};
+use super::durable_mailbox::DurableMailbox;
+use super::errors::*;
+use super::mailbox::UserMessageWrapper;
+use super::remote::RemoteActor;
+use super::util::type_name;
+use futures::future::{self, Either};
 use futures::prelude::*;
 use futures::sync::oneshot;
+use serde::Serialize;
 use std::clone::Clone;
+use std::time::Duration;
+use tokio_core::reactor::Timeout;
 use uuid::Uuid;
 
 pub struct ActorRef<A> {
-    cell: ActorCellHandle<A>,
+    location: ActorLocation<A>,
+    headers: Option<Headers>,
+}
+
+enum ActorLocation<A> {
+    Local(ActorCellHandle<A>),
+    Remote(RemoteActor),
+}
+
+impl<A> Clone for ActorLocation<A>
+where
+    A: Actor,
+{
+    fn clone(&self) -> Self {
+        match *self {
+            ActorLocation::Local(ref cell) => ActorLocation::Local(cell.clone()),
+            ActorLocation::Remote(ref remote) => ActorLocation::Remote(remote.clone()),
+        }
+    }
 }
 
 impl<A> Clone for ActorRef<A>
@@ -13,7 +53,10 @@ where
     A: Actor,
 {
     fn clone(&self) -> Self {
-        Self::new(self.cell.clone())
+        Self {
+            location: self.location.clone(),
+            headers: self.headers.clone(),
+        }
     }
 }
 
@@ -23,46 +66,275 @@ where
 {
     pub(crate) fn new(cell: ActorCellHandle<A>) -> Self {
         Self {
-            cell: cell,
+            location: ActorLocation::Local(cell),
+            headers: None,
         }
     }
 
+    /// A ref to an actor in another actor system. It is used the same way
+    /// as a local ref, but only sends messages registered with the
+    /// system's `SerializerRegistry`, and can't stop, watch or stream to the
+    /// actor.
+    pub fn remote(actor: RemoteActor) -> Self {
+        Self {
+            location: ActorLocation::Remote(actor),
+            headers: None,
+        }
+    }
+
+    pub fn is_remote(&self) -> bool {
+        match self.location {
+            ActorLocation::Local(_) => false,
+            ActorLocation::Remote(_) => true,
+        }
+    }
+
+    /// Returns a ref that attaches `headers` to every message it sends,
+    /// including those sent through generated `XxxRef` methods.
+    pub fn with_headers(&self, headers: Headers) -> Self {
+        Self {
+            location: self.location.clone(),
+            headers: Some(headers),
+        }
+    }
+
+    /// Always true for remote actors, which can't be checked without
+    /// messaging them.
     pub fn exists(&self) -> bool {
-        self.cell.exists()
+        match self.location {
+            ActorLocation::Local(ref cell) => cell.exists(),
+            ActorLocation::Remote(_) => true,
+        }
     }
 
     pub fn id(&self) -> Uuid {
-        self.cell.id()
+        match self.location {
+            ActorLocation::Local(ref cell) => cell.id(),
+            ActorLocation::Remote(ref remote) => remote.id(),
+        }
+    }
+
+    /// Number of messages waiting to be processed by the actor. Always 0 for
+    /// remote actors.
+    pub fn mailbox_len(&self) -> usize {
+        match self.location {
+            ActorLocation::Local(ref cell) => cell.mailbox_len(),
+            ActorLocation::Remote(_) => 0,
+        }
     }
 
     pub fn request<T>(
         &self,
         message: T,
     ) -> impl Future<Item = ActorResponse<A::Response>, Error = ()>
+    where
+        A: MessageHandler<T>,
+        T: Send + 'static,
+    {
+        self.request_envelope(self.envelope(message))
+    }
+
+    pub fn request_envelope<T>(
+        &self,
+        envelope: Envelope<T>,
+    ) -> impl Future<Item = ActorResponse<A::Response>, Error = ()>
     where
         A: MessageHandler<T>,
         T: Send + 'static,
     {
         let (promise, future) = oneshot::channel();
-        self.cell.enqueue_message(message, Some(promise));
+        self.enqueue_message(envelope, Some(promise));
         future.map_err(|_| ())
     }
 
+    /// Like `request`, but fails if no response arrives within `timeout`.
+    /// Timeouts are counted in the `akio_ask_timeouts_total` metric.
+    pub fn request_with_timeout<T>(
+        &self,
+        message: T,
+        timeout: Duration,
+    ) -> impl Future<Item = ActorResponse<A::Response>, Error = ()>
+    where
+        A: MessageHandler<T>,
+        T: Send + 'static,
+    {
+        let system = context::system();
+        let timer = Timeout::new(timeout, &context::handle()).expect("failed to create timeout");
+        self.request(message)
+            .select2(timer)
+            .then(move |result| match result {
+                Ok(Either::A((response, _))) => Ok(response),
+                Ok(Either::B(_)) => {
+                    system
+                        .metrics()
+                        .counter("akio_ask_timeouts_total", &[("actor_type", type_name::<A>())])
+                        .inc();
+                    Err(())
+                }
+                Err(_) => Err(()),
+            })
+    }
+
     pub fn send<T>(&self, message: T)
     where
         A: MessageHandler<T>,
         T: Send + 'static,
     {
-        self.cell.enqueue_message(message, None);
+        self.send_envelope(self.envelope(message))
     }
 
-    fn system_send(&self, message: SystemMessage) {
-        self.cell.enqueue_system_message(message)
+    /// Like `send`, but the message is first logged by the durable mailbox
+    /// registered for `A` and `T`, and is delivered again if the actor stops
+    /// before handling it. Returns once the message is logged.
+    pub fn send_durable<T>(&self, message: T) -> Result<()>
+    where
+        A: MessageHandler<T>,
+        T: Serialize + Send + 'static,
+    {
+        let cell = match self.location {
+            ActorLocation::Local(ref cell) => cell,
+            ActorLocation::Remote(_) => bail!(ErrorKind::RemoteActor("send_durable")),
+        };
+        let mailbox = context::system()
+            .durable_mailbox::<A, T>()
+            .ok_or_else(|| ErrorKind::NoDurableMailbox(type_name::<A>(), type_name::<T>()))?;
+        DurableMailbox::send(&mailbox, cell, self.envelope(message))
     }
 
+    pub fn send_envelope<T>(&self, envelope: Envelope<T>)
+    where
+        A: MessageHandler<T>,
+        T: Send + 'static,
+    {
+        self.enqueue_message(envelope, None);
+    }
+
+    /// A type-erased handle that accepts messages of type `T`.
+    pub fn recipient<T>(&self) -> Recipient<T>
+    where
+        A: MessageHandler<T>,
+        T: Send + 'static,
+    {
+        Recipient::from(self.clone())
+    }
+
+    /// A type-erased handle that accepts requests of type `T`.
+    pub fn ask_recipient<T>(&self) -> AskRecipient<T, A::Response>
+    where
+        A: MessageHandler<T>,
+        T: Send + 'static,
+    {
+        AskRecipient::from(self.clone())
+    }
+
+    /// Sends `message` and lets this actor answer through `responder`.
+    pub fn send_with_responder<T>(&self, message: T, responder: Responder<A::Response>)
+    where
+        A: MessageHandler<T>,
+        T: Send + 'static,
+    {
+        self.enqueue_message(self.envelope(message), responder.into_promise());
+    }
+
+    /// Delivers the items of `stream` to the actor through its
+    /// `StreamHandler` impl. The stream is dropped once the actor stops.
+    /// Must be called from within the actor system, and only on local refs.
+    pub fn add_stream<S>(&self, stream: S)
+    where
+        S: Stream + 'static,
+        S::Item: Send + 'static,
+        A: StreamHandler<S::Item>,
+    {
+        if self.is_remote() {
+            error!(
+                "Can't stream '{}' to remote actor '{}'",
+                type_name::<S::Item>(),
+                self.id()
+            );
+            return;
+        }
+        let message_type = type_name::<S::Item>();
+        let target = self.clone();
+        let finished = self.clone();
+        let started = self.run(message_type, |actor: &mut A| {
+            StreamHandler::<S::Item>::started(actor)
+        });
+        let items = stream.map_err(|_| ()).for_each(move |item| {
+            target.run(message_type, move |actor: &mut A| {
+                StreamHandler::<S::Item>::handle_item(actor, item)
+            })
+        });
+        let forward = started.and_then(|_| items).then(move |_| {
+            finished.run(message_type, |actor: &mut A| {
+                StreamHandler::<S::Item>::finished(actor)
+            })
+        });
+        let subscription = forward.select2(self.terminated()).then(|_| Ok(()));
+        context::handle().spawn(subscription);
+    }
+
+    /// Runs `f` against the actor in turn with its other messages. Resolves
+    /// once it has run.
+    fn run<F>(&self, message_type: &'static str, f: F) -> impl Future<Item = (), Error = ()>
+    where
+        F: FnOnce(&mut A) + Send + 'static,
+    {
+        let (promise, future) = oneshot::channel();
+        let message = UserMessageWrapper::from_fn(message_type, move |actor| {
+            f(actor);
+            let _ = promise.send(());
+        });
+        if let ActorLocation::Local(ref cell) = self.location {
+            cell.enqueue_user_message(message);
+        }
+        future.map_err(|_| ())
+    }
+
+    fn enqueue_message<T>(
+        &self,
+        envelope: Envelope<T>,
+        promise: Option<oneshot::Sender<ActorResponse<A::Response>>>,
+    ) where
+        A: MessageHandler<T>,
+        T: Send + 'static,
+    {
+        match self.location {
+            ActorLocation::Local(ref cell) => cell.enqueue_message(envelope, promise),
+            ActorLocation::Remote(ref remote) => remote.enqueue_message(envelope, promise),
+        }
+    }
+
+    fn envelope<T>(&self, message: T) -> Envelope<T> {
+        match self.headers {
+            Some(ref headers) => Envelope::with_headers(message, headers.clone()),
+            None => Envelope::new(message),
+        }
+    }
+
+    /// Fails for remote actors, which can't be stopped from here.
     pub fn stop(&self) -> impl Future<Item = (), Error = ()> {
+        let cell = match self.location {
+            ActorLocation::Local(ref cell) => cell,
+            ActorLocation::Remote(ref remote) => {
+                error!("Can't stop remote actor '{}'", remote.id());
+                return Either::B(future::err(()));
+            }
+        };
+        let (promise, future) = oneshot::channel();
+        cell.enqueue_system_message(SystemMessage::Stop(promise));
+        Either::A(future.map_err(|e| error!("Error when stopping: {:?}", e)))
+    }
+
+    /// Resolves once the actor has stopped. Resolves immediately if the actor
+    /// no longer exists. Never resolves for remote actors, whose lifecycle
+    /// can't be watched.
+    pub fn terminated(&self) -> impl Future<Item = (), Error = ()> {
+        let cell = match self.location {
+            ActorLocation::Local(ref cell) => cell,
+            ActorLocation::Remote(_) => return Either::B(future::empty()),
+        };
         let (promise, future) = oneshot::channel();
-        self.system_send(SystemMessage::Stop(promise));
-        future.map_err(|e| println!("Error when stopping: {:?}", e))
+        cell.enqueue_system_message(SystemMessage::Watch(promise));
+        Either::A(future.then(|_| Ok(())))
     }
 }
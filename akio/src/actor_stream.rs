@@ -0,0 +1,54 @@
+use super::context;
+use futures::{Future, Poll, Sink, Stream};
+use futures::sync::mpsc;
+
+/// How many items a producer may run ahead of the consumer.
+const STREAM_BUFFER: usize = 16;
+
+/// A response that arrives as a series of items rather than all at once.
+///
+/// `#[actor_api]` methods returning `ActorStream<T>` are exposed on the
+/// generated `XxxRef` as methods returning a `Stream<Item = T>`. The producer
+/// is throttled when the consumer falls behind, and stops once the consumer
+/// drops the stream.
+pub struct ActorStream<T> {
+    receiver: mpsc::Receiver<T>,
+}
+
+impl<T> ActorStream<T>
+where
+    T: Send + 'static,
+{
+    /// Creates a stream fed through the returned sender.
+    pub fn channel() -> (mpsc::Sender<T>, Self) {
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        let stream = Self {
+            receiver: receiver,
+        };
+        (sender, stream)
+    }
+
+    /// Forwards `stream` on the current thread's event loop. Forwarding ends
+    /// at the first error, which the consumer sees as the end of the stream.
+    pub fn from_stream<S>(stream: S) -> Self
+    where
+        S: Stream<Item = T> + 'static,
+    {
+        let (sender, actor_stream) = Self::channel();
+        let forward = sender
+            .sink_map_err(|_| ())
+            .send_all(stream.map_err(|_| ()))
+            .map(|_| ());
+        context::handle().spawn(forward);
+        actor_stream
+    }
+}
+
+impl<T> Stream for ActorStream<T> {
+    type Item = T;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<T>, ()> {
+        self.receiver.poll()
+    }
+}
@@ -5,27 +5,79 @@ use super::{
     ActorContainer,
     ActorRef,
     ActorResponse,
+    DeadLetter,
     Dispatcher,
+    GrainKey,
+    Headers,
     MessageHandler,
+    Recipient,
 };
-use super::actor_factory::create_actor;
+use super::actor_factory::{
+    create_actor,
+    Factory,
+    FactoryKey,
+    PassivatedKey,
+    PassivationKey,
+    Recovery,
+    RecoveryKey,
+};
+use super::durable_mailbox::{DurableMailbox, DurableMailboxesKey};
 use super::errors::*;
+use super::event_stream::EventStream;
+use super::metrics::MetricsRegistry;
+use super::metrics_exporter::serve_metrics;
+use super::persistence::{
+    self,
+    DurableStateActor,
+    Journal,
+    JournalKey,
+    PersistentActor,
+    SnapshotActor,
+    SnapshotPolicy,
+    SnapshotStore,
+    Snapshots,
+    SnapshotsKey,
+    StateStore,
+    StateStoreKey,
+};
+use super::serialization::{
+    Serializable,
+    SerializableMessage,
+    SerializedMessage,
+    SerializerRegistry,
+};
+use super::util::type_name;
 use futures::Future;
-use futures::sync::oneshot;
+use futures::sync::{mpsc, oneshot};
 use parking_lot::RwLock;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::any::TypeId;
 use std::boxed::FnBox;
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::ptr;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use typemap::ShareMap;
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct ActorSystem {
     inner: Arc<RwLock<ActorSystemInner>>,
+    metrics: MetricsRegistry,
+    events: EventStream,
+    serializers: SerializerRegistry,
 }
 
 struct ActorSystemInner {
     dispatcher: Dispatcher,
     root_actor: Option<ActorRef<GuardianActor>>,
     actors: ActorContainer,
+    // Per actor type settings: factories, passivation timeouts, journals,
+    // snapshot and state stores, durable mailboxes and how to recover state.
+    actor_types: ShareMap,
+    dead_letter_listeners: Vec<mpsc::UnboundedSender<DeadLetter>>,
     done_signal: Option<oneshot::Sender<()>>,
 }
 
@@ -36,10 +88,15 @@ impl ActorSystem {
             dispatcher: dispatcher,
             root_actor: None,
             actors: ActorContainer::new(),
+            actor_types: ShareMap::custom(),
+            dead_letter_listeners: Vec::new(),
             done_signal: None,
         };
         let system = Self {
             inner: Arc::new(RwLock::new(inner)),
+            metrics: MetricsRegistry::new(),
+            events: EventStream::new(),
+            serializers: SerializerRegistry::new(),
         };
         system.inner.write().dispatcher.start(system.clone());
         system.inner.write().root_actor = Some(create_actor(
@@ -91,7 +148,7 @@ impl ActorSystem {
         A: Actor,
     {
         if let Some(_) = self.inner.write().actors.insert(id.clone(), actor) {
-            println!("Replacing existing actor?")
+            warn!("Replacing existing actor '{}'", id)
         }
     }
 
@@ -107,6 +164,308 @@ impl ActorSystem {
             .map(|_: Arc<ActorCell<A>>| ())
     }
 
+    /// Registers `actor` under `id` unless a running actor already is,
+    /// returning the registered actor and whether it is the new one.
+    pub(crate) fn register_actor_if_absent<A, F>(
+        &self,
+        id: Uuid,
+        actor: F,
+    ) -> (Arc<ActorCell<A>>, bool)
+    where
+        A: Actor,
+        F: FnOnce() -> Arc<ActorCell<A>>,
+    {
+        let mut inner = self.inner.write();
+        if let Some(existing) = inner.actors.get::<A>(&id) {
+            if !existing.is_terminated() {
+                return (existing.clone(), false);
+            }
+        }
+        let actor = actor();
+        inner.actors.insert(id, actor.clone());
+        (actor, true)
+    }
+
+    /// Deregisters `actor` unless another actor has since been registered
+    /// under its id.
+    pub(crate) fn deregister_actor_cell<A>(&self, id: &Uuid, actor: &ActorCell<A>)
+    where
+        A: Actor,
+    {
+        let mut inner = self.inner.write();
+        let registered = inner
+            .actors
+            .get::<A>(id)
+            .map_or(false, |registered| ptr::eq(&**registered, actor));
+        if registered {
+            inner.actors.remove::<A>(id);
+        }
+    }
+
+    pub(crate) fn running_cell<A>(&self, id: &Uuid) -> Option<Arc<ActorCell<A>>>
+    where
+        A: Actor,
+    {
+        match self.inner.read().actors.get::<A>(id) {
+            Some(cell) if !cell.is_terminated() => Some(cell.clone()),
+            _ => None,
+        }
+    }
+
+    /// Lets actors of type `A` be re-created by `factory` from their id when
+    /// a message is sent to one that has been passivated, or to a grain that
+    /// isn't running. Actors stopped otherwise stay stopped. Messages sent
+    /// while it is activated are delivered once its `on_start` hook has run.
+    /// Actors are only activated by messages sent from the actor system's
+    /// threads, e.g. from handlers or `on_startup`.
+    pub fn register_factory<A, F>(&self, factory: F)
+    where
+        A: Actor,
+        F: Fn(Uuid) -> A + Send + Sync + 'static,
+    {
+        let factory: Factory<A> = Arc::new(factory);
+        self.inner
+            .write()
+            .actor_types
+            .insert::<FactoryKey<A>>(factory);
+    }
+
+    /// Stops actors of type `A` started from now on once they have had no
+    /// messages for `timeout`.
+    pub fn passivate_after<A>(&self, timeout: Duration)
+    where
+        A: Actor,
+    {
+        self.inner
+            .write()
+            .actor_types
+            .insert::<PassivationKey<A>>(timeout);
+    }
+
+    pub(crate) fn set_passivated<A>(&self, id: Uuid)
+    where
+        A: Actor,
+    {
+        self.inner
+            .write()
+            .actor_types
+            .entry::<PassivatedKey<A>>()
+            .or_insert_with(HashSet::new)
+            .insert(id);
+    }
+
+    pub(crate) fn clear_passivated<A>(&self, id: &Uuid)
+    where
+        A: Actor,
+    {
+        if let Some(passivated) = self.inner
+            .write()
+            .actor_types
+            .get_mut::<PassivatedKey<A>>()
+        {
+            passivated.remove(id);
+        }
+    }
+
+    pub(crate) fn is_passivated<A>(&self, id: &Uuid) -> bool
+    where
+        A: Actor,
+    {
+        self.inner
+            .read()
+            .actor_types
+            .get::<PassivatedKey<A>>()
+            .map_or(false, |passivated| passivated.contains(id))
+    }
+
+    pub(crate) fn passivation_timeout<A>(&self) -> Option<Duration>
+    where
+        A: Actor,
+    {
+        self.inner
+            .read()
+            .actor_types
+            .get::<PassivationKey<A>>()
+            .cloned()
+    }
+
+    /// Persists the events of actors of type `A` to `journal`, and replays
+    /// them whenever one of these actors starts.
+    pub fn register_journal<A, J>(&self, journal: J)
+    where
+        A: PersistentActor,
+        J: Journal,
+    {
+        let journal: Arc<Journal> = Arc::new(journal);
+        let recovery: Recovery<A> = Arc::new(persistence::recover::<A>);
+        let mut inner = self.inner.write();
+        inner.actor_types.insert::<JournalKey<A>>(journal);
+        inner.actor_types.insert::<RecoveryKey<A>>(recovery);
+    }
+
+    /// Snapshots the state of actors of type `A` to `store` according to
+    /// `policy`. They then recover from their latest snapshot and the events
+    /// persisted after it. `A` also needs a journal.
+    pub fn register_snapshot_store<A, S>(&self, store: S, policy: SnapshotPolicy)
+    where
+        A: SnapshotActor,
+        S: SnapshotStore,
+    {
+        let snapshots = Arc::new(Snapshots::<A>::new(store, policy));
+        self.inner
+            .write()
+            .actor_types
+            .insert::<SnapshotsKey<A>>(snapshots);
+    }
+
+    /// Loads the state of actors of type `A` from `store` when they start,
+    /// and saves it there when they change it.
+    pub fn register_state_store<A, S>(&self, store: S)
+    where
+        A: DurableStateActor,
+        S: StateStore,
+    {
+        let store: Arc<StateStore> = Arc::new(store);
+        let recovery: Recovery<A> = Arc::new(persistence::load_state::<A>);
+        let mut inner = self.inner.write();
+        inner.actor_types.insert::<StateStoreKey<A>>(store);
+        inner.actor_types.insert::<RecoveryKey<A>>(recovery);
+    }
+
+    pub(crate) fn state_store<A>(&self) -> Option<Arc<StateStore>>
+    where
+        A: Actor,
+    {
+        self.inner
+            .read()
+            .actor_types
+            .get::<StateStoreKey<A>>()
+            .cloned()
+    }
+
+    /// Logs messages of type `M` sent to actors of type `A` with
+    /// `ActorRef::send_durable` to `journal` before they are delivered.
+    /// Messages an actor hasn't finished handling when it stops, or when the
+    /// process exits, are delivered again once an actor with the same id is
+    /// started. Logs are named after `M`, so renaming it orphans messages
+    /// already logged. Fails if a mailbox is already registered for `A` and
+    /// `M`.
+    pub fn register_durable_mailbox<A, M, J>(&self, journal: J) -> Result<()>
+    where
+        A: Actor + MessageHandler<M>,
+        M: Serialize + DeserializeOwned + Send + 'static,
+        J: Journal,
+    {
+        let mut inner = self.inner.write();
+        let mailboxes = inner
+            .actor_types
+            .entry::<DurableMailboxesKey<A>>()
+            .or_insert_with(Vec::new);
+        if mailboxes
+            .iter()
+            .any(|mailbox| mailbox.message_type() == TypeId::of::<M>())
+        {
+            bail!(ErrorKind::DurableMailboxExists(type_name::<A>(), type_name::<M>()));
+        }
+        mailboxes.push(Arc::new(DurableMailbox::<A>::new::<M, J>(journal)));
+        Ok(())
+    }
+
+    pub(crate) fn durable_mailbox<A, M>(&self) -> Option<Arc<DurableMailbox<A>>>
+    where
+        A: Actor,
+        M: 'static,
+    {
+        self.durable_mailboxes::<A>()
+            .into_iter()
+            .find(|mailbox| mailbox.message_type() == TypeId::of::<M>())
+    }
+
+    pub(crate) fn durable_mailboxes<A>(&self) -> Vec<Arc<DurableMailbox<A>>>
+    where
+        A: Actor,
+    {
+        self.inner
+            .read()
+            .actor_types
+            .get::<DurableMailboxesKey<A>>()
+            .cloned()
+            .unwrap_or_else(Vec::new)
+    }
+
+    pub(crate) fn snapshots<A>(&self) -> Option<Arc<Snapshots<A>>>
+    where
+        A: Actor,
+    {
+        self.inner
+            .read()
+            .actor_types
+            .get::<SnapshotsKey<A>>()
+            .cloned()
+    }
+
+    pub(crate) fn journal<A>(&self) -> Option<Arc<Journal>>
+    where
+        A: Actor,
+    {
+        self.inner
+            .read()
+            .actor_types
+            .get::<JournalKey<A>>()
+            .cloned()
+    }
+
+    pub(crate) fn recovery<A>(&self) -> Option<Recovery<A>>
+    where
+        A: Actor,
+    {
+        self.inner
+            .read()
+            .actor_types
+            .get::<RecoveryKey<A>>()
+            .cloned()
+    }
+
+    pub(crate) fn factory<A>(&self) -> Option<Factory<A>>
+    where
+        A: Actor,
+    {
+        self.inner
+            .read()
+            .actor_types
+            .get::<FactoryKey<A>>()
+            .cloned()
+    }
+
+    /// Returns a ref to the grain of type `A` with `key`. The ref stays valid
+    /// whether or not the actor is running: the first message sent to an
+    /// inactive grain creates it with the factory registered for `A`, and
+    /// concurrent activations of the same key start a single actor. `exists`
+    /// is only true while the grain is active. Like other activations, it
+    /// only happens for messages sent from the actor system's threads.
+    pub fn grain<A>(&self, key: &GrainKey) -> ActorRef<A>
+    where
+        A: Actor,
+    {
+        if self.factory::<A>().is_none() {
+            warn!("No factory registered for grain type '{}'", type_name::<A>());
+        }
+        self.actor_ref(key.grain_id())
+    }
+
+    /// A ref to actor `id`, which may not be running. Messages sent to it
+    /// activate it from the factory registered for `A` whenever it isn't
+    /// running.
+    pub(crate) fn actor_ref<A>(&self, id: Uuid) -> ActorRef<A>
+    where
+        A: Actor,
+    {
+        let cell = self.running_cell::<A>(&id)
+            .map(|cell| Arc::downgrade(&cell))
+            .unwrap_or_else(Weak::new);
+        ActorRef::new(ActorCellHandle::activating(id, cell))
+    }
+
     pub fn get_actor<T>(&self, id: &Uuid) -> Option<ActorRef<T>>
     where
         T: Actor,
@@ -115,7 +474,113 @@ impl ActorSystem {
             .read()
             .actors
             .get(id)
-            .map(|rc| ActorRef::new(ActorCellHandle::new(Arc::downgrade(rc))))
+            .map(|rc| ActorRef::new(ActorCellHandle::new(*id, Arc::downgrade(rc))))
+    }
+
+    pub fn metrics(&self) -> MetricsRegistry {
+        self.metrics.clone()
+    }
+
+    /// Exposes this system's metrics in the Prometheus text format over HTTP
+    /// on `addr`.
+    pub fn serve_metrics(&self, addr: &SocketAddr) -> Result<SocketAddr> {
+        serve_metrics(self.metrics(), addr)
+    }
+
+    pub fn serializers(&self) -> SerializerRegistry {
+        self.serializers.clone()
+    }
+
+    /// Lets messages of type `M`, and their responses, be decoded from their
+    /// message type id. Fails if another message type uses the same id.
+    pub fn register_message<M>(&self) -> Result<()>
+    where
+        M: SerializableMessage,
+        M::Response: Serializable,
+    {
+        self.serializers.register::<M>()
+    }
+
+    /// Like `register_message`, but also lets messages of type `M` from
+    /// other nodes be delivered to actors of type `A`.
+    pub fn register_actor_message<A, M>(&self) -> Result<()>
+    where
+        A: Actor + MessageHandler<M, Response = M::Response>,
+        M: SerializableMessage,
+        M::Response: Serializable,
+    {
+        self.serializers.register_handler::<A, M>()
+    }
+
+    /// Delivers a message from another node to actor `actor_id`, resolving
+    /// to its encoded response. Must be called from within the actor system.
+    pub fn deliver_serialized(
+        &self,
+        actor_id: Uuid,
+        message: &SerializedMessage,
+        headers: Headers,
+    ) -> Box<Future<Item = Vec<u8>, Error = Error>> {
+        self.serializers.deliver(self, actor_id, message, headers)
+    }
+
+    pub fn subscribe_dead_letters(&self) -> mpsc::UnboundedReceiver<DeadLetter> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.inner.write().dead_letter_listeners.push(sender);
+        receiver
+    }
+
+    pub(crate) fn publish_dead_letter(&self, dead_letter: DeadLetter) {
+        self.metrics.counter("akio_dead_letters_total", &[]).inc();
+        self.inner
+            .write()
+            .dead_letter_listeners
+            .retain(|listener| listener.unbounded_send(dead_letter.clone()).is_ok());
+        self.publish(dead_letter);
+    }
+
+    /// Delivers every event of type `E` published on this system to
+    /// `recipient`, whatever its topic, until the recipient stops. The system
+    /// publishes `ActorStarted`, `ActorStopped` and `DeadLetter` events.
+    pub fn subscribe<E>(&self, recipient: Recipient<E>)
+    where
+        E: Clone + Send + 'static,
+    {
+        self.events.subscribe(None, recipient)
+    }
+
+    /// Like `subscribe`, but only for events published to `topic`.
+    pub fn subscribe_topic<E>(&self, topic: &str, recipient: Recipient<E>)
+    where
+        E: Clone + Send + 'static,
+    {
+        self.events.subscribe(Some(topic.to_string()), recipient)
+    }
+
+    /// Removes the subscriptions of the actor with id `subscriber` to events
+    /// of type `E`.
+    pub fn unsubscribe<E>(&self, subscriber: &Uuid)
+    where
+        E: Clone + Send + 'static,
+    {
+        self.events.unsubscribe::<E>(subscriber)
+    }
+
+    /// Sends `event` to the subscribers of its type that didn't ask for a
+    /// particular topic.
+    pub fn publish<E>(&self, event: E)
+    where
+        E: Clone + Send + 'static,
+    {
+        self.events.publish(None, event)
+    }
+
+    /// Sends `event` to the subscribers of its type that asked for `topic`,
+    /// or for no particular topic.
+    pub fn publish_topic<E>(&self, topic: &str, event: E)
+    where
+        E: Clone + Send + 'static,
+    {
+        self.events.publish(Some(topic), event)
     }
 }
 
@@ -7,12 +7,12 @@ use akio::prelude::*;
 use std::iter;
 
 pub struct PongActor {
-    ping: ActorRef<PingActor>,
+    ping: Recipient<PingActorMessagePong>,
 }
 
 #[actor_impl]
 impl PongActor {
-    pub fn new(ping: ActorRef<PingActor>) -> Self {
+    pub fn new(ping: Recipient<PingActorMessagePong>) -> Self {
         Self {
             ping: ping,
         }
@@ -20,7 +20,7 @@ impl PongActor {
 
     #[actor_api]
     pub fn ping(&mut self) {
-        self.ping.send_pong();
+        self.ping.send(PingActorMessagePong());
         self.done()
     }
 
@@ -36,7 +36,7 @@ impl PongActor {
 }
 
 pub struct PingActor {
-    pong: Option<ActorRef<PongActor>>,
+    pong: Option<Recipient<PongActorMessagePing>>,
 }
 
 #[actor_impl]
@@ -48,14 +48,16 @@ impl PingActor {
     }
 
     #[actor_api]
-    pub fn initialize(&mut self, pong: ActorRef<PongActor>) {
+    pub fn initialize(&mut self, pong: Recipient<PongActorMessagePing>) {
         self.pong = Some(pong);
         self.done()
     }
 
     #[actor_api]
     pub fn pong(&mut self) {
-        self.pong.as_ref().map(|pong| pong.send_ping());
+        self.pong
+            .as_ref()
+            .map(|pong| pong.send(PongActorMessagePing()));
         self.done()
     }
 
@@ -74,8 +76,8 @@ impl PingActor {
 
 fn spawn_ping_loop() {
     let ping = PingActor::new().start();
-    let pong = PongActor::new(ping.clone()).start();
-    ping.initialize(pong.clone());
+    let pong = PongActor::new(ping.recipient()).start();
+    ping.initialize(pong.recipient());
     iter::repeat(()).take(20).for_each(|_| {
         pong.ping();
     });
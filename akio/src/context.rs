@@ -26,6 +26,12 @@ pub fn handle() -> Handle {
     CURRENT_THREAD.with(|ctx| ctx.borrow().as_ref().unwrap().handle.clone())
 }
 
+pub fn maybe_system() -> Option<ActorSystem> {
+    CURRENT_THREAD.with(|ctx| {
+        ctx.borrow().as_ref().map(|inner| inner.system.clone())
+    })
+}
+
 pub fn system() -> ActorSystem {
     CURRENT_THREAD.with(|ctx| ctx.borrow().as_ref().unwrap().system.clone())
 }
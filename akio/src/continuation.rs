@@ -0,0 +1,219 @@
+use super::{context, Actor, ActorCellHandle, ActorResponse, Headers, Span};
+use super::responder::with_pending_promise;
+use super::util::type_name;
+use futures::{Future, IntoFuture};
+use futures::sync::oneshot;
+use std::any::Any;
+use std::marker::PhantomData;
+
+/// Whether other messages may be handled while a continuation's future is
+/// pending.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Reentrancy {
+    /// The mailbox is paused until the continuation has run, so the handler
+    /// observes the actor's state exactly as it left it.
+    NonReentrant,
+    /// Other messages interleave with the pending future.
+    Reentrant,
+}
+
+/// The rest of a message handler, to be run against the actor's state once a
+/// future resolves. Created with `Actor::respond_async`.
+pub struct Continuation<T> {
+    reentrancy: Reentrancy,
+    // A `Box<PendingContinuation<A, T> + Send>`, erased so that responses
+    // don't have to name the actor type.
+    pending: Box<Any + Send>,
+    _response: PhantomData<fn() -> T>,
+}
+
+impl<T> Continuation<T>
+where
+    T: Send + 'static,
+{
+    pub(crate) fn new<A, F, C>(future: F, continuation: C) -> Self
+    where
+        A: Actor,
+        F: IntoFuture,
+        F::Future: Send + 'static,
+        F::Item: Send + 'static,
+        F::Error: Send + 'static,
+        C: FnOnce(&mut A, Result<F::Item, F::Error>) -> ActorResponse<T> + Send + 'static,
+    {
+        let pending: Box<PendingContinuation<A, T> + Send> = Box::new(FutureContinuation {
+            future: future.into_future(),
+            continuation: continuation,
+            _actor: PhantomData,
+        });
+        Self {
+            reentrancy: Reentrancy::NonReentrant,
+            pending: Box::new(pending),
+            _response: PhantomData,
+        }
+    }
+
+    pub(crate) fn reentrant(self) -> Self {
+        Self {
+            reentrancy: Reentrancy::Reentrant,
+            ..self
+        }
+    }
+
+    /// Waits for the future on the current thread's event loop and queues
+    /// the continuation on `cell` once it resolves. Returns whether the
+    /// actor's mailbox should be suspended in the meantime.
+    ///
+    /// A continuation created for another actor type is dropped, which
+    /// cancels the sender's response future.
+    fn spawn<A>(
+        self,
+        cell: &ActorCellHandle<A>,
+        promise: Option<oneshot::Sender<ActorResponse<T>>>,
+    ) -> bool
+    where
+        A: Actor,
+    {
+        let pending = match self.pending.downcast::<Box<PendingContinuation<A, T> + Send>>() {
+            Ok(pending) => *pending,
+            Err(_) => {
+                error!(
+                    "Dropping a continuation that wasn't created for actor '{}'",
+                    type_name::<A>()
+                );
+                return false;
+            }
+        };
+        pending.spawn(cell.clone(), self.reentrancy, promise);
+        self.reentrancy == Reentrancy::NonReentrant
+    }
+}
+
+trait PendingContinuation<A, T> {
+    fn spawn(
+        self: Box<Self>,
+        cell: ActorCellHandle<A>,
+        reentrancy: Reentrancy,
+        promise: Option<oneshot::Sender<ActorResponse<T>>>,
+    );
+}
+
+struct FutureContinuation<A, F, C> {
+    future: F,
+    continuation: C,
+    _actor: PhantomData<fn(&mut A)>,
+}
+
+impl<A, F, C, T> PendingContinuation<A, T> for FutureContinuation<A, F, C>
+where
+    A: Actor,
+    F: Future + 'static,
+    F::Item: Send + 'static,
+    F::Error: Send + 'static,
+    C: FnOnce(&mut A, Result<F::Item, F::Error>) -> ActorResponse<T> + Send + 'static,
+    T: Send + 'static,
+{
+    fn spawn(
+        self: Box<Self>,
+        cell: ActorCellHandle<A>,
+        reentrancy: Reentrancy,
+        promise: Option<oneshot::Sender<ActorResponse<T>>>,
+    ) {
+        let FutureContinuation {
+            future,
+            continuation,
+            ..
+        } = *self;
+        let headers = Headers::current();
+        let message_type = Span::current()
+            .map(|span| span.message_type)
+            .unwrap_or("continuation");
+        let resumed = future.then(move |result| {
+            let target = cell.clone();
+            let resume = move |actor: &mut A| {
+                let _headers = headers.map(Headers::enter);
+                let (response, promise) =
+                    with_pending_promise(promise, || continuation(actor, result));
+                let response = match reentrancy {
+                    Reentrancy::Reentrant => response.reentrant(),
+                    Reentrancy::NonReentrant => response,
+                };
+                complete_response(&cell, response, promise)
+            };
+            target.enqueue_continuation(ContinuationMessage::new(
+                reentrancy,
+                message_type,
+                resume,
+            ));
+            Ok(())
+        });
+        context::handle().spawn(resumed);
+    }
+}
+
+/// Sends a handler's response to whoever is waiting for it. A continuation
+/// is started instead, and its eventual response is sent when it completes.
+/// Returns whether the actor's mailbox should be suspended.
+pub(crate) fn complete_response<A, T>(
+    cell: &ActorCellHandle<A>,
+    response: ActorResponse<T>,
+    promise: Option<oneshot::Sender<ActorResponse<T>>>,
+) -> bool
+where
+    A: Actor,
+    T: Send + 'static,
+{
+    match response {
+        ActorResponse::Continuation(continuation) => continuation.spawn(cell, promise),
+        response => {
+            promise.map(|promise| promise.send(response));
+            false
+        }
+    }
+}
+
+trait Resume<A>: Send {
+    fn resume(self: Box<Self>, actor: &mut A) -> bool;
+}
+
+impl<A, F> Resume<A> for F
+where
+    F: FnOnce(&mut A) -> bool + Send,
+{
+    fn resume(self: Box<Self>, actor: &mut A) -> bool {
+        (*self)(actor)
+    }
+}
+
+/// A continuation that is ready to run, queued in the actor's mailbox.
+pub(crate) struct ContinuationMessage<A> {
+    reentrancy: Reentrancy,
+    message_type: &'static str,
+    resume: Box<Resume<A>>,
+}
+
+impl<A> ContinuationMessage<A> {
+    fn new<F>(reentrancy: Reentrancy, message_type: &'static str, resume: F) -> Self
+    where
+        F: FnOnce(&mut A) -> bool + Send + 'static,
+    {
+        Self {
+            reentrancy: reentrancy,
+            message_type: message_type,
+            resume: Box::new(resume),
+        }
+    }
+
+    pub fn is_reentrant(&self) -> bool {
+        self.reentrancy == Reentrancy::Reentrant
+    }
+
+    pub fn message_type(&self) -> &'static str {
+        self.message_type
+    }
+
+    /// Runs the continuation. Returns whether the actor's mailbox should be
+    /// suspended again.
+    pub fn resume(self, actor: &mut A) -> bool {
+        self.resume.resume(actor)
+    }
+}
@@ -0,0 +1,17 @@
+use uuid::Uuid;
+
+/// A message that could not be delivered because its recipient was stopped.
+#[derive(Clone, Debug)]
+pub struct DeadLetter {
+    pub recipient: Uuid,
+    pub message_type: &'static str,
+}
+
+impl DeadLetter {
+    pub fn new(recipient: Uuid, message_type: &'static str) -> Self {
+        Self {
+            recipient: recipient,
+            message_type: message_type,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use super::{context, Actor, ActorCellHandle, ActorSystem};
+use super::metrics::Counter;
 #[cfg(target_os = "linux")]
 use core_affinity;
 use futures::future::Executor;
@@ -10,17 +11,10 @@ use rand;
 use rand::Rng;
 use std::iter;
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tokio_core::reactor::{Core, Remote};
 
-lazy_static! {
-    static ref START: Instant = Instant::now();
-    static ref COUNTER: AtomicUsize = AtomicUsize::new(0);
-}
-
-
 enum ThreadMessage {
     ProcessActor(Box<ActorProcessor>),
     Stop(),
@@ -79,7 +73,6 @@ impl Dispatcher {
     }
 
     pub fn start(&mut self, system: ActorSystem) {
-        let _ = *START;
         self.handles = self.create_threads(system);
     }
 
@@ -101,14 +94,21 @@ impl Dispatcher {
         core_affinity::get_core_ids()
             .unwrap()
             .into_iter()
-            .map(|core_id| self.create_thread(core_id, system.clone()))
+            .enumerate()
+            .map(|(i, core_id)| self.create_thread(i, core_id, system.clone()))
             .collect::<Vec<ThreadHandle>>()
     }
 
     #[cfg(target_os = "linux")]
-    fn create_thread(&self, core_id: core_affinity::CoreId, system: ActorSystem) -> ThreadHandle {
+    fn create_thread(
+        &self,
+        index: usize,
+        core_id: core_affinity::CoreId,
+        system: ActorSystem,
+    ) -> ThreadHandle {
         let (sender, receiver) = mpsc::channel(100);
-        let (remote, handle) = DispatcherThread::new(system, receiver).run_with_affinity(core_id);
+        let (remote, handle) =
+            DispatcherThread::new(index, system, receiver).run_with_affinity(core_id);
         ThreadHandle {
             sender: sender,
             handle: handle,
@@ -120,14 +120,15 @@ impl Dispatcher {
     fn create_threads(&self, system: ActorSystem) -> Vec<ThreadHandle> {
         iter::repeat(())
             .take(num_cpus::get())
-            .map(|_| self.create_thread(system.clone()))
+            .enumerate()
+            .map(|(i, _)| self.create_thread(i, system.clone()))
             .collect::<Vec<ThreadHandle>>()
     }
 
     #[cfg(not(target_os = "linux"))]
-    fn create_thread(&self, system: ActorSystem) -> ThreadHandle {
+    fn create_thread(&self, index: usize, system: ActorSystem) -> ThreadHandle {
         let (sender, receiver) = mpsc::channel(100);
-        let (remote, handle) = DispatcherThread::new(system, receiver).run();
+        let (remote, handle) = DispatcherThread::new(index, system, receiver).run();
         ThreadHandle {
             sender: sender,
             handle: handle,
@@ -145,13 +146,19 @@ impl Dispatcher {
 struct DispatcherThread {
     receiver: mpsc::Receiver<ThreadMessage>,
     system: ActorSystem,
+    processed: Counter,
 }
 
 impl DispatcherThread {
-    pub fn new(system: ActorSystem, receiver: mpsc::Receiver<ThreadMessage>) -> Self {
+    pub fn new(index: usize, system: ActorSystem, receiver: mpsc::Receiver<ThreadMessage>) -> Self {
+        let processed = system.metrics().counter(
+            "akio_dispatcher_messages_total",
+            &[("thread", &index.to_string())],
+        );
         Self {
             receiver: receiver,
             system: system,
+            processed: processed,
         }
     }
 
@@ -159,7 +166,9 @@ impl DispatcherThread {
         let arc_remote = Arc::new(Mutex::new(None));
         let cloned_arc_remote = arc_remote.clone();
         let handle = thread::spawn(move || {
-            let stream = self.receiver.for_each(|message| handle_message(message));
+            let processed = self.processed;
+            let stream = self.receiver
+                .for_each(move |message| handle_message(message, &processed));
             let mut core = Core::new().expect("Failed to start dispatcher thread");
             let handle = core.handle();
             *cloned_arc_remote.lock().unwrap() = Some(core.remote());
@@ -189,7 +198,9 @@ impl DispatcherThread {
         let cloned_arc_remote = arc_remote.clone();
         let handle = thread::spawn(move || {
             core_affinity::set_for_current(core_id);
-            let stream = self.receiver.for_each(|message| handle_message(message));
+            let processed = self.processed;
+            let stream = self.receiver
+                .for_each(move |message| handle_message(message, &processed));
             let mut core = Core::new().expect("Failed to start dispatcher thread");
             let handle = core.handle();
             *cloned_arc_remote.lock().unwrap() = Some(core.remote());
@@ -211,24 +222,12 @@ impl DispatcherThread {
     }
 }
 
-fn handle_message(message: ThreadMessage) -> Result<(), ()> {
+fn handle_message(message: ThreadMessage, processed: &Counter) -> Result<(), ()> {
     match message {
         ThreadMessage::ProcessActor(processor) => {
-            let n = processor.process();
-            count(n);
+            processed.add(processor.process());
             Ok(())
         }
         ThreadMessage::Stop() => Err(()),
     }
 }
-
-fn count(n: usize) {
-    let count = COUNTER.fetch_add(n, Ordering::SeqCst) + n;
-    if (count - n) % 10000000 > count % 10000000 {
-        let dt = (Instant::now() - *START).as_secs() as usize;
-        if dt > 0 {
-            let rate = count / dt;
-            println!("Dispatch {} ({}/s)", count, rate);
-        }
-    }
-}
@@ -0,0 +1,312 @@
+use super::{context, Actor, ActorCellHandle, ActorResponse, Envelope, MessageHandler};
+use super::errors::*;
+use super::mailbox::{UserMessage, UserMessageWrapper};
+use super::persistence::Journal;
+use super::util::type_name;
+use bincode;
+use futures::future::{self, Either};
+use futures::prelude::*;
+use futures::sync::oneshot;
+use parking_lot::{Mutex, ReentrantMutex};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::any::TypeId;
+use std::collections::{BTreeSet, HashMap};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use typemap::Key;
+use uuid::Uuid;
+
+/// The log and its acknowledgements are trimmed after this many messages
+/// have been acknowledged, rather than after each one.
+const COMPACT_EVERY: u64 = 64;
+
+pub(crate) struct DurableMailboxesKey<A> {
+    _actor: PhantomData<A>,
+}
+
+impl<A> Key for DurableMailboxesKey<A>
+where
+    A: Actor,
+{
+    type Value = Vec<Arc<DurableMailbox<A>>>;
+}
+
+/// Logs the messages of one type sent to actors of type `A`, so that those
+/// not yet handled when an actor stops or the process exits are delivered
+/// again once the actor is recreated. Each actor's messages are logged under
+/// its id and the message type's name. Handled messages are recorded in a
+/// second log of acknowledgements, and both logs are trimmed from time to
+/// time.
+pub(crate) struct DurableMailbox<A> {
+    journal: Arc<Journal>,
+    message_type: TypeId,
+    message_type_name: &'static str,
+    decode: fn(&[u8]) -> Result<LoggedMessageParts<A>>,
+    // Held from logging a message until it is in the mailbox, and while
+    // logged messages are replayed, so that mailboxes receive messages in
+    // the order they were logged. Sending may start the actor, which
+    // replays its messages on the same thread.
+    ordering: ReentrantMutex<()>,
+    // Progress of each running actor through its log. Removed when the
+    // actor stops.
+    logs: Mutex<HashMap<Uuid, ActorLog>>,
+}
+
+#[derive(Default)]
+struct ActorLog {
+    // The last message whose handler has run. A replayed message may also
+    // have been delivered directly.
+    started: u64,
+    // Every message up to this one has been handled and acknowledged.
+    acknowledged: u64,
+    // Handled messages waiting for earlier ones to be handled.
+    completed: BTreeSet<u64>,
+    acknowledged_since_compaction: u64,
+}
+
+/// Resolves once a message's handler, including any continuation or
+/// deferred response, has finished.
+type Handled = Box<Future<Item = (), Error = ()> + Send>;
+
+type LoggedMessageParts<A> = (UserMessageWrapper<A>, Handled);
+
+impl<A> DurableMailbox<A>
+where
+    A: Actor,
+{
+    pub fn new<M, J>(journal: J) -> Self
+    where
+        A: MessageHandler<M>,
+        M: DeserializeOwned + Send + 'static,
+        J: Journal,
+    {
+        Self {
+            journal: Arc::new(journal),
+            message_type: TypeId::of::<M>(),
+            message_type_name: type_name::<M>(),
+            decode: decode_message::<A, M>,
+            ordering: ReentrantMutex::new(()),
+            logs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn message_type(&self) -> TypeId {
+        self.message_type
+    }
+
+    pub fn message_type_name(&self) -> &'static str {
+        self.message_type_name
+    }
+
+    /// Logs `envelope`'s message durably, then delivers it. Headers aren't
+    /// logged, so a replayed message has new ones.
+    pub fn send<M>(
+        me: &Arc<Self>,
+        actor: &ActorCellHandle<A>,
+        envelope: Envelope<M>,
+    ) -> Result<()>
+    where
+        A: MessageHandler<M>,
+        M: Serialize + Send + 'static,
+    {
+        let payload = bincode::serialize(&envelope.message, bincode::Infinite)?;
+        let _ordering = me.ordering.lock();
+        let sequence_nr = me.journal.append(&me.log_id(&actor.id()), vec![payload])?;
+        let (promise, response) = oneshot::channel();
+        let message = UserMessageWrapper::make(envelope, Some(promise));
+        let handled = handled(response);
+        actor.enqueue_user_message(Self::logged(me, actor.id(), sequence_nr, message, handled));
+        Ok(())
+    }
+
+    /// Puts the logged messages of the actor that haven't been acknowledged
+    /// ahead of those already in its mailbox. Called while the actor starts.
+    pub fn replay(me: &Arc<Self>, actor: &ActorCellHandle<A>) -> Result<()> {
+        let _ordering = me.ordering.lock();
+        let acknowledged = me.last_acknowledged(&actor.id())?;
+        me.logs.lock().insert(
+            actor.id(),
+            ActorLog {
+                started: acknowledged,
+                acknowledged: acknowledged,
+                ..ActorLog::default()
+            },
+        );
+        let entries = me.journal
+            .replay(&me.log_id(&actor.id()), acknowledged + 1)?;
+        if !entries.is_empty() {
+            debug!("Replaying {} messages to '{}'", entries.len(), actor.id());
+        }
+        let mut messages = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let (message, handled) = (me.decode)(&entry.payload)?;
+            messages.push(Self::logged(me, actor.id(), entry.sequence_nr, message, handled));
+        }
+        actor.enqueue_user_messages_first(messages);
+        Ok(())
+    }
+
+    /// Drops what is known about the actor once it has stopped.
+    pub fn forget(&self, actor_id: &Uuid) {
+        self.logs.lock().remove(actor_id);
+    }
+
+    fn logged(
+        me: &Arc<Self>,
+        actor_id: Uuid,
+        sequence_nr: u64,
+        message: UserMessageWrapper<A>,
+        handled: Handled,
+    ) -> UserMessageWrapper<A> {
+        UserMessageWrapper::new(Box::new(LoggedMessage {
+            mailbox: me.clone(),
+            actor_id: actor_id,
+            sequence_nr: sequence_nr,
+            message: message,
+            handled: Some(handled),
+        }))
+    }
+
+    fn log_id(&self, actor_id: &Uuid) -> String {
+        format!("mailbox-{}-{}", actor_id, self.message_type_name)
+    }
+
+    fn acknowledgements_id(&self, actor_id: &Uuid) -> String {
+        format!("mailbox-acks-{}-{}", actor_id, self.message_type_name)
+    }
+
+    fn last_acknowledged(&self, actor_id: &Uuid) -> Result<u64> {
+        let acknowledgements = self.acknowledgements_id(actor_id);
+        let last = self.journal.highest_sequence_nr(&acknowledgements)?;
+        Ok(self.journal
+            .replay(&acknowledgements, last)?
+            .last()
+            .map_or(Ok(0), |entry| bincode::deserialize::<u64>(&entry.payload))?)
+    }
+
+    /// Records that the handler of `sequence_nr` is running. Returns false
+    /// if it already ran.
+    fn start(&self, actor_id: &Uuid, sequence_nr: u64) -> bool {
+        let mut logs = self.logs.lock();
+        let log = logs.entry(*actor_id).or_insert_with(|| {
+            // Only when the mailbox was registered after the actor started.
+            let acknowledged = self.last_acknowledged(actor_id).unwrap_or_else(|e| {
+                error!("Failed to read acknowledgements of '{}': {}", actor_id, e);
+                0
+            });
+            ActorLog {
+                started: acknowledged,
+                acknowledged: acknowledged,
+                ..ActorLog::default()
+            }
+        });
+        if sequence_nr <= log.started {
+            return false;
+        }
+        log.started = sequence_nr;
+        true
+    }
+
+    /// Acknowledges `sequence_nr` once every earlier message has been
+    /// handled too.
+    fn complete(&self, actor_id: Uuid, sequence_nr: u64) {
+        let (acknowledged, compact) = {
+            let mut logs = self.logs.lock();
+            let log = match logs.get_mut(&actor_id) {
+                Some(log) => log,
+                // The actor stopped. Its unacknowledged messages are
+                // replayed when it is started again.
+                None => return,
+            };
+            log.completed.insert(sequence_nr);
+            let previous = log.acknowledged;
+            while log.completed.remove(&(log.acknowledged + 1)) {
+                log.acknowledged += 1;
+            }
+            if log.acknowledged == previous {
+                return;
+            }
+            log.acknowledged_since_compaction += log.acknowledged - previous;
+            let compact = log.acknowledged_since_compaction >= COMPACT_EVERY;
+            if compact {
+                log.acknowledged_since_compaction = 0;
+            }
+            (log.acknowledged, compact)
+        };
+        if let Err(e) = self.acknowledge(&actor_id, acknowledged, compact) {
+            error!("Failed to acknowledge message to '{}': {}", actor_id, e);
+        }
+    }
+
+    fn acknowledge(&self, actor_id: &Uuid, acknowledged: u64, compact: bool) -> Result<()> {
+        let acknowledgements = self.acknowledgements_id(actor_id);
+        let payload = bincode::serialize(&acknowledged, bincode::Infinite)?;
+        let marker = self.journal.append(&acknowledgements, vec![payload])?;
+        if compact {
+            self.journal.delete_to(&self.log_id(actor_id), acknowledged)?;
+            self.journal.delete_to(&acknowledgements, marker - 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn decode_message<A, M>(payload: &[u8]) -> Result<LoggedMessageParts<A>>
+where
+    A: Actor + MessageHandler<M>,
+    M: DeserializeOwned + Send + 'static,
+{
+    let message: M = bincode::deserialize(payload)?;
+    let (promise, response) = oneshot::channel();
+    Ok((UserMessageWrapper::make(Envelope::new(message), Some(promise)), handled(response)))
+}
+
+fn handled<T>(response: oneshot::Receiver<ActorResponse<T>>) -> Handled
+where
+    T: Send + 'static,
+{
+    Box::new(response.then(|response| match response {
+        Ok(response) => Either::A(response.then(|_| Ok(()))),
+        Err(_) => Either::B(future::ok(())),
+    }))
+}
+
+struct LoggedMessage<A>
+where
+    A: Actor,
+{
+    mailbox: Arc<DurableMailbox<A>>,
+    actor_id: Uuid,
+    sequence_nr: u64,
+    message: UserMessageWrapper<A>,
+    handled: Option<Handled>,
+}
+
+impl<A> UserMessage<A> for LoggedMessage<A>
+where
+    A: Actor,
+{
+    /// Acknowledged once the handler has produced its response, which for
+    /// `respond_async` handlers is after the continuation has run. Messages
+    /// the actor has already handled are skipped.
+    fn handle(&mut self, actor: &mut A, cell: &ActorCellHandle<A>) -> bool {
+        if !self.mailbox.start(&self.actor_id, self.sequence_nr) {
+            return false;
+        }
+        let suspend = self.message.handle(actor, cell);
+        if let Some(handled) = self.handled.take() {
+            let mailbox = self.mailbox.clone();
+            let actor_id = self.actor_id;
+            let sequence_nr = self.sequence_nr;
+            // If the actor stops first, its log is forgotten before the
+            // response is dropped, so the message stays in the log.
+            let finished = handled.map(move |_| mailbox.complete(actor_id, sequence_nr));
+            context::handle().spawn(finished);
+        }
+        suspend
+    }
+
+    fn message_type(&self) -> &'static str {
+        self.message.message_type()
+    }
+}
@@ -0,0 +1,102 @@
+use super::Span;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::mem;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+thread_local! {
+    static CURRENT_HEADERS: RefCell<Option<Headers>> = RefCell::new(None)
+}
+
+/// Metadata that travels with a message.
+///
+/// New headers inherit the correlation id and trace context of the message
+/// currently being handled, so a chain of messages can be followed across
+/// actors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Headers {
+    pub correlation_id: Uuid,
+    pub sender: Option<Uuid>,
+    pub timestamp: SystemTime,
+    pub trace_context: Option<String>,
+    pub extra: BTreeMap<String, String>,
+}
+
+impl Headers {
+    pub fn new() -> Self {
+        let sender = Span::current().map(|span| span.actor_id);
+        match Self::current() {
+            Some(current) => Self {
+                correlation_id: current.correlation_id,
+                sender: sender,
+                timestamp: SystemTime::now(),
+                trace_context: current.trace_context,
+                extra: BTreeMap::new(),
+            },
+            None => Self {
+                correlation_id: Uuid::new_v4(),
+                sender: sender,
+                timestamp: SystemTime::now(),
+                trace_context: None,
+                extra: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// Headers of the message currently being handled on this thread.
+    pub fn current() -> Option<Headers> {
+        CURRENT_HEADERS.with(|headers| headers.borrow().clone())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.extra.get(key).map(|value| value.as_str())
+    }
+
+    pub fn insert<K, V>(&mut self, key: K, value: V) -> Option<String>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.extra.insert(key.into(), value.into())
+    }
+
+    pub(crate) fn enter(self) -> HeadersGuard {
+        let previous = CURRENT_HEADERS.with(|headers| {
+            mem::replace(&mut *headers.borrow_mut(), Some(self))
+        });
+        HeadersGuard {
+            previous: previous,
+        }
+    }
+}
+
+pub(crate) struct HeadersGuard {
+    previous: Option<Headers>,
+}
+
+impl Drop for HeadersGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT_HEADERS.with(|headers| *headers.borrow_mut() = previous);
+    }
+}
+
+/// A message together with its headers.
+pub struct Envelope<M> {
+    pub headers: Headers,
+    pub message: M,
+}
+
+impl<M> Envelope<M> {
+    pub fn new(message: M) -> Self {
+        Self::with_headers(message, Headers::new())
+    }
+
+    pub fn with_headers(message: M, headers: Headers) -> Self {
+        Self {
+            headers: headers,
+            message: message,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::time::Duration;
 use uuid::Uuid;
 
 error_chain!{
@@ -11,5 +12,85 @@ error_chain!{
             description("invalid actor")
             display("invalid actor: '{}'", id)
         }
+        ExpectTimeout(duration: Duration) {
+            description("timed out waiting for expectation")
+            display("timed out after {:?}", duration)
+        }
+        UnexpectedMessage(message_type: &'static str) {
+            description("unexpected message")
+            display("unexpected message: '{}'", message_type)
+        }
+        ProbeClosed
+        NoJournal(actor_type: &'static str) {
+            description("no journal registered")
+            display("no journal registered for '{}'", actor_type)
+        }
+        CorruptJournal(path: String, sequence_nr: u64) {
+            description("corrupt journal record")
+            display("record {} of journal '{}' is corrupt", sequence_nr, path)
+        }
+        NoSnapshotStore(actor_type: &'static str) {
+            description("no snapshot store registered")
+            display("no snapshot store registered for '{}'", actor_type)
+        }
+        NoDurableMailbox(actor_type: &'static str, message_type: &'static str) {
+            description("no durable mailbox registered")
+            display(
+                "no durable mailbox registered for '{}' messages to '{}'",
+                message_type,
+                actor_type
+            )
+        }
+        DurableMailboxExists(actor_type: &'static str, message_type: &'static str) {
+            description("durable mailbox already registered")
+            display(
+                "a durable mailbox is already registered for '{}' messages to '{}'",
+                message_type,
+                actor_type
+            )
+        }
+        DuplicateMessageType(id: &'static str, registered: &'static str, other: &'static str) {
+            description("message type id already registered")
+            display("message type id '{}' of '{}' is already used by '{}'", id, other, registered)
+        }
+        UnknownMessageType(id: String) {
+            description("unknown message type")
+            display("no message registered with type id '{}'", id)
+        }
+        NoMessageHandler(id: String) {
+            description("no actor registered for message type")
+            display("no actor registered to handle messages with type id '{}'", id)
+        }
+        NoResponse(actor_id: Uuid, message_type_id: String) {
+            description("actor did not respond")
+            display("actor '{}' did not respond to '{}'", actor_id, message_type_id)
+        }
+        RemoteActor(operation: &'static str) {
+            description("unsupported on remote actors")
+            display("'{}' is not supported on remote actors", operation)
+        }
+        NoStateStore(actor_type: &'static str) {
+            description("no state store registered")
+            display("no state store registered for '{}'", actor_type)
+        }
+        VersionConflict(persistence_id: String, expected: u64, actual: u64) {
+            description("state was saved by another writer")
+            display(
+                "state of '{}' is at version {}, expected {}",
+                persistence_id,
+                actual,
+                expected
+            )
+        }
+        StateLocked(path: String) {
+            description("state is locked by another writer")
+            display("timed out waiting for lock file '{}'", path)
+        }
+    }
+
+    foreign_links {
+        Bincode(::bincode::Error);
+        Io(::std::io::Error);
+        Logger(::log::SetLoggerError);
     }
 }
@@ -0,0 +1,110 @@
+use super::Recipient;
+use parking_lot::Mutex;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Published by the system once an actor has started.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ActorStarted {
+    pub actor_id: Uuid,
+    pub actor_type: &'static str,
+}
+
+/// Published by the system once an actor has stopped.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ActorStopped {
+    pub actor_id: Uuid,
+    pub actor_type: &'static str,
+}
+
+struct Subscriber<E> {
+    topic: Option<String>,
+    recipient: Recipient<E>,
+}
+
+impl<E> Subscriber<E>
+where
+    E: Send + 'static,
+{
+    fn wants(&self, topic: Option<&str>) -> bool {
+        match self.topic {
+            Some(ref wanted) => topic == Some(wanted.as_str()),
+            None => true,
+        }
+    }
+}
+
+/// Subscribers to each event type, behind the `ActorSystem`'s publish and
+/// subscribe methods.
+#[derive(Clone)]
+pub(crate) struct EventStream {
+    // Each value is a `Vec<Subscriber<E>>` for the `E` of its key.
+    subscribers: Arc<Mutex<HashMap<TypeId, Box<Any + Send>>>>,
+}
+
+impl EventStream {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn subscribe<E>(&self, topic: Option<String>, recipient: Recipient<E>)
+    where
+        E: Clone + Send + 'static,
+    {
+        let subscriber = Subscriber {
+            topic: topic,
+            recipient: recipient,
+        };
+        self.subscribers
+            .lock()
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Vec::<Subscriber<E>>::new()))
+            .downcast_mut::<Vec<Subscriber<E>>>()
+            .expect("subscribers stored under the wrong event type")
+            .push(subscriber)
+    }
+
+    pub fn unsubscribe<E>(&self, subscriber: &Uuid)
+    where
+        E: Clone + Send + 'static,
+    {
+        self.with_subscribers::<E, _, _>(|subscribers| {
+            subscribers.retain(|existing| existing.recipient.id() != *subscriber)
+        });
+    }
+
+    pub fn publish<E>(&self, topic: Option<&str>, event: E)
+    where
+        E: Clone + Send + 'static,
+    {
+        // Sent after releasing the lock, since delivering to a stopped
+        // actor publishes a dead letter.
+        let recipients = self.with_subscribers::<E, _, _>(|subscribers| {
+            subscribers.retain(|subscriber| subscriber.recipient.exists());
+            subscribers
+                .iter()
+                .filter(|subscriber| subscriber.wants(topic))
+                .map(|subscriber| subscriber.recipient.clone())
+                .collect::<Vec<_>>()
+        });
+        for recipient in recipients.unwrap_or_else(Vec::new) {
+            recipient.send(event.clone());
+        }
+    }
+
+    fn with_subscribers<E, F, R>(&self, f: F) -> Option<R>
+    where
+        E: Send + 'static,
+        F: FnOnce(&mut Vec<Subscriber<E>>) -> R,
+    {
+        self.subscribers
+            .lock()
+            .get_mut(&TypeId::of::<E>())
+            .and_then(|subscribers| subscribers.downcast_mut::<Vec<Subscriber<E>>>())
+            .map(f)
+    }
+}
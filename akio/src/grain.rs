@@ -0,0 +1,26 @@
+use uuid::{Uuid, NAMESPACE_OID};
+
+/// Identifies a grain, an actor addressed by key rather than by a running
+/// instance. String keys are mapped to name-based UUIDs, so the same key
+/// always addresses the same actor.
+pub trait GrainKey {
+    fn grain_id(&self) -> Uuid;
+}
+
+impl GrainKey for Uuid {
+    fn grain_id(&self) -> Uuid {
+        *self
+    }
+}
+
+impl<'a> GrainKey for &'a str {
+    fn grain_id(&self) -> Uuid {
+        Uuid::new_v5(&NAMESPACE_OID, self)
+    }
+}
+
+impl GrainKey for String {
+    fn grain_id(&self) -> Uuid {
+        self.as_str().grain_id()
+    }
+}
@@ -1,21 +1,28 @@
 #![feature(conservative_impl_trait)]
+#![feature(core_intrinsics)]
 #![feature(fnbox)]
+#![feature(on_unimplemented)]
 #![feature(proc_macro)]
 #![recursion_limit = "1024"]
 
 extern crate akio_syntax;
+extern crate bincode;
+extern crate bytes;
 #[cfg(target_os = "linux")]
 extern crate core_affinity;
+extern crate env_logger;
 #[macro_use]
 extern crate error_chain;
 extern crate futures;
 #[macro_use]
-extern crate lazy_static;
+extern crate log;
 #[cfg(not(target_os = "linux"))]
 extern crate num_cpus;
 extern crate parking_lot;
 extern crate rand;
+extern crate serde;
 extern crate tokio_core;
+extern crate tokio_io;
 extern crate typemap;
 extern crate uuid;
 
@@ -24,14 +31,37 @@ mod actor_cell;
 mod actor_container;
 mod actor_factory;
 mod actor_ref;
+mod actor_stream;
 mod actor_system;
 pub mod context;
+mod continuation;
+mod dead_letter;
 mod dispatcher;
+mod durable_mailbox;
+mod envelope;
 pub mod errors;
+mod event_stream;
+mod grain;
+pub mod logging;
 mod mailbox;
+pub mod metrics;
+mod metrics_exporter;
+pub mod persistence;
+mod pipe_to;
+mod pool;
 pub mod prelude;
+mod recipient;
+pub mod remote;
+mod responder;
+mod router;
+pub mod serialization;
+mod span;
+mod stream_handler;
+pub mod testkit;
+mod util;
 
 pub use actor::Actor;
+pub use actor::ActorMessage;
 pub use actor::ActorResponse;
 pub use actor::MessageHandler;
 use actor_cell::ActorCell;
@@ -39,8 +69,30 @@ use actor_cell::ActorCellHandle;
 use actor_container::ActorContainer;
 use actor_factory::create_actor;
 pub use actor_ref::ActorRef;
+pub use actor_stream::ActorStream;
 pub use actor_system::ActorSystem;
+pub use continuation::Continuation;
+pub use dead_letter::DeadLetter;
 use dispatcher::Dispatcher;
+pub use envelope::{Envelope, Headers};
+pub use event_stream::{ActorStarted, ActorStopped};
+pub use grain::GrainKey;
 use mailbox::Mailbox;
 use mailbox::MailboxMessage;
 use mailbox::SystemMessage;
+pub use pipe_to::PipeTo;
+pub use pool::{Pool, PoolConfig};
+pub use recipient::{AskRecipient, Recipient};
+pub use responder::Responder;
+pub use router::{
+    Broadcast,
+    ConsistentHash,
+    ConsistentHashKey,
+    Random,
+    RoundRobin,
+    Router,
+    RoutingLogic,
+    SmallestMailbox,
+};
+pub use span::Span;
+pub use stream_handler::StreamHandler;
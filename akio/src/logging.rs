@@ -0,0 +1,42 @@
+//! Log output attributed to actors.
+//!
+//! Akio logs through the `log` facade. `init` installs an `env_logger` whose
+//! lines are tagged with the current `Span`, so anything logged from inside a
+//! handler names the actor and message that produced it.
+
+use super::Span;
+use super::errors::*;
+use env_logger::LogBuilder;
+use log::LogRecord;
+use std::env;
+
+/// Installs a logger configured by `RUST_LOG` that tags lines with the
+/// current actor span.
+pub fn init() -> Result<()> {
+    let mut builder = LogBuilder::new();
+    builder.format(format_record);
+    if let Ok(spec) = env::var("RUST_LOG") {
+        builder.parse(&spec);
+    }
+    builder.init()?;
+    Ok(())
+}
+
+/// Formats a record the way `init` does, for use with other loggers.
+pub fn format_record(record: &LogRecord) -> String {
+    match Span::current() {
+        Some(span) => format!(
+            "{}:{}: [{}] {}",
+            record.level(),
+            record.location().module_path(),
+            span,
+            record.args()
+        ),
+        None => format!(
+            "{}:{}: {}",
+            record.level(),
+            record.location().module_path(),
+            record.args()
+        ),
+    }
+}
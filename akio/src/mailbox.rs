@@ -1,14 +1,42 @@
-use super::{Actor, ActorResponse, MessageHandler};
+use super::{Actor, ActorCellHandle, ActorResponse, Envelope, MessageHandler};
+use super::continuation::{complete_response, ContinuationMessage};
+use super::responder::with_pending_promise;
+use super::util::type_name;
 use futures::sync::oneshot;
 use std::collections::VecDeque;
 
 pub enum SystemMessage {
     Stop(oneshot::Sender<()>),
+    /// Stops the actor if it is still idle.
+    Passivate,
+    Watch(oneshot::Sender<()>),
+}
+
+impl SystemMessage {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SystemMessage::Stop(_) => "Stop",
+            SystemMessage::Passivate => "Passivate",
+            SystemMessage::Watch(_) => "Watch",
+        }
+    }
 }
 
 pub(crate) enum MailboxMessage<A> {
     User(UserMessageWrapper<A>),
     System(SystemMessage),
+    Continuation(ContinuationMessage<A>),
+}
+
+impl<A> MailboxMessage<A> {
+    /// Whether the message may be handled while the mailbox is suspended.
+    fn bypasses_suspension(&self) -> bool {
+        match *self {
+            MailboxMessage::User(_) => false,
+            MailboxMessage::System(_) => true,
+            MailboxMessage::Continuation(ref continuation) => !continuation.is_reentrant(),
+        }
+    }
 }
 
 pub(crate) struct UserMessageWrapper<A>(Box<UserMessage<A>>);
@@ -17,28 +45,55 @@ impl<A> UserMessageWrapper<A>
 where
     A: Actor,
 {
-    pub fn make<M>(message: M, promise: Option<oneshot::Sender<ActorResponse<A::Response>>>) -> Self
+    pub fn new(message: Box<UserMessage<A>>) -> Self {
+        UserMessageWrapper(message)
+    }
+
+    pub fn make<M>(
+        envelope: Envelope<M>,
+        promise: Option<oneshot::Sender<ActorResponse<A::Response>>>,
+    ) -> Self
     where
         M: Send + 'static,
         A: MessageHandler<M>,
     {
-        UserMessageWrapper(Box::new(LocalUserMessage::new(message, promise)))
+        UserMessageWrapper(Box::new(LocalUserMessage::new(envelope, promise)))
+    }
+
+    /// A message handled by calling `handler` with the actor, for messages
+    /// that don't go through a `MessageHandler` impl.
+    pub fn from_fn<F>(message_type: &'static str, handler: F) -> Self
+    where
+        F: FnOnce(&mut A) + Send + 'static,
+    {
+        UserMessageWrapper(Box::new(FnUserMessage {
+            message_type: message_type,
+            handler: Some(handler),
+        }))
+    }
+
+    /// Returns whether the mailbox should be suspended until the handler's
+    /// continuation has run.
+    pub fn handle(&mut self, actor: &mut A, cell: &ActorCellHandle<A>) -> bool {
+        self.0.handle(actor, cell)
     }
 
-    pub fn handle(&mut self, actor: &mut A) {
-        self.0.handle(actor)
+    pub fn message_type(&self) -> &'static str {
+        self.0.message_type()
     }
 }
 
-trait UserMessage<A>: Send {
-    fn handle(&mut self, actor: &mut A);
+pub(crate) trait UserMessage<A>: Send {
+    fn handle(&mut self, actor: &mut A, cell: &ActorCellHandle<A>) -> bool;
+
+    fn message_type(&self) -> &'static str;
 }
 
 struct LocalUserMessage<A, M>
 where
     A: MessageHandler<M>,
 {
-    message: Option<M>,
+    envelope: Option<Envelope<M>>,
     promise: Option<oneshot::Sender<ActorResponse<A::Response>>>,
 }
 
@@ -46,9 +101,12 @@ impl<A, M> LocalUserMessage<A, M>
 where
     A: MessageHandler<M>,
 {
-    pub fn new(message: M, promise: Option<oneshot::Sender<ActorResponse<A::Response>>>) -> Self {
+    pub fn new(
+        envelope: Envelope<M>,
+        promise: Option<oneshot::Sender<ActorResponse<A::Response>>>,
+    ) -> Self {
         Self {
-            message: Some(message),
+            envelope: Some(envelope),
             promise: promise,
         }
     }
@@ -59,16 +117,48 @@ where
     A: Actor + MessageHandler<M>,
     M: Send,
 {
-    fn handle(&mut self, actor: &mut A) {
-        if let Some(message) = self.message.take() {
-            let response = actor.handle_message(message);
-            self.promise.take().map(|promise| promise.send(response));
+    fn handle(&mut self, actor: &mut A, cell: &ActorCellHandle<A>) -> bool {
+        match self.envelope.take() {
+            Some(envelope) => {
+                let _headers = envelope.headers.enter();
+                let message = envelope.message;
+                let (response, promise) =
+                    with_pending_promise(self.promise.take(), || actor.handle_message(message));
+                complete_response(cell, response, promise)
+            }
+            None => false,
         }
     }
+
+    fn message_type(&self) -> &'static str {
+        type_name::<M>()
+    }
+}
+
+struct FnUserMessage<F> {
+    message_type: &'static str,
+    handler: Option<F>,
+}
+
+impl<A, F> UserMessage<A> for FnUserMessage<F>
+where
+    F: FnOnce(&mut A) + Send,
+{
+    fn handle(&mut self, actor: &mut A, _cell: &ActorCellHandle<A>) -> bool {
+        self.handler.take().map(|handler| handler(actor));
+        false
+    }
+
+    fn message_type(&self) -> &'static str {
+        self.message_type
+    }
 }
 
 pub(crate) struct Mailbox<A> {
     messages: VecDeque<MailboxMessage<A>>,
+    // Set while a non-reentrant handler waits on its continuation. Only
+    // system messages and that continuation are delivered in the meantime.
+    suspended: bool,
 }
 
 impl<A> Mailbox<A>
@@ -78,24 +168,46 @@ where
     pub fn new() -> Self {
         Self {
             messages: VecDeque::new(),
+            suspended: false,
         }
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.messages.is_empty()
+    /// Whether `pop` would return a message.
+    pub fn has_ready(&self) -> bool {
+        if self.suspended {
+            self.messages
+                .iter()
+                .any(MailboxMessage::bypasses_suspension)
+        } else {
+            !self.messages.is_empty()
+        }
     }
 
-    pub fn push<M>(
-        &mut self,
-        message: M,
-        promise: Option<oneshot::Sender<ActorResponse<A::Response>>>,
-    ) where
-        A: MessageHandler<M>,
-        M: Send + 'static,
-    {
-        self.messages.push_back(MailboxMessage::User(
-            UserMessageWrapper::make(message, promise),
-        ))
+    pub fn suspend(&mut self) {
+        self.suspended = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.suspended = false;
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn push_user_message(&mut self, message: UserMessageWrapper<A>) {
+        self.messages.push_back(MailboxMessage::User(message))
+    }
+
+    /// Puts `messages` ahead of those already waiting, keeping their order.
+    pub fn push_user_messages_first(&mut self, messages: Vec<UserMessageWrapper<A>>) {
+        for message in messages.into_iter().rev() {
+            self.messages.push_front(MailboxMessage::User(message))
+        }
     }
 
     pub fn push_system_message(&mut self, system_message: SystemMessage) {
@@ -103,7 +215,23 @@ where
             .push_back(MailboxMessage::System(system_message))
     }
 
+    pub fn push_continuation(&mut self, continuation: ContinuationMessage<A>) {
+        self.messages
+            .push_back(MailboxMessage::Continuation(continuation))
+    }
+
     pub fn pop(&mut self) -> Option<MailboxMessage<A>> {
-        self.messages.pop_front()
+        if self.suspended {
+            self.messages
+                .iter()
+                .position(MailboxMessage::bypasses_suspension)
+                .and_then(|index| self.messages.remove(index))
+        } else {
+            self.messages.pop_front()
+        }
+    }
+
+    pub fn drain(&mut self) -> Vec<MailboxMessage<A>> {
+        self.messages.drain(..).collect()
     }
 }
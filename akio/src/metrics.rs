@@ -0,0 +1,325 @@
+//! In-process metrics for an `ActorSystem`.
+//!
+//! Metrics are registered by name and label set on a `MetricsRegistry` and
+//! handed out as cheap, cloneable handles so hot paths such as message
+//! processing only touch atomics.
+
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use std::time::Duration;
+
+const LATENCY_BUCKETS: &[f64] = &[0.00001, 0.0001, 0.001, 0.01, 0.1, 1.0, 10.0];
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MetricKey {
+    pub name: &'static str,
+    pub labels: Vec<(&'static str, String)>,
+}
+
+impl MetricKey {
+    fn new(name: &'static str, labels: &[(&'static str, &str)]) -> Self {
+        Self {
+            name: name,
+            labels: labels
+                .iter()
+                .map(|&(key, value)| (key, value.to_string()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Counter {
+    value: Arc<AtomicUsize>,
+}
+
+impl Counter {
+    fn new() -> Self {
+        Self {
+            value: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.add(1)
+    }
+
+    pub fn add(&self, n: usize) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> usize {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Gauge {
+    value: Arc<AtomicIsize>,
+}
+
+impl Gauge {
+    fn new() -> Self {
+        Self {
+            value: Arc::new(AtomicIsize::new(0)),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.add(1)
+    }
+
+    pub fn dec(&self) {
+        self.add(-1)
+    }
+
+    pub fn add(&self, n: isize) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn set(&self, n: isize) {
+        self.value.store(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> isize {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+struct HistogramInner {
+    bounds: Vec<f64>,
+    buckets: Vec<AtomicUsize>,
+    sum_nanos: AtomicUsize,
+    count: AtomicUsize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    inner: Arc<HistogramInner>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramSnapshot {
+    /// Cumulative counts for each upper bound, in seconds.
+    pub buckets: Vec<(f64, usize)>,
+    pub sum: f64,
+    pub count: usize,
+}
+
+impl Histogram {
+    fn new(bounds: &[f64]) -> Self {
+        let inner = HistogramInner {
+            bounds: bounds.to_vec(),
+            buckets: bounds.iter().map(|_| AtomicUsize::new(0)).collect(),
+            sum_nanos: AtomicUsize::new(0),
+            count: AtomicUsize::new(0),
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let nanos = duration.as_secs() as usize * 1_000_000_000 + duration.subsec_nanos() as usize;
+        let seconds = nanos as f64 / 1_000_000_000.0;
+        let maybe_bucket = self.inner.bounds.iter().position(|bound| seconds <= *bound);
+        if let Some(i) = maybe_bucket {
+            self.inner.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.inner.sum_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.inner.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = self.inner
+            .bounds
+            .iter()
+            .zip(self.inner.buckets.iter())
+            .map(|(bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+        HistogramSnapshot {
+            buckets: buckets,
+            sum: self.inner.sum_nanos.load(Ordering::Relaxed) as f64 / 1_000_000_000.0,
+            count: self.inner.count.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Metric {
+    Counter(Counter),
+    Gauge(Gauge),
+    Histogram(Histogram),
+}
+
+impl Metric {
+    fn value(&self) -> MetricValue {
+        match *self {
+            Metric::Counter(ref counter) => MetricValue::Counter(counter.get()),
+            Metric::Gauge(ref gauge) => MetricValue::Gauge(gauge.get()),
+            Metric::Histogram(ref histogram) => MetricValue::Histogram(histogram.snapshot()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetricValue {
+    Counter(usize),
+    Gauge(isize),
+    Histogram(HistogramSnapshot),
+}
+
+#[derive(Clone, Debug)]
+pub struct MetricsRegistry {
+    metrics: Arc<RwLock<BTreeMap<MetricKey, Metric>>>,
+}
+
+impl MetricsRegistry {
+    pub fn new() -> Self {
+        Self {
+            metrics: Arc::new(RwLock::new(BTreeMap::new())),
+        }
+    }
+
+    pub fn counter(&self, name: &'static str, labels: &[(&'static str, &str)]) -> Counter {
+        match self.get_or_insert(name, labels, || Metric::Counter(Counter::new())) {
+            Metric::Counter(counter) => counter,
+            _ => panic!("metric '{}' is not a counter", name),
+        }
+    }
+
+    pub fn gauge(&self, name: &'static str, labels: &[(&'static str, &str)]) -> Gauge {
+        match self.get_or_insert(name, labels, || Metric::Gauge(Gauge::new())) {
+            Metric::Gauge(gauge) => gauge,
+            _ => panic!("metric '{}' is not a gauge", name),
+        }
+    }
+
+    pub fn histogram(&self, name: &'static str, labels: &[(&'static str, &str)]) -> Histogram {
+        let make = || Metric::Histogram(Histogram::new(LATENCY_BUCKETS));
+        match self.get_or_insert(name, labels, make) {
+            Metric::Histogram(histogram) => histogram,
+            _ => panic!("metric '{}' is not a histogram", name),
+        }
+    }
+
+    pub fn get(&self, name: &'static str, labels: &[(&'static str, &str)]) -> Option<MetricValue> {
+        self.metrics
+            .read()
+            .get(&MetricKey::new(name, labels))
+            .map(Metric::value)
+    }
+
+    pub fn snapshot(&self) -> Vec<(MetricKey, MetricValue)> {
+        self.metrics
+            .read()
+            .iter()
+            .map(|(key, metric)| (key.clone(), metric.value()))
+            .collect()
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut last_name = None;
+        for (key, value) in self.snapshot() {
+            if last_name != Some(key.name) {
+                let type_name = match value {
+                    MetricValue::Counter(_) => "counter",
+                    MetricValue::Gauge(_) => "gauge",
+                    MetricValue::Histogram(_) => "histogram",
+                };
+                let _ = writeln!(out, "# TYPE {} {}", key.name, type_name);
+                last_name = Some(key.name);
+            }
+            match value {
+                MetricValue::Counter(n) => {
+                    let _ = writeln!(out, "{}{} {}", key.name, render_labels(&key.labels, None), n);
+                }
+                MetricValue::Gauge(n) => {
+                    let _ = writeln!(out, "{}{} {}", key.name, render_labels(&key.labels, None), n);
+                }
+                MetricValue::Histogram(snapshot) => render_histogram(&mut out, &key, &snapshot),
+            }
+        }
+        out
+    }
+
+    fn get_or_insert<F>(&self, name: &'static str, labels: &[(&'static str, &str)], make: F) -> Metric
+    where
+        F: FnOnce() -> Metric,
+    {
+        let key = MetricKey::new(name, labels);
+        if let Some(metric) = self.metrics.read().get(&key) {
+            return metric.clone();
+        }
+        self.metrics
+            .write()
+            .entry(key)
+            .or_insert_with(make)
+            .clone()
+    }
+}
+
+fn render_labels(labels: &[(&'static str, String)], le: Option<String>) -> String {
+    let mut rendered = labels
+        .iter()
+        .map(|&(key, ref value)| format!("{}=\"{}\"", key, escape_label_value(value)))
+        .collect::<Vec<String>>();
+    if let Some(le) = le {
+        rendered.push(format!("le=\"{}\"", le));
+    }
+    if rendered.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", rendered.join(","))
+    }
+}
+
+/// Escapes what the Prometheus text format doesn't allow in a label value.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn render_histogram(out: &mut String, key: &MetricKey, snapshot: &HistogramSnapshot) {
+    for &(bound, count) in &snapshot.buckets {
+        let labels = render_labels(&key.labels, Some(format!("{}", bound)));
+        let _ = writeln!(out, "{}_bucket{} {}", key.name, labels, count);
+    }
+    let inf_labels = render_labels(&key.labels, Some("+Inf".to_string()));
+    let _ = writeln!(out, "{}_bucket{} {}", key.name, inf_labels, snapshot.count);
+    let labels = render_labels(&key.labels, None);
+    let _ = writeln!(out, "{}_sum{} {}", key.name, labels, snapshot.sum);
+    let _ = writeln!(out, "{}_count{} {}", key.name, labels, snapshot.count);
+}
+
+/// Metric handles for a single actor type, resolved once when an actor is
+/// created.
+#[derive(Clone, Debug)]
+pub(crate) struct ActorMetrics {
+    pub actors: Gauge,
+    pub mailbox_depth: Gauge,
+    pub processing_time: Histogram,
+}
+
+impl ActorMetrics {
+    pub fn new(registry: &MetricsRegistry, actor_type: &str) -> Self {
+        let labels = &[("actor_type", actor_type)];
+        Self {
+            actors: registry.gauge("akio_actors", labels),
+            mailbox_depth: registry.gauge("akio_mailbox_depth", labels),
+            processing_time: registry.histogram("akio_message_processing_seconds", labels),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use super::errors::*;
+use super::metrics::MetricsRegistry;
+use futures::prelude::*;
+use std::net;
+use std::net::SocketAddr;
+use std::thread;
+use tokio_core::net::TcpListener;
+use tokio_core::reactor::Core;
+use tokio_io::io;
+
+/// Serves the registry in the Prometheus text format on `addr` from a
+/// dedicated thread. Returns the bound address, which is useful when binding
+/// to port 0.
+pub fn serve_metrics(registry: MetricsRegistry, addr: &SocketAddr) -> Result<SocketAddr> {
+    let std_listener = net::TcpListener::bind(addr)?;
+    let local_addr = std_listener.local_addr()?;
+    thread::spawn(move || {
+        let mut core = Core::new().expect("Failed to start metrics exporter");
+        let handle = core.handle();
+        let listener = TcpListener::from_listener(std_listener, &local_addr, &handle)
+            .expect("Failed to listen for metrics requests");
+        let server = listener.incoming().for_each(|(socket, _)| {
+            let body = registry.render_prometheus();
+            let response = format!(
+                "HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                 Content-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let f = io::read(socket, vec![0; 4096])
+                .and_then(move |(socket, _, _)| io::write_all(socket, response))
+                .then(|_| Ok(()));
+            handle.spawn(f);
+            Ok(())
+        });
+        let _ = core.run(server);
+    });
+    Ok(local_addr)
+}
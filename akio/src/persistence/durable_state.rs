@@ -0,0 +1,152 @@
+use super::super::{context, Actor, ActorSystem};
+use super::super::errors::*;
+use super::super::util::type_name;
+use bincode;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::Arc;
+use typemap::Key;
+
+/// A state as stored in a `StateStore`, along with how many times it has
+/// been saved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersionedState {
+    pub version: u64,
+    pub payload: Vec<u8>,
+}
+
+/// Storage for the latest state of durable state actors.
+pub trait StateStore: Send + Sync + 'static {
+    fn load(&self, persistence_id: &str) -> Result<Option<VersionedState>>;
+
+    /// Durably replaces the state of `persistence_id`, returning its new
+    /// version. Fails with `ErrorKind::VersionConflict` unless the stored
+    /// version is `expected_version`, which is 0 for a state never saved.
+    fn save(&self, persistence_id: &str, expected_version: u64, payload: Vec<u8>) -> Result<u64>;
+}
+
+/// Keeps states in memory, for tests. Clones share the same states.
+#[derive(Clone, Default)]
+pub struct MemoryStateStore {
+    states: Arc<Mutex<HashMap<String, VersionedState>>>,
+}
+
+impl MemoryStateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StateStore for MemoryStateStore {
+    fn load(&self, persistence_id: &str) -> Result<Option<VersionedState>> {
+        Ok(self.states.lock().get(persistence_id).cloned())
+    }
+
+    fn save(&self, persistence_id: &str, expected_version: u64, payload: Vec<u8>) -> Result<u64> {
+        let mut states = self.states.lock();
+        let version = states.get(persistence_id).map_or(0, |state| state.version);
+        if version != expected_version {
+            bail!(ErrorKind::VersionConflict(
+                persistence_id.to_string(),
+                expected_version,
+                version
+            ));
+        }
+        states.insert(
+            persistence_id.to_string(),
+            VersionedState {
+                version: version + 1,
+                payload: payload,
+            },
+        );
+        Ok(version + 1)
+    }
+}
+
+/// The state of a durable state actor and the version it was loaded or
+/// last saved at.
+pub struct DurableState<S> {
+    state: S,
+    version: u64,
+}
+
+impl<S> DurableState<S> {
+    /// The state of an actor that has never been saved.
+    pub fn new(initial: S) -> Self {
+        Self {
+            state: initial,
+            version: 0,
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+impl<S> Deref for DurableState<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.state
+    }
+}
+
+/// An actor whose state is loaded from a `StateStore` when it starts and
+/// saved whole after each change. Register a store for the actor type with
+/// `ActorSystem::register_state_store`; messages sent to the actor wait until
+/// its state has been loaded.
+pub trait DurableStateActor: Actor {
+    type State: Serialize + DeserializeOwned + Send + 'static;
+
+    /// Identifies the actor's state in the store. It must stay the same
+    /// across restarts.
+    fn persistence_id(&self) -> String;
+
+    fn durable_state(&mut self) -> &mut DurableState<Self::State>;
+
+    /// Saves `state` and makes it the actor's state. Fails, leaving the
+    /// state unchanged, if another writer has saved this actor's state since
+    /// it was loaded.
+    fn persist_state(&mut self, state: Self::State) -> Result<()> {
+        let store = context::system()
+            .state_store::<Self>()
+            .ok_or_else(|| ErrorKind::NoStateStore(type_name::<Self>()))?;
+        let payload = bincode::serialize(&state, bincode::Infinite)?;
+        let persistence_id = self.persistence_id();
+        let durable = self.durable_state();
+        durable.version = store.save(&persistence_id, durable.version, payload)?;
+        durable.state = state;
+        Ok(())
+    }
+}
+
+pub(crate) struct StateStoreKey<A> {
+    _actor: PhantomData<A>,
+}
+
+impl<A> Key for StateStoreKey<A>
+where
+    A: Actor,
+{
+    type Value = Arc<StateStore>;
+}
+
+pub(crate) fn load_state<A>(actor: &mut A, system: &ActorSystem) -> Result<()>
+where
+    A: DurableStateActor,
+{
+    let store = system
+        .state_store::<A>()
+        .ok_or_else(|| ErrorKind::NoStateStore(type_name::<A>()))?;
+    if let Some(stored) = store.load(&actor.persistence_id())? {
+        let durable = actor.durable_state();
+        durable.state = bincode::deserialize(&stored.payload)?;
+        durable.version = stored.version;
+    }
+    Ok(())
+}
@@ -0,0 +1,236 @@
+use super::{file_name, write_atomically};
+use super::journal::{Journal, JournalEntry};
+use super::super::errors::*;
+use bytes::{Buf, BufMut, LittleEndian};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::u32;
+
+// Each record is the event's sequence number, the payload length and a
+// CRC-32 of the three, followed by the payload.
+const HEADER_LEN: usize = 16;
+const CHECKSUMMED_HEADER_LEN: usize = 12;
+// Stands in for the payload length of a record marking that the events up
+// to its sequence number were deleted.
+const DELETED_MARKER: u32 = u32::MAX;
+
+/// Stores the events of each persistence id in its own append-only file
+/// under a directory. Appends are flushed to disk before they are
+/// acknowledged. Clones share the same open files.
+#[derive(Clone)]
+pub struct FileJournal {
+    directory: PathBuf,
+    files: Arc<Mutex<HashMap<String, JournalFile>>>,
+}
+
+struct JournalFile {
+    path: PathBuf,
+    file: File,
+    last_sequence_nr: u64,
+}
+
+impl FileJournal {
+    pub fn open<P>(directory: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        fs::create_dir_all(directory.as_ref())?;
+        Ok(Self {
+            directory: directory.as_ref().to_path_buf(),
+            files: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    fn path(&self, persistence_id: &str) -> PathBuf {
+        self.directory.join(format!("{}.journal", file_name(persistence_id)))
+    }
+
+    fn with_file<F, R>(&self, persistence_id: &str, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut JournalFile) -> Result<R>,
+    {
+        let mut files = self.files.lock();
+        if !files.contains_key(persistence_id) {
+            let file = JournalFile::open(self.path(persistence_id))?;
+            files.insert(persistence_id.to_string(), file);
+        }
+        f(files.get_mut(persistence_id).unwrap())
+    }
+}
+
+impl Journal for FileJournal {
+    fn append(&self, persistence_id: &str, events: Vec<Vec<u8>>) -> Result<u64> {
+        self.with_file(persistence_id, |journal| journal.append(events))
+    }
+
+    fn replay(&self, persistence_id: &str, from_sequence_nr: u64) -> Result<Vec<JournalEntry>> {
+        self.with_file(persistence_id, |journal| {
+            let (entries, _) = journal.read()?;
+            Ok(entries
+                .into_iter()
+                .filter(|entry| entry.sequence_nr >= from_sequence_nr)
+                .collect())
+        })
+    }
+
+    fn highest_sequence_nr(&self, persistence_id: &str) -> Result<u64> {
+        self.with_file(persistence_id, |journal| Ok(journal.last_sequence_nr))
+    }
+
+    fn delete_to(&self, persistence_id: &str, to_sequence_nr: u64) -> Result<()> {
+        self.with_file(persistence_id, |journal| journal.delete_to(to_sequence_nr))
+    }
+}
+
+impl JournalFile {
+    fn open(path: PathBuf) -> Result<Self> {
+        let file = Self::open_append(&path)?;
+        let mut journal = Self {
+            path: path,
+            file: file,
+            last_sequence_nr: 0,
+        };
+        let (entries, deleted_to) = journal.read()?;
+        journal.last_sequence_nr = entries
+            .last()
+            .map_or(deleted_to, |entry| entry.sequence_nr);
+        Ok(journal)
+    }
+
+    fn open_append(path: &Path) -> Result<File> {
+        Ok(OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?)
+    }
+
+    fn append(&mut self, events: Vec<Vec<u8>>) -> Result<u64> {
+        let mut buffer = Vec::new();
+        let mut sequence_nr = self.last_sequence_nr;
+        for payload in events {
+            sequence_nr += 1;
+            write_record(&mut buffer, sequence_nr, &payload);
+        }
+        let len = self.file.metadata()?.len();
+        let written = self.file
+            .write_all(&buffer)
+            .and_then(|_| self.file.sync_data());
+        if let Err(e) = written {
+            // Otherwise later appends would follow a partial record.
+            if let Err(truncate_error) = self.file.set_len(len) {
+                error!(
+                    "Failed to remove partial append to '{}': {}",
+                    self.path.display(),
+                    truncate_error
+                );
+            }
+            return Err(e.into());
+        }
+        self.last_sequence_nr = sequence_nr;
+        Ok(sequence_nr)
+    }
+
+    /// Rewrites the file without the deleted events. It is replaced only once
+    /// the new one is on disk, so a crash leaves one or the other.
+    fn delete_to(&mut self, to_sequence_nr: u64) -> Result<()> {
+        let (entries, deleted_to) = self.read()?;
+        if to_sequence_nr <= deleted_to {
+            return Ok(());
+        }
+        let mut buffer = Vec::new();
+        write_header(&mut buffer, to_sequence_nr, DELETED_MARKER, &[]);
+        for entry in entries {
+            if entry.sequence_nr > to_sequence_nr {
+                write_record(&mut buffer, entry.sequence_nr, &entry.payload);
+            }
+        }
+        write_atomically(&self.path, &buffer)?;
+        self.file = Self::open_append(&self.path)?;
+        Ok(())
+    }
+
+    /// Reads every complete record, along with the sequence number events
+    /// have been deleted up to. The last record, if a crash cut it short or
+    /// garbled it while it was appended, is removed. Any other damaged
+    /// record is an error.
+    fn read(&mut self) -> Result<(Vec<JournalEntry>, u64)> {
+        let mut contents = Vec::new();
+        File::open(&self.path)?.read_to_end(&mut contents)?;
+        let mut cursor = Cursor::new(&contents[..]);
+        let mut entries = Vec::new();
+        let mut deleted_to = 0;
+        let mut valid_len = 0;
+        while cursor.remaining() >= HEADER_LEN {
+            let header = cursor.position() as usize;
+            let sequence_nr = cursor.get_u64::<LittleEndian>();
+            let len = cursor.get_u32::<LittleEndian>();
+            let expected_checksum = cursor.get_u32::<LittleEndian>();
+            let payload_len = if len == DELETED_MARKER { 0 } else { len as usize };
+            if cursor.remaining() < payload_len {
+                break;
+            }
+            let start = cursor.position() as usize;
+            let end = start + payload_len;
+            let checksummed = &contents[header..header + CHECKSUMMED_HEADER_LEN];
+            if checksum(&[checksummed, &contents[start..end]]) != expected_checksum {
+                if end < contents.len() {
+                    bail!(ErrorKind::CorruptJournal(
+                        self.path.display().to_string(),
+                        sequence_nr
+                    ));
+                }
+                break;
+            }
+            if len == DELETED_MARKER {
+                deleted_to = sequence_nr;
+            } else {
+                entries.push(JournalEntry {
+                    sequence_nr: sequence_nr,
+                    payload: contents[start..end].to_vec(),
+                });
+            }
+            cursor.advance(payload_len);
+            valid_len = cursor.position();
+        }
+        if valid_len < contents.len() as u64 {
+            warn!("Truncating incomplete record of '{}'", self.path.display());
+            self.file.set_len(valid_len)?;
+            self.file.sync_data()?;
+        }
+        Ok((entries, deleted_to))
+    }
+}
+
+fn write_record(buffer: &mut Vec<u8>, sequence_nr: u64, payload: &[u8]) {
+    write_header(buffer, sequence_nr, payload.len() as u32, payload);
+    buffer.put_slice(payload);
+}
+
+fn write_header(buffer: &mut Vec<u8>, sequence_nr: u64, len: u32, payload: &[u8]) {
+    let header = buffer.len();
+    buffer.put_u64::<LittleEndian>(sequence_nr);
+    buffer.put_u32::<LittleEndian>(len);
+    let checksum = checksum(&[&buffer[header..], payload]);
+    buffer.put_u32::<LittleEndian>(checksum);
+}
+
+/// The CRC-32 (IEEE) of `parts` one after the other.
+fn checksum(parts: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in parts.iter().flat_map(|part| part.iter()) {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,76 @@
+use super::{file_name, write_atomically};
+use super::snapshot::SnapshotStore;
+use super::super::errors::*;
+use std::fs::{self, File};
+use std::io::{ErrorKind as IoErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+/// Stores each snapshot in its own file, under a directory per persistence
+/// id.
+#[derive(Clone)]
+pub struct FileSnapshotStore {
+    directory: PathBuf,
+}
+
+impl FileSnapshotStore {
+    pub fn open<P>(directory: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        fs::create_dir_all(directory.as_ref())?;
+        Ok(Self {
+            directory: directory.as_ref().to_path_buf(),
+        })
+    }
+
+    fn directory(&self, persistence_id: &str) -> PathBuf {
+        self.directory.join(file_name(persistence_id))
+    }
+
+    fn path(&self, persistence_id: &str, sequence_nr: u64) -> PathBuf {
+        self.directory(persistence_id)
+            .join(format!("{:020}.snapshot", sequence_nr))
+    }
+}
+
+impl SnapshotStore for FileSnapshotStore {
+    /// Written to a temporary file first, so a crash never leaves a partial
+    /// snapshot behind.
+    fn save(&self, persistence_id: &str, sequence_nr: u64, payload: Vec<u8>) -> Result<()> {
+        fs::create_dir_all(self.directory(persistence_id))?;
+        write_atomically(&self.path(persistence_id, sequence_nr), &payload)
+    }
+
+    fn sequence_nrs(&self, persistence_id: &str) -> Result<Vec<u64>> {
+        let entries = match fs::read_dir(self.directory(persistence_id)) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == IoErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut sequence_nrs = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().map_or(false, |extension| extension == "snapshot") {
+                let sequence_nr = path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse().ok());
+                if let Some(sequence_nr) = sequence_nr {
+                    sequence_nrs.push(sequence_nr);
+                }
+            }
+        }
+        sequence_nrs.sort();
+        Ok(sequence_nrs)
+    }
+
+    fn load(&self, persistence_id: &str, sequence_nr: u64) -> Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        File::open(self.path(persistence_id, sequence_nr))?.read_to_end(&mut payload)?;
+        Ok(payload)
+    }
+
+    fn delete(&self, persistence_id: &str, sequence_nr: u64) -> Result<()> {
+        fs::remove_file(self.path(persistence_id, sequence_nr))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use super::{file_name, write_atomically};
+use super::durable_state::{StateStore, VersionedState};
+use super::super::errors::*;
+use bytes::{Buf, BufMut, LittleEndian};
+use parking_lot::Mutex;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Cursor, ErrorKind as IoErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a save waits for another writer of the same state.
+const LOCK_TIMEOUT_MILLIS: u64 = 5_000;
+/// Lock files older than this were left by writers that died holding them.
+const STALE_LOCK_SECS: u64 = 60;
+
+/// Stores each state in its own file, prefixed with its version. Saves to
+/// the same state are checked against each other whether they come from
+/// this store, another store opened on the same directory, or another
+/// process.
+#[derive(Clone)]
+pub struct FileStateStore {
+    directory: PathBuf,
+    // Saves in this process take turns before taking the lock file.
+    lock: Arc<Mutex<()>>,
+}
+
+/// A file created exclusively next to a state while it is being saved, and
+/// removed when dropped.
+struct LockFile {
+    path: PathBuf,
+}
+
+impl FileStateStore {
+    pub fn open<P>(directory: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        fs::create_dir_all(directory.as_ref())?;
+        Ok(Self {
+            directory: directory.as_ref().to_path_buf(),
+            lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    fn path(&self, persistence_id: &str) -> PathBuf {
+        self.directory.join(format!("{}.state", file_name(persistence_id)))
+    }
+
+    fn lock_path(&self, persistence_id: &str) -> PathBuf {
+        self.directory.join(format!("{}.state.lock", file_name(persistence_id)))
+    }
+
+    fn read(&self, persistence_id: &str) -> Result<Option<VersionedState>> {
+        let path = self.path(persistence_id);
+        let mut contents = Vec::new();
+        match File::open(&path) {
+            Ok(mut file) => file.read_to_end(&mut contents)?,
+            Err(ref e) if e.kind() == IoErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if contents.len() < 8 {
+            bail!("corrupt state file '{}'", path.display());
+        }
+        let mut cursor = Cursor::new(&contents[..]);
+        let version = cursor.get_u64::<LittleEndian>();
+        Ok(Some(VersionedState {
+            version: version,
+            payload: contents[8..].to_vec(),
+        }))
+    }
+}
+
+impl StateStore for FileStateStore {
+    /// States are replaced atomically, so loading doesn't wait for saves.
+    fn load(&self, persistence_id: &str) -> Result<Option<VersionedState>> {
+        self.read(persistence_id)
+    }
+
+    fn save(&self, persistence_id: &str, expected_version: u64, payload: Vec<u8>) -> Result<u64> {
+        let _lock = self.lock.lock();
+        let _lock_file = LockFile::acquire(self.lock_path(persistence_id))?;
+        let version = self.read(persistence_id)?
+            .map_or(0, |state| state.version);
+        if version != expected_version {
+            bail!(ErrorKind::VersionConflict(
+                persistence_id.to_string(),
+                expected_version,
+                version
+            ));
+        }
+        let mut contents = Vec::with_capacity(8 + payload.len());
+        contents.put_u64::<LittleEndian>(version + 1);
+        contents.put_slice(&payload);
+        write_atomically(&self.path(persistence_id), &contents)?;
+        Ok(version + 1)
+    }
+}
+
+impl LockFile {
+    /// Waits for other writers to remove the lock file, then creates it.
+    fn acquire(path: PathBuf) -> Result<Self> {
+        let deadline = Instant::now() + Duration::from_millis(LOCK_TIMEOUT_MILLIS);
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(LockFile { path: path }),
+                Err(ref e) if e.kind() == IoErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+            if Self::is_stale(&path) {
+                warn!("Removing stale lock file '{}'", path.display());
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            if Instant::now() >= deadline {
+                bail!(ErrorKind::StateLocked(path.display().to_string()));
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn is_stale(path: &Path) -> bool {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .map_or(false, |age| age > Duration::from_secs(STALE_LOCK_SECS))
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            error!("Failed to remove lock file '{}': {}", self.path.display(), e);
+        }
+    }
+}
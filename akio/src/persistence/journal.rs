@@ -0,0 +1,97 @@
+use super::super::errors::*;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// An event as stored in a `Journal`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JournalEntry {
+    pub sequence_nr: u64,
+    pub payload: Vec<u8>,
+}
+
+/// Append-only storage for the events of persistent actors. Each persistence
+/// id has its own sequence of events, numbered from 1.
+pub trait Journal: Send + Sync + 'static {
+    /// Durably appends `events` after the last event of `persistence_id`,
+    /// returning the sequence number of the last one appended.
+    fn append(&self, persistence_id: &str, events: Vec<Vec<u8>>) -> Result<u64>;
+
+    /// The events of `persistence_id` numbered `from_sequence_nr` or higher,
+    /// in order.
+    fn replay(&self, persistence_id: &str, from_sequence_nr: u64) -> Result<Vec<JournalEntry>>;
+
+    /// The sequence number of the last event appended for `persistence_id`,
+    /// or 0 if there is none. Deleting events doesn't change it.
+    fn highest_sequence_nr(&self, persistence_id: &str) -> Result<u64>;
+
+    /// Deletes the events of `persistence_id` numbered up to and including
+    /// `to_sequence_nr`.
+    fn delete_to(&self, persistence_id: &str, to_sequence_nr: u64) -> Result<()>;
+}
+
+/// Keeps events in memory, for tests. Clones share the same events.
+#[derive(Clone, Default)]
+pub struct MemoryJournal {
+    journals: Arc<Mutex<HashMap<String, MemoryEvents>>>,
+}
+
+#[derive(Default)]
+struct MemoryEvents {
+    entries: Vec<JournalEntry>,
+    highest_sequence_nr: u64,
+}
+
+impl MemoryJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Journal for MemoryJournal {
+    fn append(&self, persistence_id: &str, events: Vec<Vec<u8>>) -> Result<u64> {
+        let mut journals = self.journals.lock();
+        let journal = journals
+            .entry(persistence_id.to_string())
+            .or_insert_with(MemoryEvents::default);
+        for payload in events {
+            journal.highest_sequence_nr += 1;
+            journal.entries.push(JournalEntry {
+                sequence_nr: journal.highest_sequence_nr,
+                payload: payload,
+            });
+        }
+        Ok(journal.highest_sequence_nr)
+    }
+
+    fn replay(&self, persistence_id: &str, from_sequence_nr: u64) -> Result<Vec<JournalEntry>> {
+        Ok(self.journals
+            .lock()
+            .get(persistence_id)
+            .map(|journal| {
+                journal
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.sequence_nr >= from_sequence_nr)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_else(Vec::new))
+    }
+
+    fn highest_sequence_nr(&self, persistence_id: &str) -> Result<u64> {
+        Ok(self.journals
+            .lock()
+            .get(persistence_id)
+            .map_or(0, |journal| journal.highest_sequence_nr))
+    }
+
+    fn delete_to(&self, persistence_id: &str, to_sequence_nr: u64) -> Result<()> {
+        if let Some(journal) = self.journals.lock().get_mut(persistence_id) {
+            journal
+                .entries
+                .retain(|entry| entry.sequence_nr > to_sequence_nr);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use super::{context, Actor, ActorSystem};
+use super::errors::*;
+use super::util::type_name;
+use bincode;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use typemap::Key;
+
+mod durable_state;
+mod file_journal;
+mod file_snapshot_store;
+mod file_state_store;
+mod journal;
+mod snapshot;
+
+pub use self::durable_state::{
+    DurableState,
+    DurableStateActor,
+    MemoryStateStore,
+    StateStore,
+    VersionedState,
+};
+pub(crate) use self::durable_state::{load_state, StateStoreKey};
+pub use self::file_journal::FileJournal;
+pub use self::file_snapshot_store::FileSnapshotStore;
+pub use self::file_state_store::FileStateStore;
+pub use self::journal::{Journal, JournalEntry, MemoryJournal};
+pub use self::snapshot::{SnapshotActor, SnapshotPolicy, SnapshotStore};
+pub(crate) use self::snapshot::{Snapshots, SnapshotsKey};
+
+pub(crate) struct JournalKey<A> {
+    _actor: PhantomData<A>,
+}
+
+impl<A> Key for JournalKey<A>
+where
+    A: Actor,
+{
+    type Value = Arc<Journal>;
+}
+
+/// An actor whose state is built from the events it has persisted. Register
+/// a journal for the actor type with `ActorSystem::register_journal`; its
+/// events are then replayed through `apply` whenever the actor starts,
+/// before `on_start` runs and before any message is handled.
+pub trait PersistentActor: Actor {
+    type Event: Serialize + DeserializeOwned + Send + 'static;
+
+    /// Identifies the actor's events in the journal. It must stay the same
+    /// across restarts.
+    fn persistence_id(&self) -> String;
+
+    /// Updates the actor's state with an event, whether it was just persisted
+    /// or is being replayed.
+    fn apply(&mut self, event: &Self::Event);
+
+    /// Writes `event` to the journal, then applies it. Once this returns,
+    /// the event is durable and the handler can reply.
+    fn persist(&mut self, event: Self::Event) -> Result<()> {
+        self.persist_all(vec![event])
+    }
+
+    /// Like `persist`, but writes all `events` in one append.
+    fn persist_all(&mut self, events: Vec<Self::Event>) -> Result<()> {
+        let system = context::system();
+        let journal = system
+            .journal::<Self>()
+            .ok_or_else(|| ErrorKind::NoJournal(type_name::<Self>()))?;
+        let payloads = events
+            .iter()
+            .map(|event| bincode::serialize(event, bincode::Infinite))
+            .collect::<::std::result::Result<Vec<_>, _>>()?;
+        let last = journal.append(&self.persistence_id(), payloads)?;
+        events.iter().for_each(|event| self.apply(event));
+        if let Some(snapshots) = system.snapshots::<Self>() {
+            let first = last + 1 - events.len() as u64;
+            snapshots.persisted(self, &system, first, last);
+        }
+        Ok(())
+    }
+}
+
+/// Restores the latest snapshot, if the actor type has a snapshot store, and
+/// replays the events persisted after it.
+pub(crate) fn recover<A>(actor: &mut A, system: &ActorSystem) -> Result<()>
+where
+    A: PersistentActor,
+{
+    let journal = system
+        .journal::<A>()
+        .ok_or_else(|| ErrorKind::NoJournal(type_name::<A>()))?;
+    let snapshot_sequence_nr = match system.snapshots::<A>() {
+        Some(snapshots) => snapshots.recover(actor)?,
+        None => 0,
+    };
+    let entries = journal.replay(&actor.persistence_id(), snapshot_sequence_nr + 1)?;
+    debug!(
+        "Replaying {} events for '{}'",
+        entries.len(),
+        actor.persistence_id()
+    );
+    for entry in entries {
+        let event: A::Event = bincode::deserialize(&entry.payload)?;
+        actor.apply(&event);
+    }
+    Ok(())
+}
+
+/// Hex encodes `persistence_id`, so that any id makes a valid file name.
+fn file_name(persistence_id: &str) -> String {
+    persistence_id
+        .bytes()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Replaces the file at `path` with `contents`. They are written to a
+/// temporary file next to it and synced first, so a crash leaves either the
+/// old file or the new one, and the directory is synced after the rename so
+/// that the new file survives one.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let temporary_path = temporary_path(path);
+    {
+        let mut file = File::create(&temporary_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temporary_path, path)?;
+    if let Some(directory) = path.parent() {
+        sync_directory(directory)?;
+    }
+    Ok(())
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name()
+        .map_or_else(OsString::new, |name| name.to_os_string());
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(unix)]
+fn sync_directory(directory: &Path) -> Result<()> {
+    File::open(directory)?.sync_all()?;
+    Ok(())
+}
+
+// Directories can't be opened as files here.
+#[cfg(not(unix))]
+fn sync_directory(_: &Path) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use super::{Journal, PersistentActor};
+use super::super::{context, Actor, ActorSystem};
+use super::super::errors::*;
+use super::super::util::type_name;
+use bincode;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use typemap::Key;
+
+/// Storage for snapshots of persistent actors' state, each tagged with the
+/// sequence number of the last event it includes.
+pub trait SnapshotStore: Send + Sync + 'static {
+    /// Durably stores a snapshot of `persistence_id` taken at `sequence_nr`.
+    fn save(&self, persistence_id: &str, sequence_nr: u64, payload: Vec<u8>) -> Result<()>;
+
+    /// The sequence numbers of the stored snapshots of `persistence_id`, in
+    /// ascending order.
+    fn sequence_nrs(&self, persistence_id: &str) -> Result<Vec<u64>>;
+
+    fn load(&self, persistence_id: &str, sequence_nr: u64) -> Result<Vec<u8>>;
+
+    fn delete(&self, persistence_id: &str, sequence_nr: u64) -> Result<()>;
+}
+
+/// When snapshots are taken and how many are kept.
+#[derive(Clone, Debug)]
+pub struct SnapshotPolicy {
+    every: Option<u64>,
+    keep: usize,
+    delete_events: bool,
+}
+
+impl SnapshotPolicy {
+    /// Snapshots are only taken by `SnapshotActor::save_snapshot`, and the
+    /// latest two are kept.
+    pub fn new() -> Self {
+        Self {
+            every: None,
+            keep: 2,
+            delete_events: false,
+        }
+    }
+
+    /// Also takes a snapshot after every `events` persisted events.
+    pub fn every(self, events: u64) -> Self {
+        assert!(events > 0, "snapshot interval must be positive");
+        Self {
+            every: Some(events),
+            ..self
+        }
+    }
+
+    /// Keeps the latest `snapshots` snapshots, deleting older ones. Defaults
+    /// to 2.
+    pub fn keep(self, snapshots: usize) -> Self {
+        assert!(snapshots > 0, "at least one snapshot must be kept");
+        Self {
+            keep: snapshots,
+            ..self
+        }
+    }
+
+    /// Whether to delete the events included in the oldest snapshot kept,
+    /// which are no longer needed for recovery. Defaults to false.
+    pub fn delete_events(self, delete_events: bool) -> Self {
+        Self {
+            delete_events: delete_events,
+            ..self
+        }
+    }
+}
+
+impl Default for SnapshotPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A persistent actor that recovers from the latest snapshot of its state
+/// and the events persisted since, instead of replaying every event. Register
+/// a store for the actor type with `ActorSystem::register_snapshot_store`.
+pub trait SnapshotActor: PersistentActor {
+    type Snapshot: Serialize + DeserializeOwned + Send + 'static;
+
+    fn snapshot(&self) -> Self::Snapshot;
+
+    /// Replaces the actor's state with `snapshot`. The events persisted after
+    /// it are then applied.
+    fn restore(&mut self, snapshot: Self::Snapshot);
+
+    /// Takes a snapshot of the actor's current state.
+    fn save_snapshot(&self) -> Result<()> {
+        let system = context::system();
+        let snapshots = system
+            .snapshots::<Self>()
+            .ok_or_else(|| ErrorKind::NoSnapshotStore(type_name::<Self>()))?;
+        snapshots.save(self, &system)
+    }
+}
+
+pub(crate) struct SnapshotsKey<A> {
+    _actor: PhantomData<A>,
+}
+
+impl<A> Key for SnapshotsKey<A>
+where
+    A: Actor,
+{
+    type Value = Arc<Snapshots<A>>;
+}
+
+/// The snapshot settings of an actor type. Snapshots are taken and restored
+/// through functions of the `SnapshotActor` impl, so that code only bound by
+/// `PersistentActor` can use them.
+pub(crate) struct Snapshots<A> {
+    store: Arc<SnapshotStore>,
+    policy: SnapshotPolicy,
+    snapshot: fn(&A) -> Result<Vec<u8>>,
+    restore: fn(&mut A, &[u8]) -> Result<()>,
+}
+
+impl<A> Snapshots<A>
+where
+    A: PersistentActor,
+{
+    pub fn new<S>(store: S, policy: SnapshotPolicy) -> Self
+    where
+        A: SnapshotActor,
+        S: SnapshotStore,
+    {
+        Self {
+            store: Arc::new(store),
+            policy: policy,
+            snapshot: encode_snapshot::<A>,
+            restore: decode_snapshot::<A>,
+        }
+    }
+
+    /// Restores the latest snapshot, if any, returning the sequence number it
+    /// was taken at.
+    pub fn recover(&self, actor: &mut A) -> Result<u64> {
+        let persistence_id = actor.persistence_id();
+        match self.store.sequence_nrs(&persistence_id)?.last() {
+            Some(&sequence_nr) => {
+                let payload = self.store.load(&persistence_id, sequence_nr)?;
+                (self.restore)(actor, &payload)?;
+                Ok(sequence_nr)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Takes a snapshot if persisting the events numbered `first` to `last`
+    /// crossed the policy's interval.
+    pub fn persisted(&self, actor: &A, system: &ActorSystem, first: u64, last: u64) {
+        let every = match self.policy.every {
+            Some(every) => every,
+            None => return,
+        };
+        if last / every > (first - 1) / every {
+            if let Err(e) = self.save(actor, system) {
+                warn!("Failed to snapshot '{}': {}", actor.persistence_id(), e);
+            }
+        }
+    }
+
+    fn save(&self, actor: &A, system: &ActorSystem) -> Result<()> {
+        let journal = system
+            .journal::<A>()
+            .ok_or_else(|| ErrorKind::NoJournal(type_name::<A>()))?;
+        let persistence_id = actor.persistence_id();
+        let sequence_nr = journal.highest_sequence_nr(&persistence_id)?;
+        self.store
+            .save(&persistence_id, sequence_nr, (self.snapshot)(actor)?)?;
+        self.apply_retention(&persistence_id, &*journal)
+    }
+
+    fn apply_retention(&self, persistence_id: &str, journal: &Journal) -> Result<()> {
+        let sequence_nrs = self.store.sequence_nrs(persistence_id)?;
+        if sequence_nrs.len() > self.policy.keep {
+            let (deleted, _) = sequence_nrs.split_at(sequence_nrs.len() - self.policy.keep);
+            for &sequence_nr in deleted {
+                self.store.delete(persistence_id, sequence_nr)?;
+            }
+        }
+        if self.policy.delete_events {
+            let oldest_kept = sequence_nrs[sequence_nrs.len().saturating_sub(self.policy.keep)];
+            journal.delete_to(persistence_id, oldest_kept)?;
+        }
+        Ok(())
+    }
+}
+
+fn encode_snapshot<A>(actor: &A) -> Result<Vec<u8>>
+where
+    A: SnapshotActor,
+{
+    Ok(bincode::serialize(&actor.snapshot(), bincode::Infinite)?)
+}
+
+fn decode_snapshot<A>(actor: &mut A, payload: &[u8]) -> Result<()>
+where
+    A: SnapshotActor,
+{
+    actor.restore(bincode::deserialize(payload)?);
+    Ok(())
+}
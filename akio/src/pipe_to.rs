@@ -0,0 +1,31 @@
+use super::{context, Actor, ActorRef, MessageHandler};
+use futures::Future;
+
+/// Delivers the outcome of a future to an actor as a message.
+pub trait PipeTo: Future + Sized + 'static {
+    /// Once this future resolves, sends `to_message(result)` to `target`.
+    /// The constructor of a message generated by `#[actor_api]` for a method
+    /// taking a single `Result` argument can be passed as `to_message`. The
+    /// future is dropped if `target` stops first. Must be called from within
+    /// the actor system.
+    fn pipe_to<A, M, F>(self, target: &ActorRef<A>, to_message: F)
+    where
+        A: Actor + MessageHandler<M>,
+        M: Send + 'static,
+        F: FnOnce(Result<Self::Item, Self::Error>) -> M + 'static,
+    {
+        let target = target.clone();
+        let cancelled = target.terminated();
+        let piped = self.then(move |result| {
+            target.send(to_message(result));
+            Ok::<(), ()>(())
+        });
+        context::handle().spawn(piped.select2(cancelled).then(|_| Ok(())));
+    }
+}
+
+impl<F> PipeTo for F
+where
+    F: Future + 'static,
+{
+}
@@ -0,0 +1,228 @@
+use super::{context, Actor, ActorRef, Router};
+use super::metrics::{Counter, Gauge, MetricsRegistry};
+use super::util::type_name;
+use futures::future;
+use futures::prelude::*;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio_core::reactor::Interval;
+use uuid::Uuid;
+
+/// Size bounds and resizing behaviour of a `Pool`.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    min_size: usize,
+    max_size: usize,
+    pressure_threshold: usize,
+    resize_interval: Duration,
+}
+
+impl PoolConfig {
+    /// A pool of between `min_size` and `max_size` instances. It starts
+    /// with `min_size`, which must be at least 1: an empty pool has no
+    /// mailboxes to measure pressure by, so it would never grow.
+    pub fn new(min_size: usize, max_size: usize) -> Self {
+        assert!(min_size >= 1, "pool min_size must be at least 1");
+        assert!(
+            min_size <= max_size,
+            "pool min_size {} exceeds max_size {}",
+            min_size,
+            max_size
+        );
+        Self {
+            min_size: min_size,
+            max_size: max_size,
+            pressure_threshold: 10,
+            resize_interval: Duration::from_millis(100),
+        }
+    }
+
+    /// The pool grows when the number of queued messages per instance
+    /// exceeds `threshold`. Defaults to 10.
+    pub fn pressure_threshold(self, threshold: usize) -> Self {
+        Self {
+            pressure_threshold: threshold,
+            ..self
+        }
+    }
+
+    /// How often the pool checks whether to grow or shrink. It changes by at
+    /// most one instance per check. Defaults to 100ms.
+    pub fn resize_interval(self, interval: Duration) -> Self {
+        Self {
+            resize_interval: interval,
+            ..self
+        }
+    }
+}
+
+struct PoolMetrics {
+    size: Gauge,
+    grown: Counter,
+    shrunk: Counter,
+    replaced: Counter,
+}
+
+impl PoolMetrics {
+    fn new(registry: &MetricsRegistry, actor_type: &str) -> Self {
+        let labels = &[("actor_type", actor_type)];
+        Self {
+            size: registry.gauge("akio_pool_size", labels),
+            grown: registry.counter("akio_pool_grown_total", labels),
+            shrunk: registry.counter("akio_pool_shrunk_total", labels),
+            replaced: registry.counter("akio_pool_replaced_total", labels),
+        }
+    }
+}
+
+struct PoolState<A, L> {
+    router: Router<A, L>,
+    factory: Box<Fn() -> A + Send + Sync>,
+    config: PoolConfig,
+    stopped: AtomicBool,
+    metrics: PoolMetrics,
+}
+
+impl<A, L> PoolState<A, L>
+where
+    A: Actor,
+    L: Send + Sync + 'static,
+{
+    fn start_member(state: &Arc<Self>) {
+        let member = (state.factory)().start();
+        state.router.add_routee(member.clone());
+        state.metrics.size.inc();
+        let id = member.id();
+        let state = Arc::downgrade(state);
+        let watch = member.terminated().map(move |_| {
+            if let Some(state) = state.upgrade() {
+                Self::member_stopped(&state, id);
+            }
+        });
+        context::handle().spawn(watch);
+    }
+
+    fn member_stopped(state: &Arc<Self>, id: Uuid) {
+        // Members the pool removed itself are no longer routees.
+        if !state.router.remove_routee(&id) {
+            return;
+        }
+        state.metrics.size.dec();
+        if !state.stopped.load(Ordering::SeqCst) {
+            debug!("Replacing stopped pool member '{}'", id);
+            state.metrics.replaced.inc();
+            Self::start_member(state);
+        }
+    }
+
+    fn resize(state: &Arc<Self>) {
+        let members = state.router.routees();
+        let size = members.len();
+        let queued = members
+            .iter()
+            .map(ActorRef::mailbox_len)
+            .sum::<usize>();
+        let under_pressure = queued > state.config.pressure_threshold * size;
+        if size < state.config.min_size {
+            Self::start_member(state);
+        } else if size < state.config.max_size && under_pressure {
+            state.metrics.grown.inc();
+            Self::start_member(state);
+        } else if size > state.config.min_size && queued == 0 {
+            let idle = members.last().expect("pool has no members");
+            state.router.remove_routee(&idle.id());
+            state.metrics.size.dec();
+            state.metrics.shrunk.inc();
+            context::handle().spawn(idle.stop());
+        }
+    }
+}
+
+/// A resizable group of instances of an actor, created from a factory, that
+/// requests are routed among through `router`. Instances that stop are
+/// replaced.
+///
+/// Resizing and replacement end once every handle to the pool has been
+/// dropped, but the instances keep running until `stop` is called.
+pub struct Pool<A, L> {
+    state: Arc<PoolState<A, L>>,
+}
+
+impl<A, L> Clone for Pool<A, L> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<A, L> Pool<A, L>
+where
+    A: Actor,
+    L: Send + Sync + 'static,
+{
+    /// Starts `config`'s minimum number of instances. Must be called from
+    /// within the actor system.
+    pub fn start<F>(config: PoolConfig, logic: L, factory: F) -> Self
+    where
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        let metrics = PoolMetrics::new(&context::system().metrics(), type_name::<A>());
+        let interval = config.resize_interval;
+        let state = Arc::new(PoolState {
+            router: Router::new(logic, Vec::new()),
+            factory: Box::new(factory),
+            config: config,
+            stopped: AtomicBool::new(false),
+            metrics: metrics,
+        });
+        for _ in 0..state.config.min_size {
+            PoolState::start_member(&state);
+        }
+        let handle = context::handle();
+        let ticks = Interval::new(interval, &handle).expect("failed to create interval");
+        handle.spawn(Self::resize_loop(Arc::downgrade(&state), ticks));
+        Self {
+            state: state,
+        }
+    }
+
+    fn resize_loop(
+        state: Weak<PoolState<A, L>>,
+        ticks: Interval,
+    ) -> impl Future<Item = (), Error = ()> {
+        ticks
+            .map_err(|e| error!("Pool resize interval failed: {}", e))
+            .for_each(move |_| match state.upgrade() {
+                Some(ref state) if !state.stopped.load(Ordering::SeqCst) => {
+                    PoolState::resize(state);
+                    Ok(())
+                }
+                _ => Err(()),
+            })
+    }
+
+    /// Routes messages among the pool's current instances. Generated
+    /// `XxxRef` methods can be called on it.
+    pub fn router(&self) -> &Router<A, L> {
+        &self.state.router
+    }
+
+    pub fn size(&self) -> usize {
+        self.state.router.routees().len()
+    }
+
+    /// Stops every instance. The pool no longer resizes or replaces
+    /// instances afterwards.
+    pub fn stop(&self) -> impl Future<Item = (), Error = ()> {
+        self.state.stopped.store(true, Ordering::SeqCst);
+        let stops = self.state
+            .router
+            .routees()
+            .into_iter()
+            .map(|member| member.stop())
+            .collect::<Vec<_>>();
+        future::join_all(stops).map(|_| ())
+    }
+}
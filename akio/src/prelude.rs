@@ -1,6 +1,41 @@
-pub use super::{context, Actor, ActorRef, ActorResponse, ActorSystem, MessageHandler};
+pub use super::{
+    context,
+    Actor,
+    ActorMessage,
+    ActorRef,
+    ActorResponse,
+    ActorStream,
+    ActorSystem,
+    AskRecipient,
+    ConsistentHashKey,
+    Envelope,
+    GrainKey,
+    Headers,
+    MessageHandler,
+    PipeTo,
+    Pool,
+    PoolConfig,
+    Recipient,
+    Responder,
+    Router,
+    RoutingLogic,
+    StreamHandler,
+};
 pub use super::errors::*;
-pub use akio_syntax::{actor_api, actor_impl, on_start, on_stop};
+pub use super::persistence::{DurableStateActor, PersistentActor};
+pub use super::serialization::{SerializableMessage, SerializerRegistry};
+// Used by the code #[actor_impl(serializable)] generates.
+#[doc(hidden)]
+pub use super::serialization::{
+    assert_serializable_field,
+    MessageField,
+    Serializable,
+    SerdeMessageField,
+    UnserializableMessageField,
+};
+#[doc(hidden)]
+pub use serde as akio_serde;
+pub use akio_syntax::{actor_api, actor_impl, actor_trait, on_start, on_stop};
 pub use futures::future;
 pub use futures::prelude::*;
 pub use uuid::Uuid;
@@ -0,0 +1,176 @@
+use super::{Actor, ActorRef, MessageHandler};
+use futures::prelude::*;
+use uuid::Uuid;
+
+trait RecipientTarget<M>: Send {
+    fn id(&self) -> Uuid;
+
+    fn exists(&self) -> bool;
+
+    fn send(&self, message: M);
+
+    fn terminated(&self) -> Box<Future<Item = (), Error = ()> + Send>;
+
+    fn boxed_clone(&self) -> Box<RecipientTarget<M>>;
+}
+
+impl<A, M> RecipientTarget<M> for ActorRef<A>
+where
+    A: Actor + MessageHandler<M>,
+    M: Send + 'static,
+{
+    fn id(&self) -> Uuid {
+        ActorRef::id(self)
+    }
+
+    fn exists(&self) -> bool {
+        ActorRef::exists(self)
+    }
+
+    fn send(&self, message: M) {
+        ActorRef::send(self, message)
+    }
+
+    fn terminated(&self) -> Box<Future<Item = (), Error = ()> + Send> {
+        Box::new(ActorRef::terminated(self))
+    }
+
+    fn boxed_clone(&self) -> Box<RecipientTarget<M>> {
+        Box::new(self.clone())
+    }
+}
+
+/// Any actor that handles messages of type `M`, regardless of its concrete
+/// type.
+pub struct Recipient<M> {
+    target: Box<RecipientTarget<M>>,
+}
+
+impl<M> Recipient<M>
+where
+    M: Send + 'static,
+{
+    pub fn id(&self) -> Uuid {
+        self.target.id()
+    }
+
+    pub fn exists(&self) -> bool {
+        self.target.exists()
+    }
+
+    pub fn send(&self, message: M) {
+        self.target.send(message)
+    }
+
+    /// Resolves once the underlying actor has stopped.
+    pub fn terminated(&self) -> Box<Future<Item = (), Error = ()> + Send> {
+        self.target.terminated()
+    }
+}
+
+impl<M> Clone for Recipient<M> {
+    fn clone(&self) -> Self {
+        Self {
+            target: self.target.boxed_clone(),
+        }
+    }
+}
+
+impl<A, M> From<ActorRef<A>> for Recipient<M>
+where
+    A: Actor + MessageHandler<M>,
+    M: Send + 'static,
+{
+    fn from(actor_ref: ActorRef<A>) -> Self {
+        Self {
+            target: Box::new(actor_ref),
+        }
+    }
+}
+
+trait AskTarget<M, R>: Send {
+    fn id(&self) -> Uuid;
+
+    fn exists(&self) -> bool;
+
+    fn send(&self, message: M);
+
+    fn request(&self, message: M) -> Box<Future<Item = R, Error = ()> + Send>;
+
+    fn boxed_clone(&self) -> Box<AskTarget<M, R>>;
+}
+
+impl<A, M, R> AskTarget<M, R> for ActorRef<A>
+where
+    A: Actor + MessageHandler<M, Response = R>,
+    M: Send + 'static,
+    R: Send + 'static,
+{
+    fn id(&self) -> Uuid {
+        ActorRef::id(self)
+    }
+
+    fn exists(&self) -> bool {
+        ActorRef::exists(self)
+    }
+
+    fn send(&self, message: M) {
+        ActorRef::send(self, message)
+    }
+
+    fn request(&self, message: M) -> Box<Future<Item = R, Error = ()> + Send> {
+        Box::new(ActorRef::request(self, message).flatten())
+    }
+
+    fn boxed_clone(&self) -> Box<AskTarget<M, R>> {
+        Box::new(self.clone())
+    }
+}
+
+/// Any actor that handles messages of type `M` and responds with `R`.
+pub struct AskRecipient<M, R> {
+    target: Box<AskTarget<M, R>>,
+}
+
+impl<M, R> AskRecipient<M, R>
+where
+    M: Send + 'static,
+    R: Send + 'static,
+{
+    pub fn id(&self) -> Uuid {
+        self.target.id()
+    }
+
+    pub fn exists(&self) -> bool {
+        self.target.exists()
+    }
+
+    pub fn send(&self, message: M) {
+        self.target.send(message)
+    }
+
+    pub fn request(&self, message: M) -> Box<Future<Item = R, Error = ()> + Send> {
+        self.target.request(message)
+    }
+}
+
+impl<M, R> Clone for AskRecipient<M, R> {
+    fn clone(&self) -> Self {
+        Self {
+            target: self.target.boxed_clone(),
+        }
+    }
+}
+
+impl<A, M, R> From<ActorRef<A>> for AskRecipient<M, R>
+where
+    A: Actor + MessageHandler<M, Response = R>,
+    M: Send + 'static,
+    R: Send + 'static,
+{
+    fn from(actor_ref: ActorRef<A>) -> Self {
+        Self {
+            target: Box::new(actor_ref),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Refs to actors in other actor systems.
+//!
+//! A remote `ActorRef` encodes each message it is sent with the system's
+//! `SerializerRegistry` and hands it to a transport, such as akio-net's
+//! `RemoteNode`, as a `RemoteMessage`. The destination system decodes it and
+//! delivers it with `ActorSystem::deliver_serialized`. Only messages
+//! registered in both systems, e.g. with the `register_messages` function
+//! generated by `#[actor_impl(serializable)]`, can be sent.
+
+use super::{ActorResponse, Envelope, Headers};
+use super::serialization::{SerializedMessage, SerializerRegistry};
+use super::util::type_name;
+use futures::prelude::*;
+use futures::sync::{mpsc, oneshot};
+use std::net::SocketAddr;
+use uuid::Uuid;
+
+/// A message on its way to an actor on another node.
+pub struct RemoteMessage {
+    pub node: SocketAddr,
+    pub actor_id: Uuid,
+    pub message: SerializedMessage,
+    pub headers: Headers,
+    /// Completed with the encoded response. Dropped if the actor can't be
+    /// reached. `None` for messages sent without expecting a reply.
+    pub response: Option<oneshot::Sender<Vec<u8>>>,
+}
+
+/// Where a remote `ActorRef` sends its messages.
+#[derive(Clone)]
+pub struct RemoteActor {
+    node: SocketAddr,
+    id: Uuid,
+    outbox: mpsc::UnboundedSender<RemoteMessage>,
+    serializers: SerializerRegistry,
+}
+
+impl RemoteActor {
+    /// An actor `id` on the node listening on `node`. Its messages are
+    /// encoded with `serializers` and pushed to `outbox`.
+    pub fn new(
+        node: SocketAddr,
+        id: Uuid,
+        outbox: mpsc::UnboundedSender<RemoteMessage>,
+        serializers: SerializerRegistry,
+    ) -> Self {
+        Self {
+            node: node,
+            id: id,
+            outbox: outbox,
+            serializers: serializers,
+        }
+    }
+
+    pub fn node(&self) -> SocketAddr {
+        self.node
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub(crate) fn enqueue_message<M, R>(
+        &self,
+        envelope: Envelope<M>,
+        promise: Option<oneshot::Sender<ActorResponse<R>>>,
+    ) where
+        M: 'static,
+        R: Send + 'static,
+    {
+        let message = match self.serializers.serialize_any(&envelope.message) {
+            Ok(message) => message,
+            Err(e) => {
+                error!(
+                    "Failed to send '{}' to remote actor '{}': {}",
+                    type_name::<M>(),
+                    self.id,
+                    e
+                );
+                return;
+            }
+        };
+        let response = promise.map(|promise| {
+            let (sender, receiver) = oneshot::channel::<Vec<u8>>();
+            let serializers = self.serializers.clone();
+            let message_type_id = message.message_type_id.clone();
+            let response = receiver.map_err(|_| ()).and_then(move |payload| {
+                serializers
+                    .deserialize_response(&message_type_id, &payload)
+                    .map_err(|e| error!("Failed to decode remote response: {}", e))
+                    .and_then(|response| response.downcast::<R>().map_err(|_| ()))
+                    .map(|response| *response)
+            });
+            let _ = promise.send(ActorResponse::Async(Box::new(response)));
+            sender
+        });
+        let remote_message = RemoteMessage {
+            node: self.node,
+            actor_id: self.id,
+            message: message,
+            headers: envelope.headers,
+            response: response,
+        };
+        if self.outbox.unbounded_send(remote_message).is_err() {
+            error!(
+                "Failed to send '{}' to remote actor '{}': its node was shut down",
+                type_name::<M>(),
+                self.id
+            );
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use super::ActorResponse;
+use super::util::type_name;
+use futures::IntoFuture;
+use futures::sync::oneshot;
+use std::any::Any;
+use std::cell::RefCell;
+use std::mem;
+
+thread_local! {
+    static PENDING_PROMISE: RefCell<Option<Box<Any>>> = RefCell::new(None)
+}
+
+/// The obligation to answer a request.
+///
+/// A handler can take the `Responder` for the message it is handling with
+/// `Actor::responder`, keep it, and complete it later, or move it to another
+/// actor with `Actor::forward`. Dropping a `Responder` without completing it
+/// fails the caller's future.
+pub struct Responder<T> {
+    promise: Option<oneshot::Sender<ActorResponse<T>>>,
+}
+
+impl<T> Responder<T>
+where
+    T: Send + 'static,
+{
+    pub(crate) fn new(promise: Option<oneshot::Sender<ActorResponse<T>>>) -> Self {
+        Self {
+            promise: promise,
+        }
+    }
+
+    /// Takes the responder for the message being handled on this thread.
+    /// Returns an empty responder if the message was sent without expecting
+    /// a reply, the responder was already taken, or the message's response
+    /// isn't a `T`. In the last case the responder is left in place for the
+    /// handler's response.
+    pub(crate) fn take_current() -> Self {
+        let maybe_promise = PENDING_PROMISE.with(|pending| pending.borrow_mut().take());
+        let promise = maybe_promise.and_then(|promise| {
+            match promise.downcast::<oneshot::Sender<ActorResponse<T>>>() {
+                Ok(promise) => Some(*promise),
+                Err(promise) => {
+                    error!(
+                        "Can't take a responder of '{}' for a message with another response type",
+                        type_name::<T>()
+                    );
+                    PENDING_PROMISE.with(|pending| *pending.borrow_mut() = Some(promise));
+                    None
+                }
+            }
+        });
+        Self::new(promise)
+    }
+
+    /// Whether the sender is waiting for a reply.
+    pub fn is_expected(&self) -> bool {
+        self.promise
+            .as_ref()
+            .map(|promise| !promise.is_canceled())
+            .unwrap_or(false)
+    }
+
+    pub fn respond(self, value: T) {
+        self.complete(ActorResponse::Normal(Some(value)))
+    }
+
+    pub fn respond_fut<F>(self, f: F)
+    where
+        F: IntoFuture<Item = T, Error = ()> + 'static,
+        F::Future: Send,
+    {
+        self.complete(ActorResponse::Async(Box::new(f.into_future())))
+    }
+
+    pub fn complete(mut self, response: ActorResponse<T>) {
+        if let Some(promise) = self.promise.take() {
+            let _ = promise.send(response);
+        }
+    }
+
+    pub(crate) fn into_promise(mut self) -> Option<oneshot::Sender<ActorResponse<T>>> {
+        self.promise.take()
+    }
+}
+
+/// Makes `promise` available to `Responder::take_current` while `f` runs,
+/// returning it afterwards unless the handler took it.
+pub(crate) fn with_pending_promise<T, F, R>(
+    promise: Option<oneshot::Sender<ActorResponse<T>>>,
+    f: F,
+) -> (R, Option<oneshot::Sender<ActorResponse<T>>>)
+where
+    T: Send + 'static,
+    F: FnOnce() -> R,
+{
+    let pending = promise.map(|promise| Box::new(promise) as Box<Any>);
+    let previous = PENDING_PROMISE.with(|slot| mem::replace(&mut *slot.borrow_mut(), pending));
+    let result = f();
+    let remaining = PENDING_PROMISE.with(|slot| mem::replace(&mut *slot.borrow_mut(), previous));
+    let promise = remaining.and_then(|promise| {
+        promise
+            .downcast::<oneshot::Sender<ActorResponse<T>>>()
+            .ok()
+            .map(|promise| *promise)
+    });
+    (result, promise)
+}
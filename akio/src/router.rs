@@ -0,0 +1,233 @@
+use super::{Actor, ActorRef, ActorResponse, MessageHandler};
+use super::util::type_name;
+use futures::future::{self, Either};
+use futures::prelude::*;
+use parking_lot::RwLock;
+use rand::{self, Rng};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use uuid::Uuid;
+
+/// Decides which routees receive each message sent through a `Router`.
+pub trait RoutingLogic<M>: Send + Sync + 'static {
+    /// Pairs `message`, or copies of it, with the indices of the routees
+    /// that should receive them. `routees` is never empty.
+    fn route<A>(&self, message: M, routees: &[ActorRef<A>]) -> Vec<(usize, M)>
+    where
+        A: Actor;
+}
+
+/// Sends messages to each routee in turn.
+pub struct RoundRobin {
+    next: AtomicUsize,
+}
+
+impl RoundRobin {
+    pub fn new() -> Self {
+        Self {
+            next: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<M> RoutingLogic<M> for RoundRobin {
+    fn route<A>(&self, message: M, routees: &[ActorRef<A>]) -> Vec<(usize, M)>
+    where
+        A: Actor,
+    {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % routees.len();
+        vec![(index, message)]
+    }
+}
+
+/// Sends each message to a randomly chosen routee.
+pub struct Random;
+
+impl<M> RoutingLogic<M> for Random {
+    fn route<A>(&self, message: M, routees: &[ActorRef<A>]) -> Vec<(usize, M)>
+    where
+        A: Actor,
+    {
+        vec![(rand::thread_rng().gen_range(0, routees.len()), message)]
+    }
+}
+
+/// Sends each message to the routee with the fewest queued messages.
+pub struct SmallestMailbox;
+
+impl<M> RoutingLogic<M> for SmallestMailbox {
+    fn route<A>(&self, message: M, routees: &[ActorRef<A>]) -> Vec<(usize, M)>
+    where
+        A: Actor,
+    {
+        let index = routees
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, routee)| routee.mailbox_len())
+            .map(|(index, _)| index)
+            .unwrap_or(0);
+        vec![(index, message)]
+    }
+}
+
+/// Sends a copy of each message to every routee. Requests are answered by
+/// the first routee. Generated messages are `Clone` if their method is
+/// marked `#[actor_api(clone)]`.
+pub struct Broadcast;
+
+impl<M> RoutingLogic<M> for Broadcast
+where
+    M: Clone,
+{
+    fn route<A>(&self, message: M, routees: &[ActorRef<A>]) -> Vec<(usize, M)>
+    where
+        A: Actor,
+    {
+        let mut deliveries = (1..routees.len())
+            .map(|index| (index, message.clone()))
+            .collect::<Vec<_>>();
+        deliveries.insert(0, (0, message));
+        deliveries
+    }
+}
+
+/// Provides the key that `ConsistentHash` routes a message by. Generated
+/// messages hash the argument named by `#[actor_api(hash_key = "arg")]`.
+pub trait ConsistentHashKey {
+    type Key: Hash;
+
+    fn hash_key(&self) -> Self::Key;
+}
+
+/// Sends messages with the same key to the same routee. Adding or removing a
+/// routee only moves the keys that it gains or loses.
+pub struct ConsistentHash;
+
+impl ConsistentHash {
+    // Rendezvous hashing: each key goes to the routee that scores highest
+    // for it, which is unaffected by the other routees coming and going.
+    fn score<K>(key: &K, routee: Uuid) -> u64
+    where
+        K: Hash,
+    {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        routee.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl<M> RoutingLogic<M> for ConsistentHash
+where
+    M: ConsistentHashKey,
+{
+    fn route<A>(&self, message: M, routees: &[ActorRef<A>]) -> Vec<(usize, M)>
+    where
+        A: Actor,
+    {
+        let key = message.hash_key();
+        let index = routees
+            .iter()
+            .enumerate()
+            .max_by_key(|&(_, routee)| Self::score(&key, routee.id()))
+            .map(|(index, _)| index)
+            .unwrap_or(0);
+        vec![(index, message)]
+    }
+}
+
+/// Spreads messages over a changing set of actors of the same type according
+/// to a `RoutingLogic`. Generated `XxxRef` traits are implemented for
+/// routers, so a router can be used in place of an `ActorRef`.
+pub struct Router<A, L> {
+    routees: Arc<RwLock<Vec<ActorRef<A>>>>,
+    logic: Arc<L>,
+}
+
+impl<A, L> Clone for Router<A, L> {
+    fn clone(&self) -> Self {
+        Self {
+            routees: self.routees.clone(),
+            logic: self.logic.clone(),
+        }
+    }
+}
+
+impl<A, L> Router<A, L>
+where
+    A: Actor,
+{
+    pub fn new(logic: L, routees: Vec<ActorRef<A>>) -> Self {
+        Self {
+            routees: Arc::new(RwLock::new(routees)),
+            logic: Arc::new(logic),
+        }
+    }
+
+    pub fn routees(&self) -> Vec<ActorRef<A>> {
+        self.routees.read().clone()
+    }
+
+    pub fn add_routee(&self, routee: ActorRef<A>) {
+        self.routees.write().push(routee)
+    }
+
+    /// Returns whether a routee with `id` was removed.
+    pub fn remove_routee(&self, id: &Uuid) -> bool {
+        let mut routees = self.routees.write();
+        let len = routees.len();
+        routees.retain(|routee| routee.id() != *id);
+        routees.len() != len
+    }
+
+    /// Routes a request. When the logic picks several routees, the first one
+    /// answers and the others are sent the message. Fails if there are no
+    /// routees.
+    pub fn request<M>(
+        &self,
+        message: M,
+    ) -> impl Future<Item = ActorResponse<A::Response>, Error = ()>
+    where
+        A: MessageHandler<M>,
+        M: Send + 'static,
+        L: RoutingLogic<M>,
+    {
+        let mut deliveries = self.route(message).into_iter();
+        match deliveries.next() {
+            Some((routee, message)) => {
+                deliveries.for_each(|(routee, message)| routee.send(message));
+                Either::A(routee.request(message))
+            }
+            None => Either::B(future::err(())),
+        }
+    }
+
+    pub fn send<M>(&self, message: M)
+    where
+        A: MessageHandler<M>,
+        M: Send + 'static,
+        L: RoutingLogic<M>,
+    {
+        self.route(message)
+            .into_iter()
+            .for_each(|(routee, message)| routee.send(message))
+    }
+
+    fn route<M>(&self, message: M) -> Vec<(ActorRef<A>, M)>
+    where
+        L: RoutingLogic<M>,
+    {
+        let routees = self.routees.read();
+        if routees.is_empty() {
+            debug!("Dropped '{}' sent to a router without routees", type_name::<M>());
+            return Vec::new();
+        }
+        self.logic
+            .route(message, &routees)
+            .into_iter()
+            .map(|(index, message)| (routees[index].clone(), message))
+            .collect()
+    }
+}
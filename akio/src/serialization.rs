@@ -0,0 +1,408 @@
+//! Byte encodings of actor messages.
+//!
+//! Messages that are stored or sent to another node are identified by a
+//! stable message type id rather than a Rust type, which can differ between
+//! builds. A `SerializerRegistry` maps those ids back to the types that
+//! decode them. Messages generated by `#[actor_impl(serializable)]`
+//! implement `SerializableMessage` and are registered with the actor's
+//! generated `register_messages` function, which also lets messages from
+//! other nodes be delivered to the actor.
+
+use super::{Actor, ActorMessage, ActorSystem, Envelope, Headers, MessageHandler};
+use super::errors::*;
+use super::util::type_name;
+use bincode;
+use futures::future;
+use futures::prelude::*;
+use parking_lot::RwLock;
+use serde::{de, ser, Serialize};
+use serde::de::{DeserializeOwned, SeqAccess};
+use serde::ser::SerializeTuple;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::result;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Implemented for every type serde can both serialize and deserialize.
+/// Serializable `#[actor_api]` methods require it of their arguments and
+/// response. Being implemented for every such type, it can't carry its own
+/// error message; the compiler reports the missing serde trait instead.
+pub trait Serializable: Serialize + DeserializeOwned + Send + 'static {}
+
+impl<T> Serializable for T
+where
+    T: Serialize + DeserializeOwned + Send + 'static,
+{
+}
+
+// The arguments of the messages #[actor_impl(serializable)] generates are
+// encoded through `MessageField`. Method resolution picks the
+// `SerdeMessageField` impl for arguments serde can encode and otherwise
+// falls back to the `UnserializableMessageField` impl through auto-ref, so
+// that the generated serde impls compile either way and an argument serde
+// can't encode is reported once, with the `SerializableField` message.
+#[doc(hidden)]
+pub struct MessageField<T>(PhantomData<T>);
+
+impl<T> MessageField<T> {
+    pub fn new() -> Self {
+        MessageField(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializableFieldCheck;
+
+#[doc(hidden)]
+pub struct UnserializableField<T>(PhantomData<T>);
+
+#[doc(hidden)]
+#[rustc_on_unimplemented = "`{Self}`: the arguments and response of a serializable actor \
+                            message must implement serde's `Serialize` and `Deserialize`"]
+pub trait SerializableField {}
+
+impl SerializableField for SerializableFieldCheck {}
+
+#[doc(hidden)]
+pub fn assert_serializable_field<C>(_: C)
+where
+    C: SerializableField,
+{
+}
+
+#[doc(hidden)]
+pub trait SerdeMessageField {
+    type Value;
+
+    fn encode_field<S>(&self, tuple: &mut S, value: &Self::Value) -> result::Result<(), S::Error>
+    where
+        S: SerializeTuple;
+
+    fn decode_field<'de, V>(&self, seq: &mut V) -> result::Result<Option<Self::Value>, V::Error>
+    where
+        V: SeqAccess<'de>;
+
+    fn check_field(&self) -> SerializableFieldCheck {
+        SerializableFieldCheck
+    }
+}
+
+impl<T> SerdeMessageField for MessageField<T>
+where
+    T: Serializable,
+{
+    type Value = T;
+
+    fn encode_field<S>(&self, tuple: &mut S, value: &T) -> result::Result<(), S::Error>
+    where
+        S: SerializeTuple,
+    {
+        tuple.serialize_element(value)
+    }
+
+    fn decode_field<'de, V>(&self, seq: &mut V) -> result::Result<Option<T>, V::Error>
+    where
+        V: SeqAccess<'de>,
+    {
+        seq.next_element()
+    }
+}
+
+#[doc(hidden)]
+pub trait UnserializableMessageField {
+    type Value;
+
+    fn encode_field<S>(&self, tuple: &mut S, value: &Self::Value) -> result::Result<(), S::Error>
+    where
+        S: SerializeTuple;
+
+    fn decode_field<'de, V>(&self, seq: &mut V) -> result::Result<Option<Self::Value>, V::Error>
+    where
+        V: SeqAccess<'de>;
+
+    fn check_field(&self) -> UnserializableField<Self::Value> {
+        UnserializableField(PhantomData)
+    }
+}
+
+impl<'a, T> UnserializableMessageField for &'a MessageField<T> {
+    type Value = T;
+
+    fn encode_field<S>(&self, _: &mut S, _: &T) -> result::Result<(), S::Error>
+    where
+        S: SerializeTuple,
+    {
+        Err(ser::Error::custom(format!("'{}' can't be serialized", type_name::<T>())))
+    }
+
+    fn decode_field<'de, V>(&self, _: &mut V) -> result::Result<Option<T>, V::Error>
+    where
+        V: SeqAccess<'de>,
+    {
+        Err(de::Error::custom(format!("'{}' can't be deserialized", type_name::<T>())))
+    }
+}
+
+/// A message that can be encoded, identified across builds and nodes by its
+/// message type id.
+pub trait SerializableMessage: ActorMessage + Serializable {
+    /// Stays the same as long as the message does. Generated messages use
+    /// `"<Actor>.<method>"`.
+    fn message_type_id() -> &'static str;
+}
+
+/// An encoded message, along with what it decodes to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SerializedMessage {
+    pub message_type_id: String,
+    pub payload: Vec<u8>,
+}
+
+pub fn serialize<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    Ok(bincode::serialize(value, bincode::Infinite)?)
+}
+
+pub fn deserialize<T>(payload: &[u8]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    Ok(bincode::deserialize(payload)?)
+}
+
+type Deliver =
+    fn(&ActorSystem, Uuid, Box<Any + Send>, Headers) -> Box<Future<Item = Box<Any + Send>, Error = ()>>;
+
+struct MessageSerializer {
+    type_id: TypeId,
+    message_type: &'static str,
+    encode: fn(&Any) -> Result<Vec<u8>>,
+    decode: fn(&[u8]) -> Result<Box<Any + Send>>,
+    encode_response: fn(&Any) -> Result<Vec<u8>>,
+    decode_response: fn(&[u8]) -> Result<Box<Any + Send>>,
+    /// Set when the message was registered along with the actor that
+    /// handles it.
+    deliver: Option<Deliver>,
+}
+
+/// Maps message type ids to the messages they identify, so that messages
+/// and their responses can be decoded without knowing their types up front.
+/// Clones share the same registrations.
+#[derive(Clone, Default)]
+pub struct SerializerRegistry {
+    serializers: Arc<RwLock<HashMap<&'static str, MessageSerializer>>>,
+}
+
+impl SerializerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails if another message type was registered with the same id.
+    pub fn register<M>(&self) -> Result<()>
+    where
+        M: SerializableMessage,
+        M::Response: Serializable,
+    {
+        self.insert::<M>(None)
+    }
+
+    /// Like `register`, but also lets encoded messages of type `M` be
+    /// delivered to actors of type `A` with `deliver`.
+    pub fn register_handler<A, M>(&self) -> Result<()>
+    where
+        A: Actor + MessageHandler<M, Response = M::Response>,
+        M: SerializableMessage,
+        M::Response: Serializable,
+    {
+        self.insert::<M>(Some(deliver_any::<A, M>))
+    }
+
+    fn insert<M>(&self, deliver: Option<Deliver>) -> Result<()>
+    where
+        M: SerializableMessage,
+        M::Response: Serializable,
+    {
+        let id = M::message_type_id();
+        let mut serializers = self.serializers.write();
+        if let Some(existing) = serializers.get_mut(id) {
+            if existing.type_id != TypeId::of::<M>() {
+                bail!(ErrorKind::DuplicateMessageType(
+                    id,
+                    existing.message_type,
+                    type_name::<M>()
+                ));
+            }
+            if deliver.is_some() {
+                existing.deliver = deliver;
+            }
+            return Ok(());
+        }
+        serializers.insert(
+            id,
+            MessageSerializer {
+                type_id: TypeId::of::<M>(),
+                message_type: type_name::<M>(),
+                encode: encode_any::<M>,
+                decode: decode_any::<M>,
+                encode_response: encode_any::<M::Response>,
+                decode_response: decode_any::<M::Response>,
+                deliver: deliver,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn is_registered(&self, message_type_id: &str) -> bool {
+        self.serializers.read().contains_key(message_type_id)
+    }
+
+    pub fn serialize<M>(&self, message: &M) -> Result<SerializedMessage>
+    where
+        M: SerializableMessage,
+    {
+        let id = M::message_type_id();
+        if !self.is_registered(id) {
+            bail!(ErrorKind::UnknownMessageType(id.to_string()));
+        }
+        Ok(SerializedMessage {
+            message_type_id: id.to_string(),
+            payload: serialize(message)?,
+        })
+    }
+
+    /// Like `serialize`, for callers that only know `message` is `'static`.
+    /// Fails unless its type was registered.
+    pub fn serialize_any<T>(&self, message: &T) -> Result<SerializedMessage>
+    where
+        T: 'static,
+    {
+        let type_id = TypeId::of::<T>();
+        let (id, encode) = self.serializers
+            .read()
+            .iter()
+            .find(|&(_, serializer)| serializer.type_id == type_id)
+            .map(|(id, serializer)| (*id, serializer.encode))
+            .ok_or_else(|| ErrorKind::UnknownMessageType(type_name::<T>().to_string()))?;
+        Ok(SerializedMessage {
+            message_type_id: id.to_string(),
+            payload: encode(message)?,
+        })
+    }
+
+    /// Decodes `message` into a box holding its registered type.
+    pub fn deserialize(&self, message: &SerializedMessage) -> Result<Box<Any + Send>> {
+        let decode = self.with_serializer(&message.message_type_id, |serializer| {
+            serializer.decode
+        })?;
+        decode(&message.payload)
+    }
+
+    /// Encodes `response`, which must be the response type of the message
+    /// registered as `message_type_id`.
+    pub fn serialize_response(&self, message_type_id: &str, response: &Any) -> Result<Vec<u8>> {
+        let encode = self.with_serializer(message_type_id, |serializer| {
+            serializer.encode_response
+        })?;
+        encode(response)
+    }
+
+    pub fn deserialize_response(
+        &self,
+        message_type_id: &str,
+        payload: &[u8],
+    ) -> Result<Box<Any + Send>> {
+        let decode = self.with_serializer(message_type_id, |serializer| {
+            serializer.decode_response
+        })?;
+        decode(payload)
+    }
+
+    /// Decodes `message`, delivers it to actor `actor_id` in `system` and
+    /// resolves to its encoded response. An inactive actor is activated
+    /// from the factory registered for its type.
+    pub fn deliver(
+        &self,
+        system: &ActorSystem,
+        actor_id: Uuid,
+        message: &SerializedMessage,
+        headers: Headers,
+    ) -> Box<Future<Item = Vec<u8>, Error = Error>> {
+        let message_type_id = message.message_type_id.clone();
+        let serializer = self.with_serializer(&message_type_id, |serializer| {
+            (serializer.decode, serializer.deliver, serializer.encode_response)
+        });
+        let (decode, deliver, encode_response) = match serializer {
+            Ok((decode, Some(deliver), encode_response)) => (decode, deliver, encode_response),
+            Ok(_) => {
+                let error = Error::from(ErrorKind::NoMessageHandler(message_type_id));
+                return Box::new(future::err(error));
+            }
+            Err(e) => return Box::new(future::err(e)),
+        };
+        let decoded = match decode(&message.payload) {
+            Ok(decoded) => decoded,
+            Err(e) => return Box::new(future::err(e)),
+        };
+        let response = deliver(system, actor_id, decoded, headers)
+            .map_err(move |_| Error::from(ErrorKind::NoResponse(actor_id, message_type_id)))
+            .and_then(move |response| encode_response(&*response));
+        Box::new(response)
+    }
+
+    fn with_serializer<F, R>(&self, message_type_id: &str, f: F) -> Result<R>
+    where
+        F: FnOnce(&MessageSerializer) -> R,
+    {
+        self.serializers
+            .read()
+            .get(message_type_id)
+            .map(f)
+            .ok_or_else(|| ErrorKind::UnknownMessageType(message_type_id.to_string()).into())
+    }
+}
+
+fn decode_any<T>(payload: &[u8]) -> Result<Box<Any + Send>>
+where
+    T: Serializable,
+{
+    Ok(Box::new(deserialize::<T>(payload)?))
+}
+
+fn encode_any<T>(value: &Any) -> Result<Vec<u8>>
+where
+    T: Serializable,
+{
+    match value.downcast_ref::<T>() {
+        Some(value) => serialize(value),
+        None => bail!(ErrorKind::UnexpectedMessage(type_name::<T>())),
+    }
+}
+
+fn deliver_any<A, M>(
+    system: &ActorSystem,
+    actor_id: Uuid,
+    message: Box<Any + Send>,
+    headers: Headers,
+) -> Box<Future<Item = Box<Any + Send>, Error = ()>>
+where
+    A: Actor + MessageHandler<M, Response = M::Response>,
+    M: SerializableMessage,
+    M::Response: Serializable,
+{
+    match message.downcast::<M>() {
+        Ok(message) => {
+            let response = system
+                .actor_ref::<A>(actor_id)
+                .request_envelope(Envelope::with_headers(*message, headers))
+                .flatten()
+                .map(|response| Box::new(response) as Box<Any + Send>);
+            Box::new(response)
+        }
+        Err(_) => Box::new(future::err(())),
+    }
+}
@@ -0,0 +1,66 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+use uuid::Uuid;
+
+thread_local! {
+    static CURRENT_SPAN: RefCell<Option<Span>> = RefCell::new(None)
+}
+
+/// Identifies the actor, and the message it is handling, that the current
+/// thread is executing on behalf of.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Span {
+    pub actor_id: Uuid,
+    pub actor_type: &'static str,
+    pub message_type: &'static str,
+}
+
+impl Span {
+    pub fn new(actor_id: Uuid, actor_type: &'static str, message_type: &'static str) -> Self {
+        Self {
+            actor_id: actor_id,
+            actor_type: actor_type,
+            message_type: message_type,
+        }
+    }
+
+    pub fn current() -> Option<Span> {
+        CURRENT_SPAN.with(|span| span.borrow().clone())
+    }
+
+    /// Makes this the current span until the returned guard is dropped.
+    pub(crate) fn enter(self) -> SpanGuard {
+        trace!("enter {}", self);
+        let previous = CURRENT_SPAN.with(|span| mem::replace(&mut *span.borrow_mut(), Some(self)));
+        SpanGuard {
+            previous: previous,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "actor_id={} actor_type={} message_type={}",
+            self.actor_id,
+            self.actor_type,
+            self.message_type
+        )
+    }
+}
+
+pub(crate) struct SpanGuard {
+    previous: Option<Span>,
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        let exited = CURRENT_SPAN.with(|span| mem::replace(&mut *span.borrow_mut(), previous));
+        if let Some(exited) = exited {
+            trace!("exit {}", exited);
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use super::Actor;
+
+/// Receives the items of a stream attached with `add_stream`.
+///
+/// Items are delivered through the actor's mailbox like any other message,
+/// one at a time: the next item isn't taken from the stream until the
+/// previous one has been handled.
+pub trait StreamHandler<T>: Actor {
+    fn handle_item(&mut self, item: T);
+
+    /// Called before the first item is delivered.
+    fn started(&mut self) {}
+
+    /// Called once the stream has ended. A stream that fails ends at its
+    /// first error. Not called if the actor stops first.
+    fn finished(&mut self) {}
+}
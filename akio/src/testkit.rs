@@ -0,0 +1,240 @@
+//! Utilities for writing tests against actors.
+//!
+//! A `TestProbe` is an actor that records every message it receives so a test
+//! can make assertions on them, and an `ActorStub` stands in for an
+//! `#[actor_api]` actor with canned responses.
+
+use super::{
+    context,
+    Actor,
+    ActorMessage,
+    ActorRef,
+    ActorResponse,
+    ActorSystem,
+    DeadLetter,
+    MessageHandler,
+};
+use super::errors::*;
+use super::util::type_name;
+use futures::future;
+use futures::prelude::*;
+use futures::sync::mpsc;
+use parking_lot::Mutex;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_core::reactor::Timeout;
+
+type SharedReceiver<T> = Arc<Mutex<mpsc::UnboundedReceiver<T>>>;
+
+/// Resolves with the result of `future`, or fails with `ExpectTimeout` if it
+/// does not complete within `duration`.
+pub fn within<F>(future: F, duration: Duration) -> Within<F>
+where
+    F: Future<Error = Error>,
+{
+    Within {
+        future: future,
+        timeout: Timeout::new(duration, &context::handle()).expect("failed to create timeout"),
+        duration: duration,
+    }
+}
+
+pub struct Within<F> {
+    future: F,
+    timeout: Timeout,
+    duration: Duration,
+}
+
+impl<F> Future for Within<F>
+where
+    F: Future<Error = Error>,
+{
+    type Item = F::Item;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Async::Ready(item) = self.future.poll()? {
+            return Ok(Async::Ready(item));
+        }
+        match self.timeout.poll()? {
+            Async::Ready(()) => bail!(ErrorKind::ExpectTimeout(self.duration)),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+fn expect_next<T>(
+    receiver: SharedReceiver<T>,
+    duration: Duration,
+) -> impl Future<Item = T, Error = Error> {
+    let next = future::poll_fn(move || match receiver.lock().poll() {
+        Ok(Async::Ready(Some(item))) => Ok(Async::Ready(item)),
+        Ok(Async::Ready(None)) | Err(()) => bail!(ErrorKind::ProbeClosed),
+        Ok(Async::NotReady) => Ok(Async::NotReady),
+    });
+    within(next, duration)
+}
+
+fn expect_none<T>(
+    receiver: SharedReceiver<T>,
+    duration: Duration,
+) -> impl Future<Item = (), Error = Error> {
+    expect_next(receiver, duration).then(|result| match result {
+        Ok(_) => bail!(ErrorKind::UnexpectedMessage(type_name::<T>())),
+        Err(Error(ErrorKind::ExpectTimeout(_), _)) => Ok(()),
+        Err(e) => Err(e),
+    })
+}
+
+/// Resolves once `actor_ref` has stopped, failing if that takes longer than
+/// `duration`.
+pub fn expect_terminated<A>(
+    actor_ref: &ActorRef<A>,
+    duration: Duration,
+) -> impl Future<Item = (), Error = Error>
+where
+    A: Actor,
+{
+    let terminated = actor_ref
+        .terminated()
+        .map_err(|_| ErrorKind::ActorDestroyed.into());
+    within(terminated, duration)
+}
+
+pub struct ProbeActor<M> {
+    sender: mpsc::UnboundedSender<M>,
+}
+
+impl<M> Actor for ProbeActor<M>
+where
+    M: Send + 'static,
+{
+}
+
+impl<M> MessageHandler<M> for ProbeActor<M>
+where
+    M: Send + 'static,
+{
+    type Response = ();
+
+    fn handle(&mut self, message: M) -> ActorResponse<()> {
+        let _ = self.sender.unbounded_send(message);
+        self.done()
+    }
+}
+
+/// An actor that records the messages it receives.
+pub struct TestProbe<M> {
+    actor_ref: ActorRef<ProbeActor<M>>,
+    receiver: SharedReceiver<M>,
+}
+
+impl<M> TestProbe<M>
+where
+    M: Send + 'static,
+{
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded();
+        let actor = ProbeActor {
+            sender: sender,
+        };
+        Self {
+            actor_ref: actor.start(),
+            receiver: Arc::new(Mutex::new(receiver)),
+        }
+    }
+
+    pub fn actor_ref(&self) -> ActorRef<ProbeActor<M>> {
+        self.actor_ref.clone()
+    }
+
+    /// Resolves with the next message received by the probe.
+    pub fn expect_msg(&self, duration: Duration) -> impl Future<Item = M, Error = Error> {
+        expect_next(self.receiver.clone(), duration)
+    }
+
+    /// Fails if the probe receives a message within `duration`.
+    pub fn expect_no_msg(&self, duration: Duration) -> impl Future<Item = (), Error = Error> {
+        expect_none(self.receiver.clone(), duration)
+    }
+}
+
+/// Records dead letters published by an `ActorSystem`.
+pub struct DeadLetterProbe {
+    receiver: SharedReceiver<DeadLetter>,
+}
+
+impl DeadLetterProbe {
+    pub fn new(system: &ActorSystem) -> Self {
+        Self {
+            receiver: Arc::new(Mutex::new(system.subscribe_dead_letters())),
+        }
+    }
+
+    pub fn expect_dead_letter(
+        &self,
+        duration: Duration,
+    ) -> impl Future<Item = DeadLetter, Error = Error> {
+        expect_next(self.receiver.clone(), duration)
+    }
+
+    pub fn expect_no_dead_letter(
+        &self,
+        duration: Duration,
+    ) -> impl Future<Item = (), Error = Error> {
+        expect_none(self.receiver.clone(), duration)
+    }
+}
+
+type StubResponder<M> = Box<FnMut(M) -> <M as ActorMessage>::Response + Send>;
+
+/// Stands in for an `#[actor_api]` actor. Any `XxxRef` method whose message
+/// has a registered response can be called on an `ActorRef<ActorStub>`;
+/// requests without a registered response fail.
+pub struct ActorStub {
+    responders: HashMap<TypeId, Box<Any + Send>>,
+}
+
+impl ActorStub {
+    pub fn new() -> Self {
+        Self {
+            responders: HashMap::new(),
+        }
+    }
+
+    pub fn on<M, F>(mut self, responder: F) -> Self
+    where
+        M: ActorMessage,
+        F: FnMut(M) -> M::Response + Send + 'static,
+    {
+        let responder: StubResponder<M> = Box::new(responder);
+        self.responders
+            .insert(TypeId::of::<M>(), Box::new(responder));
+        self
+    }
+}
+
+impl Actor for ActorStub {}
+
+impl<M> MessageHandler<M> for ActorStub
+where
+    M: ActorMessage,
+{
+    type Response = M::Response;
+
+    fn handle(&mut self, message: M) -> ActorResponse<M::Response> {
+        let maybe_response = self.responders
+            .get_mut(&TypeId::of::<M>())
+            .and_then(|responder| responder.downcast_mut::<StubResponder<M>>())
+            .map(|responder| responder(message));
+        match maybe_response {
+            Some(response) => self.respond(response),
+            None => {
+                warn!("No stubbed response for '{}'", type_name::<M>());
+                ActorResponse::Async(Box::new(future::err(())))
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+use std::intrinsics;
+
+pub(crate) fn type_name<T>() -> &'static str {
+    unsafe { intrinsics::type_name::<T>() }
+}
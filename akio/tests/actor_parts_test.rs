@@ -0,0 +1,81 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+
+mod common;
+
+use akio::prelude::*;
+use common::*;
+
+pub struct Counter {
+    count: u64,
+    events: Vec<&'static str>,
+}
+
+impl Counter {
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+            events: Vec::new(),
+        }
+    }
+}
+
+#[actor_impl(parts(admin))]
+impl Counter {
+    #[on_start]
+    fn start_counting(&mut self) {
+        self.events.push("counter");
+        self.done()
+    }
+
+    #[actor_api]
+    pub fn increment(&mut self) -> u64 {
+        self.count += 1;
+        self.respond(self.count)
+    }
+
+    #[actor_api]
+    pub fn events(&mut self) -> Vec<&'static str> {
+        self.respond(self.events.clone())
+    }
+}
+
+mod admin {
+    use akio::prelude::*;
+    use super::Counter;
+
+    #[actor_impl(part(admin))]
+    impl Counter {
+        #[on_start]
+        fn start_admin(&mut self) {
+            self.events.push("admin");
+            self.done()
+        }
+
+        #[actor_api]
+        pub fn reset(&mut self) {
+            self.count = 0;
+            self.done()
+        }
+    }
+}
+
+use admin::CounterAdminRef;
+
+#[test]
+fn test_actor_api_across_blocks() {
+    let count = with_actor_system_async(|_| {
+        let counter = Counter::new().start();
+        counter.send_increment();
+        counter.send_reset();
+        counter.increment()
+    });
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn test_hooks_from_all_blocks_run() {
+    let events = with_actor_system_async(|_| Counter::new().start().events());
+    assert_eq!(events, vec!["counter", "admin"]);
+}
@@ -0,0 +1,86 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+
+mod common;
+
+use akio::prelude::*;
+use akio::testkit::ActorStub;
+use common::*;
+
+#[actor_trait]
+pub trait Counter {
+    fn increment(&mut self, by: u64) -> u64;
+}
+
+struct MemoryCounter {
+    count: u64,
+}
+
+#[actor_impl]
+impl MemoryCounter {
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+        }
+    }
+}
+
+#[actor_impl]
+impl Counter for MemoryCounter {
+    fn increment(&mut self, by: u64) -> u64 {
+        self.count += by;
+        self.respond(self.count)
+    }
+}
+
+struct DoublingCounter {
+    count: u64,
+}
+
+#[actor_impl]
+impl DoublingCounter {
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+        }
+    }
+}
+
+#[actor_impl]
+impl Counter for DoublingCounter {
+    fn increment(&mut self, by: u64) -> u64 {
+        self.count += by * 2;
+        self.respond(self.count)
+    }
+}
+
+#[test]
+fn test_swappable_implementations() {
+    let counts = with_actor_system_async(|_| {
+        let counters: Vec<CounterRef> = vec![
+            MemoryCounter::new().start().into(),
+            DoublingCounter::new().start().into(),
+            ActorStub::new()
+                .on(|message: CounterMessageIncrement| message.0 * 100)
+                .start()
+                .into(),
+        ];
+        let increments = counters
+            .iter()
+            .map(|counter| counter.increment(1))
+            .collect::<Vec<_>>();
+        future::join_all(increments)
+    });
+    assert_eq!(counts, vec![1, 2, 100]);
+}
+
+#[test]
+fn test_send_through_trait_ref() {
+    let count = with_actor_system_async(|_| {
+        let counter: CounterRef = MemoryCounter::new().start().into();
+        counter.send_increment(5);
+        counter.increment(1)
+    });
+    assert_eq!(count, 6);
+}
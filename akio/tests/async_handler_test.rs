@@ -0,0 +1,110 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+extern crate futures;
+
+mod common;
+
+use akio::prelude::*;
+use common::*;
+use futures::sync::oneshot;
+
+pub struct Account {
+    balance: u64,
+}
+
+#[actor_impl]
+impl Account {
+    pub fn new() -> Self {
+        Self {
+            balance: 0,
+        }
+    }
+
+    #[actor_api]
+    pub fn deposit_after(&mut self, amount: u64, signal: oneshot::Receiver<()>) -> u64 {
+        self.respond_async(signal, move |account, _| {
+            account.balance += amount;
+            account.respond(account.balance)
+        })
+    }
+
+    #[actor_api]
+    pub fn balance(&mut self) -> u64 {
+        self.respond(self.balance)
+    }
+
+    #[actor_api]
+    pub fn passed_through_gate(&mut self) -> u64 {
+        Gate::new().respond_async(Ok::<(), ()>(()), |gate, _| gate.respond(gate.passed))
+    }
+}
+
+pub struct Gate {
+    release: Option<oneshot::Sender<()>>,
+    passed: u64,
+}
+
+#[actor_impl]
+impl Gate {
+    pub fn new() -> Self {
+        Self {
+            release: None,
+            passed: 0,
+        }
+    }
+
+    #[actor_api(reentrant)]
+    pub fn pass(&mut self) -> u64 {
+        let (release, released) = oneshot::channel();
+        self.release = Some(release);
+        self.respond_async(released, |gate, _| {
+            gate.passed += 1;
+            gate.respond(gate.passed)
+        })
+    }
+
+    #[actor_api]
+    pub fn open(&mut self) {
+        if let Some(release) = self.release.take() {
+            let _ = release.send(());
+        }
+        self.done()
+    }
+}
+
+#[test]
+fn test_non_reentrant_handler_holds_the_mailbox() {
+    let balances = with_actor_system_async(|_| {
+        let (signal, waiter) = oneshot::channel();
+        let account = Account::new().start();
+        let deposit = account.deposit_after(10, waiter);
+        let balance = account.balance();
+        signal.send(()).unwrap();
+        deposit.join(balance)
+    });
+    assert_eq!(balances, (10, 10));
+}
+
+#[test]
+fn test_reentrant_handler_lets_messages_interleave() {
+    let passed = with_actor_system_async(|_| {
+        let gate = Gate::new().start();
+        let pass = gate.pass();
+        gate.send_open();
+        pass
+    });
+    assert_eq!(passed, 1);
+}
+
+#[test]
+fn test_continuation_of_another_actor_is_dropped() {
+    let failed = with_actor_system_async(|_| {
+        let account = Account::new().start();
+        account
+            .passed_through_gate()
+            .then(|result| Ok(result.is_err()))
+            .join(account.balance())
+    });
+    assert_eq!(failed, (true, 0));
+}
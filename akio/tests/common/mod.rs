@@ -5,4 +5,5 @@ mod test_actor;
 
 pub use self::system::with_actor_system;
 pub use self::system::with_actor_system_async;
+pub use self::system::with_actor_system_local;
 pub use self::test_actor::*;
@@ -20,6 +20,26 @@ where
     result.unwrap().unwrap()
 }
 
+/// Like `with_actor_system_async`, for futures that aren't `Send`, such as
+/// timers and the testkit's expectations.
+pub fn with_actor_system_local<F, R, U>(f: F) -> R
+where
+    F: FnOnce(ActorSystem) -> U + Send + 'static,
+    U: Future<Item = R, Error = ()> + 'static,
+    R: Send + 'static,
+{
+    let mut system = ActorSystem::new();
+    let system_clone = system.clone();
+    let (sender, receiver) = oneshot::channel();
+    system.on_startup(move || {
+        let fut = f(system_clone.clone()).then(|f_result| Ok(sender.send(f_result).ok().unwrap()));
+        context::handle().spawn(fut);
+    });
+    let result = receiver.wait();
+    system.stop();
+    result.unwrap().unwrap()
+}
+
 pub fn with_actor_system<F, R>(f: F) -> R
 where
     F: FnOnce(ActorSystem) -> R + Send + 'static,
@@ -0,0 +1,16 @@
+#![feature(proc_macro)]
+extern crate akio;
+
+use akio::prelude::*;
+
+struct Counter {
+    count: u64,
+}
+
+#[actor_impl] //~ ERROR #[actor_api] must decorate a method, but `LIMIT` is not one
+impl Counter {
+    #[actor_api]
+    const LIMIT: u64 = 10;
+}
+
+fn main() {}
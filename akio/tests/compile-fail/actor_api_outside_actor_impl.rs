@@ -0,0 +1,15 @@
+#![feature(proc_macro)]
+extern crate akio;
+
+use akio::prelude::*;
+
+struct Counter {
+    count: u64,
+}
+
+#[actor_api] //~ ERROR #[actor_api] can only be used on methods inside an #[actor_impl] block
+fn increment(counter: &mut Counter) {
+    counter.count += 1;
+}
+
+fn main() {}
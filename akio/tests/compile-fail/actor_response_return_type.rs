@@ -0,0 +1,18 @@
+#![feature(proc_macro)]
+extern crate akio;
+
+use akio::prelude::*;
+
+struct Counter {
+    count: u64,
+}
+
+#[actor_impl] //~ ERROR #[actor_api] method `get` should declare the response type itself
+impl Counter {
+    #[actor_api]
+    fn get(&mut self) -> ActorResponse<u64> {
+        self.respond(self.count)
+    }
+}
+
+fn main() {}
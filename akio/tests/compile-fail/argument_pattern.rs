@@ -0,0 +1,19 @@
+#![feature(proc_macro)]
+extern crate akio;
+
+use akio::prelude::*;
+
+struct Counter {
+    count: u64,
+}
+
+#[actor_impl] //~ ERROR #[actor_api] method `add` uses an unsupported argument pattern
+impl Counter {
+    #[actor_api]
+    fn add(&mut self, (a, b): (u64, u64)) {
+        self.count += a + b;
+        self.done()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,25 @@
+#![feature(proc_macro)]
+extern crate akio;
+
+use akio::prelude::*;
+
+struct Counter {
+    count: u64,
+}
+
+#[actor_impl] //~ ERROR duplicate #[on_start] hook `log_start` for `Counter`
+impl Counter {
+    #[on_start]
+    fn reset(&mut self) {
+        self.count = 0;
+        self.done()
+    }
+
+    #[on_start]
+    fn log_start(&self) {
+        println!("starting");
+        self.done()
+    }
+}
+
+fn main() {}
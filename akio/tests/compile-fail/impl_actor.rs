@@ -0,0 +1,15 @@
+#![feature(proc_macro)]
+extern crate akio;
+
+use akio::prelude::*;
+
+struct Counter {
+    count: u64,
+}
+
+#[actor_impl] //~ ERROR #[actor_impl] generates the `Actor` impl itself
+impl Actor for Counter {
+    fn on_start(&mut self) {}
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+#![feature(proc_macro)]
+extern crate akio;
+
+use akio::prelude::*;
+
+struct Counter {
+    count: u64,
+}
+
+#[actor_impl] //~ ERROR #[actor_api] method `increment` must return an `ActorResponse`
+impl Counter {
+    #[actor_api]
+    fn increment(&mut self) {
+        self.count += 1;
+    }
+}
+
+fn main() {}
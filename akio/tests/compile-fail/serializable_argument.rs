@@ -0,0 +1,21 @@
+#![feature(proc_macro)]
+extern crate akio;
+
+use akio::prelude::*;
+
+pub struct Connection;
+
+pub struct Pool {
+    connections: Vec<Connection>,
+}
+
+#[actor_impl(serializable)] //~ ERROR must implement serde's `Serialize` and `Deserialize`
+impl Pool {
+    #[actor_api]
+    pub fn add(&mut self, connection: Connection) {
+        self.connections.push(connection);
+        self.done()
+    }
+}
+
+fn main() {}
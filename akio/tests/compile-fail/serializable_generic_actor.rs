@@ -0,0 +1,19 @@
+#![feature(proc_macro)]
+extern crate akio;
+
+use akio::prelude::*;
+
+struct Holder<T> {
+    value: Option<T>,
+}
+
+#[actor_impl(serializable)] //~ ERROR #[actor_impl(serializable)] can't be used on generic actor `Holder`
+impl<T> Holder<T> {
+    #[actor_api]
+    fn put(&mut self, value: T) {
+        self.value = Some(value);
+        self.done()
+    }
+}
+
+fn main() {}
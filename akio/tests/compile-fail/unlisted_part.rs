@@ -0,0 +1,32 @@
+#![feature(proc_macro)]
+extern crate akio;
+
+use akio::prelude::*;
+
+struct Counter {
+    count: u64,
+}
+
+#[actor_impl]
+impl Counter {
+    #[actor_api]
+    pub fn increment(&mut self) -> u64 {
+        self.count += 1;
+        self.respond(self.count)
+    }
+}
+
+mod admin {
+    use akio::prelude::*;
+    use super::Counter;
+
+    #[actor_impl(part(admin))] //~ ERROR part `admin` of `Counter` must be listed in the primary block's #[actor_impl(parts(..))]
+    impl Counter {
+        #[on_start]
+        fn start_admin(&mut self) {
+            self.done()
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,41 @@
+extern crate compiletest_rs as compiletest;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// The newest `akio` rlib next to this test, which is the one cargo just
+/// built. Older builds may still be in `deps`, so the tests name it with
+/// `--extern` rather than letting rustc choose.
+fn akio_rlib() -> PathBuf {
+    let exe = env::current_exe().expect("failed to find the test executable");
+    let deps = exe.parent().expect("test executable has no directory");
+    fs::read_dir(deps)
+        .expect("failed to read the deps directory")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with("libakio-") && name.ends_with(".rlib")
+        })
+        .max_by_key(|entry| entry.metadata().and_then(|metadata| metadata.modified()).ok())
+        .map(|entry| entry.path())
+        .expect("no akio rlib found")
+}
+
+#[test]
+fn test_macro_diagnostics() {
+    let mut config = compiletest::Config::default();
+    config.mode = "compile-fail".parse().unwrap();
+    config.src_base = PathBuf::from("tests/compile-fail");
+    config.link_deps();
+    config.clean_rmeta();
+    let flags = config.target_rustcflags.take().unwrap_or_else(String::new);
+    // Only the errors are under test; warnings, which vary between
+    // compilers, would otherwise fail them.
+    config.target_rustcflags = Some(format!(
+        "{} --extern akio={} -A warnings",
+        flags,
+        akio_rlib().display()
+    ));
+    compiletest::run_tests(&config);
+}
@@ -0,0 +1,236 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+extern crate futures;
+#[macro_use]
+extern crate serde_derive;
+extern crate tokio_core;
+
+mod common;
+
+use akio::errors::ErrorKind;
+use akio::persistence::{FileJournal, MemoryJournal};
+use akio::prelude::*;
+use common::*;
+use std::env;
+use std::time::Duration;
+use tokio_core::reactor::Timeout;
+
+#[derive(Deserialize, Serialize)]
+pub struct Step(u64);
+
+#[derive(Deserialize, Serialize)]
+pub struct SlowStep(u64);
+
+#[derive(Deserialize, Serialize)]
+pub struct GetSteps;
+
+pub struct Workflow {
+    steps: Vec<u64>,
+    // Whether slow steps wait forever.
+    stalled: bool,
+}
+
+impl Actor for Workflow {}
+
+impl MessageHandler<Step> for Workflow {
+    type Response = ();
+
+    fn handle(&mut self, step: Step) -> ActorResponse<()> {
+        self.steps.push(step.0);
+        self.done()
+    }
+}
+
+impl MessageHandler<SlowStep> for Workflow {
+    type Response = ();
+
+    fn handle(&mut self, step: SlowStep) -> ActorResponse<()> {
+        if !self.stalled {
+            self.steps.push(step.0);
+            return self.done();
+        }
+        self.respond_async(future::empty::<(), ()>(), move |workflow, _| {
+            workflow.steps.push(step.0);
+            workflow.done()
+        }).reentrant()
+    }
+}
+
+impl MessageHandler<GetSteps> for Workflow {
+    type Response = Vec<u64>;
+
+    fn handle(&mut self, _: GetSteps) -> ActorResponse<Vec<u64>> {
+        self.respond(self.steps.clone())
+    }
+}
+
+fn workflow() -> Workflow {
+    Workflow {
+        steps: Vec::new(),
+        stalled: false,
+    }
+}
+
+fn sleep(millis: u64) -> impl Future<Item = (), Error = ()> {
+    Timeout::new(Duration::from_millis(millis), &context::handle())
+        .expect("failed to create timeout")
+        .map_err(|_| ())
+}
+
+fn get_steps(system: &ActorSystem) -> impl Future<Item = Vec<u64>, Error = ()> {
+    system
+        .grain::<Workflow>(&"workflow")
+        .request(GetSteps)
+        .and_then(|response| response)
+}
+
+#[test]
+fn test_logged_messages_survive_restart() {
+    let directory = env::temp_dir().join(format!("akio-mailbox-{}", Uuid::new_v4()));
+    let journal = FileJournal::open(&directory).unwrap();
+    with_actor_system_local(move |system| {
+        system
+            .register_durable_mailbox::<Workflow, Step, _>(journal)
+            .unwrap();
+        // Not started in this system, so the messages are only logged.
+        let workflow = system.grain::<Workflow>(&"workflow");
+        for step in 1..4 {
+            workflow.send_durable(Step(step)).unwrap();
+        }
+        future::ok(())
+    });
+
+    let journal = FileJournal::open(&directory).unwrap();
+    let steps = with_actor_system_local(move |system| {
+        system
+            .register_durable_mailbox::<Workflow, Step, _>(journal)
+            .unwrap();
+        system.register_factory(|_| workflow());
+        get_steps(&system)
+    });
+    assert_eq!(steps, vec![1, 2, 3]);
+}
+
+#[test]
+fn test_handled_messages_are_not_replayed() {
+    let journal = MemoryJournal::new();
+    let restarted = journal.clone();
+    let steps = with_actor_system_local(move |system| {
+        system
+            .register_durable_mailbox::<Workflow, Step, _>(journal)
+            .unwrap();
+        system.register_factory(|_| workflow());
+        let workflow = system.grain::<Workflow>(&"workflow");
+        workflow.send_durable(Step(1)).unwrap();
+        workflow.send_durable(Step(2)).unwrap();
+        // Acknowledgements are written after the responses are sent.
+        get_steps(&system).and_then(|steps| sleep(50).map(move |_| steps))
+    });
+    assert_eq!(steps, vec![1, 2]);
+
+    let steps = with_actor_system_local(move |system| {
+        system
+            .register_durable_mailbox::<Workflow, Step, _>(restarted)
+            .unwrap();
+        system.register_factory(|_| workflow());
+        let workflow = system.grain::<Workflow>(&"workflow");
+        workflow.send_durable(Step(3)).unwrap();
+        get_steps(&system)
+    });
+    assert_eq!(steps, vec![3]);
+}
+
+#[test]
+fn test_messages_are_acknowledged_after_their_continuation() {
+    let journal = MemoryJournal::new();
+    let restarted = journal.clone();
+    let steps = with_actor_system_local(move |system| {
+        system
+            .register_durable_mailbox::<Workflow, SlowStep, _>(journal)
+            .unwrap();
+        system.register_factory(|_| Workflow {
+            stalled: true,
+            ..workflow()
+        });
+        let workflow = system.grain::<Workflow>(&"workflow");
+        workflow.send_durable(SlowStep(7)).unwrap();
+        // The system stops while the continuation is waiting.
+        get_steps(&system).and_then(|steps| sleep(50).map(move |_| steps))
+    });
+    assert!(steps.is_empty());
+
+    let steps = with_actor_system_local(move |system| {
+        system
+            .register_durable_mailbox::<Workflow, SlowStep, _>(restarted)
+            .unwrap();
+        system.register_factory(|_| workflow());
+        get_steps(&system)
+    });
+    assert_eq!(steps, vec![7]);
+}
+
+#[test]
+fn test_mailboxes_are_kept_per_message_type() {
+    let journal = MemoryJournal::new();
+    let restarted = journal.clone();
+    with_actor_system_local(move |system| {
+        system
+            .register_durable_mailbox::<Workflow, Step, _>(journal.clone())
+            .unwrap();
+        system
+            .register_durable_mailbox::<Workflow, SlowStep, _>(journal)
+            .unwrap();
+        let workflow = system.grain::<Workflow>(&"workflow");
+        workflow.send_durable(Step(1)).unwrap();
+        workflow.send_durable(SlowStep(2)).unwrap();
+        future::ok(())
+    });
+
+    let mut steps = with_actor_system_local(move |system| {
+        system
+            .register_durable_mailbox::<Workflow, Step, _>(restarted.clone())
+            .unwrap();
+        system
+            .register_durable_mailbox::<Workflow, SlowStep, _>(restarted)
+            .unwrap();
+        system.register_factory(|_| workflow());
+        get_steps(&system)
+    });
+    steps.sort();
+    assert_eq!(steps, vec![1, 2]);
+}
+
+#[test]
+fn test_duplicate_durable_mailbox() {
+    with_actor_system(|system| {
+        system
+            .register_durable_mailbox::<Workflow, Step, _>(MemoryJournal::new())
+            .unwrap();
+        match system.register_durable_mailbox::<Workflow, Step, _>(MemoryJournal::new()) {
+            Err(e) => match *e.kind() {
+                ErrorKind::DurableMailboxExists(..) => {}
+                ref kind => panic!("unexpected error: {}", kind),
+            },
+            Ok(()) => panic!("registered two durable mailboxes for the same message type"),
+        }
+    })
+}
+
+#[test]
+fn test_send_durable_requires_registered_message_type() {
+    with_actor_system_local(|system| {
+        system
+            .register_durable_mailbox::<Workflow, Step, _>(MemoryJournal::new())
+            .unwrap();
+        let workflow = workflow().start();
+        match workflow.send_durable(GetSteps) {
+            Err(e) => match *e.kind() {
+                ErrorKind::NoDurableMailbox(..) => {}
+                ref kind => panic!("unexpected error: {}", kind),
+            },
+            Ok(()) => panic!("sent a message without a durable mailbox"),
+        }
+        future::ok(())
+    })
+}
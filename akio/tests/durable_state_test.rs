@@ -0,0 +1,154 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+#[macro_use]
+extern crate serde_derive;
+
+mod common;
+
+use akio::errors::ErrorKind;
+use akio::persistence::*;
+use akio::prelude::*;
+use common::*;
+use std::env;
+use std::thread;
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Profile {
+    name: String,
+    visits: u64,
+}
+
+pub struct ProfileActor {
+    user: String,
+    profile: DurableState<Profile>,
+}
+
+#[actor_impl]
+impl ProfileActor {
+    pub fn new(user: &str) -> Self {
+        Self {
+            user: user.to_string(),
+            profile: DurableState::new(Profile::default()),
+        }
+    }
+
+    #[actor_api]
+    pub fn visit(&mut self, name: String) -> u64 {
+        let profile = Profile {
+            name: name,
+            visits: self.profile.visits + 1,
+        };
+        self.persist_state(profile).expect("failed to save");
+        self.respond(self.profile.visits)
+    }
+
+    #[actor_api]
+    pub fn profile(&mut self) -> (Profile, u64) {
+        self.respond(((*self.profile).clone(), self.profile.version()))
+    }
+}
+
+impl DurableStateActor for ProfileActor {
+    type State = Profile;
+
+    fn persistence_id(&self) -> String {
+        format!("profile-{}", self.user)
+    }
+
+    fn durable_state(&mut self) -> &mut DurableState<Profile> {
+        &mut self.profile
+    }
+}
+
+#[test]
+fn test_state_is_loaded_on_start() {
+    let (profile, version) = with_actor_system_async(|system| {
+        system.register_state_store::<ProfileActor, _>(MemoryStateStore::new());
+        let actor = ProfileActor::new("ann").start();
+        actor
+            .visit("Ann".to_string())
+            .join(actor.visit("Annie".to_string()))
+            .and_then(move |_| actor.stop())
+            // The state is loaded by `start`, before any message.
+            .and_then(|_| ProfileActor::new("ann").start().profile())
+    });
+    assert_eq!(
+        profile,
+        Profile {
+            name: "Annie".to_string(),
+            visits: 2,
+        }
+    );
+    assert_eq!(version, 2);
+}
+
+#[test]
+fn test_file_state_store_detects_concurrent_writers() {
+    let directory = env::temp_dir().join(format!("akio-state-{}", Uuid::new_v4()));
+    let store = FileStateStore::open(&directory).unwrap();
+    assert_eq!(store.save("a", 0, vec![1]).unwrap(), 1);
+    assert_eq!(store.save("a", 1, vec![2]).unwrap(), 2);
+    match store.save("a", 1, vec![3]) {
+        Err(e) => match *e.kind() {
+            ErrorKind::VersionConflict(_, expected, actual) => {
+                assert_eq!((expected, actual), (1, 2))
+            }
+            ref kind => panic!("unexpected error: {}", kind),
+        },
+        Ok(version) => panic!("saved conflicting state as version {}", version),
+    }
+
+    let reopened = FileStateStore::open(&directory).unwrap();
+    assert_eq!(
+        reopened.load("a").unwrap(),
+        Some(VersionedState {
+            version: 2,
+            payload: vec![2],
+        })
+    );
+    assert_eq!(reopened.load("b").unwrap(), None);
+}
+
+#[test]
+fn test_file_state_stores_on_the_same_directory_detect_each_others_writes() {
+    let directory = env::temp_dir().join(format!("akio-state-{}", Uuid::new_v4()));
+    let first = FileStateStore::open(&directory).unwrap();
+    let second = FileStateStore::open(&directory).unwrap();
+    assert_eq!(first.save("a", 0, vec![1]).unwrap(), 1);
+    match second.save("a", 0, vec![2]) {
+        Err(e) => match *e.kind() {
+            ErrorKind::VersionConflict(_, expected, actual) => {
+                assert_eq!((expected, actual), (0, 1))
+            }
+            ref kind => panic!("unexpected error: {}", kind),
+        },
+        Ok(version) => panic!("saved conflicting state as version {}", version),
+    }
+
+    // Each store increments the stored number, retrying on conflicts.
+    let writers = vec![first, second]
+        .into_iter()
+        .map(|store| {
+            thread::spawn(move || for _ in 0..50 {
+                loop {
+                    let state = store.load("a").unwrap().unwrap();
+                    let next = vec![state.payload[0].wrapping_add(1)];
+                    if store.save("a", state.version, next).is_ok() {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    let state = FileStateStore::open(&directory)
+        .unwrap()
+        .load("a")
+        .unwrap()
+        .unwrap();
+    assert_eq!(state.version, 101);
+    assert_eq!(state.payload, vec![101]);
+}
@@ -0,0 +1,49 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+
+mod common;
+
+use akio::prelude::*;
+use common::*;
+
+struct HeadersActor;
+
+#[actor_impl]
+impl HeadersActor {
+    #[actor_api]
+    pub fn current_headers(&mut self) -> Headers {
+        self.respond(self.headers().expect("handled message has headers"))
+    }
+}
+
+#[test]
+fn test_handler_reads_headers() {
+    let (sent, received) = with_actor_system_async(|_| {
+        let mut headers = Headers::new();
+        headers.insert("idempotency-key", "abc");
+        let actor_ref = HeadersActor.start().with_headers(headers.clone());
+        actor_ref
+            .current_headers()
+            .map(move |received| (headers, received))
+    });
+    assert_eq!(received.correlation_id, sent.correlation_id);
+    assert_eq!(received.get("idempotency-key"), Some("abc"));
+}
+
+#[test]
+fn test_correlation_id_propagates_from_handler() {
+    let (outer, first, second) = with_actor_system_async(|_| {
+        // This closure runs inside a handler, so new headers inherit its
+        // correlation id.
+        let outer = Headers::current().unwrap();
+        let actor_ref = HeadersActor.start();
+        actor_ref
+            .current_headers()
+            .join(actor_ref.current_headers())
+            .map(move |(first, second)| (outer, first, second))
+    });
+    assert_eq!(first.correlation_id, outer.correlation_id);
+    assert_eq!(second.correlation_id, outer.correlation_id);
+    assert!(first.sender.is_some());
+}
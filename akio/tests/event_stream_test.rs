@@ -0,0 +1,85 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+
+mod common;
+
+use akio::{ActorStarted, ActorStopped};
+use akio::prelude::*;
+use akio::testkit::*;
+use common::*;
+use std::time::Duration;
+
+fn timeout() -> Duration {
+    Duration::from_millis(500)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrgCreated(u64);
+
+#[test]
+fn test_publish_to_subscribers() {
+    let event = with_actor_system_local(|system| {
+        let probe = TestProbe::<OrgCreated>::new();
+        system.subscribe(probe.actor_ref().recipient());
+        system.publish(OrgCreated(1));
+        probe
+            .expect_msg(timeout())
+            .map_err(|e| panic!("{}", e))
+    });
+    assert_eq!(event, OrgCreated(1));
+}
+
+#[test]
+fn test_topic_subscribers_only_receive_their_topic() {
+    let event = with_actor_system_local(|system| {
+        let probe = TestProbe::<OrgCreated>::new();
+        system.subscribe_topic("orgs", probe.actor_ref().recipient());
+        system.publish(OrgCreated(1));
+        system.publish_topic("users", OrgCreated(2));
+        system.publish_topic("orgs", OrgCreated(3));
+        probe
+            .expect_msg(timeout())
+            .map_err(|e| panic!("{}", e))
+    });
+    assert_eq!(event, OrgCreated(3));
+}
+
+#[test]
+fn test_stopped_subscribers_are_removed() {
+    with_actor_system_local(|system| {
+        let dead_letters = DeadLetterProbe::new(&system);
+        let probe = TestProbe::<OrgCreated>::new();
+        system.subscribe(probe.actor_ref().recipient());
+        probe.actor_ref().stop().and_then(move |_| {
+            system.publish(OrgCreated(1));
+            dead_letters
+                .expect_no_dead_letter(Duration::from_millis(50))
+                .map_err(|e| panic!("{}", e))
+        })
+    })
+}
+
+#[test]
+fn test_lifecycle_events() {
+    let (actor_id, started, stopped) = with_actor_system_local(|system| {
+        let started = TestProbe::<ActorStarted>::new();
+        let stopped = TestProbe::<ActorStopped>::new();
+        system.subscribe(started.actor_ref().recipient());
+        system.subscribe(stopped.actor_ref().recipient());
+        let actor_ref = TestActor::new().start();
+        let actor_id = actor_ref.id();
+        actor_ref
+            .stop()
+            .and_then(move |_| started.expect_msg(timeout()).map_err(|e| panic!("{}", e)))
+            .and_then(move |started| {
+                stopped
+                    .expect_msg(timeout())
+                    .map_err(|e| panic!("{}", e))
+                    .map(move |stopped| (actor_id, started, stopped))
+            })
+    });
+    assert_eq!(started.actor_id, actor_id);
+    assert_eq!(stopped.actor_id, actor_id);
+    assert_eq!(stopped.actor_type, started.actor_type);
+}
@@ -0,0 +1,82 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+
+mod common;
+
+use akio::prelude::*;
+use common::*;
+
+struct ProxyActor {
+    target: ActorRef<TestActor>,
+    waiting: Vec<Responder<u64>>,
+}
+
+#[actor_impl]
+impl ProxyActor {
+    pub fn new(target: ActorRef<TestActor>) -> Self {
+        Self {
+            target: target,
+            waiting: Vec::new(),
+        }
+    }
+
+    #[actor_api]
+    pub fn get_test_method_calls(&mut self) -> u64 {
+        self.forward(&self.target, TestActorMessageGetTestMethodCalls())
+    }
+
+    #[actor_api]
+    pub fn wait_for_release(&mut self) -> u64 {
+        let responder = self.responder();
+        self.waiting.push(responder);
+        self.deferred()
+    }
+
+    #[actor_api]
+    pub fn take_mistyped_responder(&mut self) -> bool {
+        let responder: Responder<String> = self.responder();
+        self.respond(responder.is_expected())
+    }
+
+    #[actor_api]
+    pub fn release(&mut self, value: u64) {
+        self.waiting
+            .drain(..)
+            .for_each(|responder| responder.respond(value));
+        self.done()
+    }
+}
+
+#[test]
+fn test_forward_replies_to_original_caller() {
+    let calls = with_actor_system_async(|_| {
+        let target = TestActor::new().start();
+        target.send_test_method();
+        target.send_test_method();
+        let proxy = ProxyActor::new(target).start();
+        proxy.get_test_method_calls()
+    });
+    assert_eq!(calls, 2);
+}
+
+#[test]
+fn test_responder_completed_later() {
+    let values = with_actor_system_async(|_| {
+        let proxy = ProxyActor::new(TestActor::new().start()).start();
+        let first = proxy.wait_for_release();
+        let second = proxy.wait_for_release();
+        proxy.send_release(7);
+        first.join(second)
+    });
+    assert_eq!(values, (7, 7));
+}
+
+#[test]
+fn test_mistyped_responder_is_empty() {
+    let expected = with_actor_system_async(|_| {
+        let proxy = ProxyActor::new(TestActor::new().start()).start();
+        proxy.take_mistyped_responder()
+    });
+    assert!(!expected);
+}
@@ -0,0 +1,67 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+
+mod common;
+
+use akio::prelude::*;
+use common::*;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+pub struct Cache<K, V> {
+    entries: HashMap<K, V>,
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Eq + Hash,
+{
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+#[actor_impl]
+impl<K, V: Clone> Cache<K, V>
+where
+    K: Eq + Hash,
+{
+    #[actor_api]
+    pub fn put(&mut self, key: K, value: V) {
+        self.entries.insert(key, value);
+        self.done()
+    }
+
+    #[actor_api]
+    pub fn get(&mut self, key: K) -> Option<V> {
+        let value = self.entries.get(&key).cloned();
+        self.respond(value)
+    }
+}
+
+#[test]
+fn test_generic_actor() {
+    let values = with_actor_system_async(|_| {
+        let cache = Cache::<String, u64>::new().start();
+        cache.send_put("a".to_string(), 1);
+        cache
+            .get("a".to_string())
+            .join(cache.get("b".to_string()))
+    });
+    assert_eq!(values, (Some(1), None));
+}
+
+#[test]
+fn test_generic_actors_with_different_parameters() {
+    let values = with_actor_system_async(|_| {
+        let numbers = Cache::<u64, u64>::new().start();
+        let names = Cache::<u64, String>::new().start();
+        numbers.send(CacheMessagePut(1, 10, Default::default()));
+        names.send_put(1, "one".to_string());
+        numbers.get(1).join(names.get(1))
+    });
+    assert_eq!(values, (Some(10), Some("one".to_string())));
+}
@@ -0,0 +1,78 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+
+mod common;
+
+use akio::prelude::*;
+use common::*;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct Counter {
+    count: u64,
+}
+
+#[actor_impl]
+impl Counter {
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+        }
+    }
+
+    #[actor_api]
+    pub fn increment(&mut self) -> u64 {
+        self.count += 1;
+        self.respond(self.count)
+    }
+}
+
+#[test]
+fn test_grains_are_addressed_by_key() {
+    let counts = with_actor_system_async(|system| {
+        system.register_factory(|_| Counter::new());
+        let first = system.grain::<Counter>(&"first").increment();
+        let second = system.grain::<Counter>(&"second").increment();
+        let first_again = system
+            .grain::<Counter>(&"first".to_string())
+            .increment();
+        first.join3(second, first_again)
+    });
+    assert_eq!(counts, (1, 1, 2));
+}
+
+#[test]
+fn test_grain_keyed_by_id() {
+    let (active, id) = with_actor_system_async(|system| {
+        system.register_factory(|_| Counter::new());
+        let id = Uuid::new_v4();
+        let counter = system.grain::<Counter>(&id);
+        let active = counter.exists();
+        counter.increment().map(move |_| {
+            let id = system.get_actor::<Counter>(&id).map(|actor| actor.id());
+            (active, id)
+        })
+    });
+    assert!(!active);
+    assert!(id.is_some());
+}
+
+#[test]
+fn test_concurrent_activations_start_one_actor() {
+    let (activations, counts) = with_actor_system_async(|system| {
+        let activations = Arc::new(AtomicUsize::new(0));
+        let factory_activations = activations.clone();
+        system.register_factory(move |_| {
+            factory_activations.fetch_add(1, Ordering::SeqCst);
+            Counter::new()
+        });
+        let requests = (0..10)
+            .map(|_| system.grain::<Counter>(&"shared").increment())
+            .collect::<Vec<_>>();
+        future::join_all(requests)
+            .map(move |counts| (activations.load(Ordering::SeqCst), counts))
+    });
+    assert_eq!(activations, 1);
+    assert_eq!(counts, (1..11).collect::<Vec<_>>());
+}
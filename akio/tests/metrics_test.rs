@@ -0,0 +1,71 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+
+mod common;
+
+use akio::metrics::MetricValue;
+use akio::prelude::*;
+use common::*;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+#[test]
+fn test_message_processing_is_recorded() {
+    let snapshot = with_actor_system_async(|system| {
+        let actor_ref = TestActor::new().start();
+        actor_ref
+            .test_method()
+            .map(move |_| system.metrics().snapshot())
+    });
+    let processed = snapshot
+        .iter()
+        .filter(|&&(ref key, _)| key.name == "akio_message_processing_seconds")
+        .any(|&(_, ref value)| match *value {
+            MetricValue::Histogram(ref histogram) => histogram.count > 0,
+            _ => false,
+        });
+    assert!(processed);
+}
+
+#[test]
+fn test_render_prometheus() {
+    let rendered = with_actor_system_async(|system| {
+        let actor_ref = TestActor::new().start();
+        actor_ref
+            .test_method()
+            .map(move |_| system.metrics().render_prometheus())
+    });
+    assert!(rendered.contains("# TYPE akio_actors gauge"));
+    assert!(rendered.contains("# TYPE akio_message_processing_seconds histogram"));
+    assert!(rendered.contains("le=\"+Inf\""));
+}
+
+#[test]
+fn test_render_prometheus_escapes_label_values() {
+    let rendered = with_actor_system_async(|system| {
+        system
+            .metrics()
+            .counter("test_escaped_total", &[("path", "C:\\logs\n\"a\"")])
+            .inc();
+        future::ok::<_, ()>(system.metrics().render_prometheus())
+    });
+    assert!(rendered.contains("test_escaped_total{path=\"C:\\\\logs\\n\\\"a\\\"\"} 1"));
+}
+
+#[test]
+fn test_serve_metrics() {
+    let addr = with_actor_system_async(|system| {
+        system.metrics().counter("test_served_total", &[]).inc();
+        let addr = system
+            .serve_metrics(&"127.0.0.1:0".parse().unwrap())
+            .expect("failed to serve metrics");
+        future::ok::<_, ()>(addr)
+    });
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(b"GET /metrics HTTP/1.0\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+    assert!(response.contains("test_served_total 1"));
+}
@@ -0,0 +1,111 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+extern crate futures;
+extern crate tokio_core;
+
+mod common;
+
+use akio::prelude::*;
+use akio::testkit::*;
+use common::*;
+use futures::future::Loop;
+use std::time::Duration;
+use tokio_core::reactor::Timeout;
+
+pub struct Counter {
+    count: u64,
+}
+
+#[actor_impl]
+impl Counter {
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+        }
+    }
+
+    #[actor_api]
+    pub fn increment(&mut self) -> u64 {
+        self.count += 1;
+        self.respond(self.count)
+    }
+}
+
+fn sleep(millis: u64) -> impl Future<Item = (), Error = ()> {
+    Timeout::new(Duration::from_millis(millis), &context::handle())
+        .expect("failed to create timeout")
+        .map_err(|_| ())
+}
+
+#[test]
+fn test_idle_actor_is_reactivated_by_next_message() {
+    let (passivated, count) = with_actor_system_local(|system| {
+        system.register_factory(|_| Counter::new());
+        system.passivate_after::<Counter>(Duration::from_millis(20));
+        let counter = Counter::new().start();
+        counter
+            .increment()
+            .and_then(|_| sleep(200))
+            .and_then(move |_| {
+                let passivated = !counter.exists();
+                counter.send_increment();
+                counter.send_increment();
+                counter.increment().map(move |count| (passivated, count))
+            })
+    });
+    assert!(passivated);
+    assert_eq!(count, 3);
+}
+
+#[test]
+fn test_busy_actor_is_not_passivated() {
+    let exists = with_actor_system_local(|system| {
+        system.passivate_after::<Counter>(Duration::from_millis(100));
+        let counter = Counter::new().start();
+        future::loop_fn(0, move |i| {
+            let counter = counter.clone();
+            sleep(20)
+                .and_then(move |_| counter.increment().map(move |_| counter))
+                .map(move |counter| if i < 10 {
+                    Loop::Continue(i + 1)
+                } else {
+                    Loop::Break(counter.exists())
+                })
+        })
+    });
+    assert!(exists);
+}
+
+#[test]
+fn test_passivated_actor_without_factory_stays_stopped() {
+    with_actor_system_local(|system| {
+        let dead_letters = DeadLetterProbe::new(&system);
+        system.passivate_after::<Counter>(Duration::from_millis(20));
+        let counter = Counter::new().start();
+        sleep(200).and_then(move |_| {
+            counter.send_increment();
+            dead_letters
+                .expect_dead_letter(Duration::from_millis(500))
+                .map(|_| ())
+                .map_err(|e| panic!("{}", e))
+        })
+    })
+}
+
+#[test]
+fn test_stopped_actor_is_not_reactivated() {
+    with_actor_system_local(|system| {
+        let dead_letters = DeadLetterProbe::new(&system);
+        system.register_factory(|_| Counter::new());
+        let counter = Counter::new().start();
+        counter.stop().and_then(move |_| {
+            counter.send_increment();
+            let exists = counter.exists();
+            dead_letters
+                .expect_dead_letter(Duration::from_millis(500))
+                .map(move |_| assert!(!exists))
+                .map_err(|e| panic!("{}", e))
+        })
+    })
+}
@@ -0,0 +1,210 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+#[macro_use]
+extern crate serde_derive;
+
+mod common;
+
+use akio::errors::ErrorKind;
+use akio::persistence::{FileJournal, Journal, JournalEntry, MemoryJournal};
+use akio::prelude::*;
+use common::*;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum AccountEvent {
+    Deposited(u64),
+    Withdrawn(u64),
+}
+
+pub struct Account {
+    name: String,
+    balance: u64,
+}
+
+#[actor_impl]
+impl Account {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            balance: 0,
+        }
+    }
+
+    #[actor_api]
+    pub fn deposit(&mut self, amount: u64) -> u64 {
+        self.persist(AccountEvent::Deposited(amount))
+            .expect("failed to persist");
+        self.respond(self.balance)
+    }
+
+    #[actor_api]
+    pub fn withdraw(&mut self, amount: u64) -> u64 {
+        self.persist(AccountEvent::Withdrawn(amount))
+            .expect("failed to persist");
+        self.respond(self.balance)
+    }
+
+    #[actor_api]
+    pub fn balance(&mut self) -> u64 {
+        self.respond(self.balance)
+    }
+}
+
+impl PersistentActor for Account {
+    type Event = AccountEvent;
+
+    fn persistence_id(&self) -> String {
+        format!("account-{}", self.name)
+    }
+
+    fn apply(&mut self, event: &AccountEvent) {
+        match *event {
+            AccountEvent::Deposited(amount) => self.balance += amount,
+            AccountEvent::Withdrawn(amount) => self.balance -= amount,
+        }
+    }
+}
+
+#[test]
+fn test_state_is_recovered_from_events() {
+    let (events, balance) = with_actor_system_async(|system| {
+        let journal = MemoryJournal::new();
+        system.register_journal::<Account, _>(journal.clone());
+        let account = Account::new("alice").start();
+        account
+            .deposit(50)
+            .join(account.withdraw(20))
+            .and_then(move |_| account.stop())
+            .and_then(move |_| {
+                let events = journal.replay("account-alice", 1).unwrap().len();
+                // The events are replayed by `start`, before any message.
+                Account::new("alice")
+                    .start()
+                    .balance()
+                    .map(move |balance| (events, balance))
+            })
+    });
+    assert_eq!(events, 2);
+    assert_eq!(balance, 30);
+}
+
+#[test]
+fn test_accounts_are_persisted_separately() {
+    let balances = with_actor_system_async(|system| {
+        system.register_journal::<Account, _>(MemoryJournal::new());
+        let alice = Account::new("alice").start();
+        let bob = Account::new("bob").start();
+        alice
+            .deposit(10)
+            .join(bob.deposit(5))
+            .and_then(|_| {
+                let alice = Account::new("alice").start();
+                let bob = Account::new("bob").start();
+                alice.balance().join(bob.balance())
+            })
+    });
+    assert_eq!(balances, (10, 5));
+}
+
+fn journal_directory() -> std::path::PathBuf {
+    env::temp_dir().join(format!("akio-journal-{}", Uuid::new_v4()))
+}
+
+#[test]
+fn test_file_journal_survives_reopening() {
+    let directory = journal_directory();
+    let journal = FileJournal::open(&directory).unwrap();
+    assert_eq!(journal.append("a", vec![vec![1], vec![2, 2]]).unwrap(), 2);
+    assert_eq!(journal.append("a", vec![vec![3]]).unwrap(), 3);
+    journal.append("b", vec![vec![4]]).unwrap();
+
+    let reopened = FileJournal::open(&directory).unwrap();
+    assert_eq!(
+        reopened.replay("a", 2).unwrap(),
+        vec![
+            JournalEntry {
+                sequence_nr: 2,
+                payload: vec![2, 2],
+            },
+            JournalEntry {
+                sequence_nr: 3,
+                payload: vec![3],
+            },
+        ]
+    );
+    assert_eq!(reopened.append("b", vec![vec![5]]).unwrap(), 2);
+}
+
+fn journal_file(directory: &std::path::Path) -> std::path::PathBuf {
+    std::fs::read_dir(directory)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path()
+}
+
+#[test]
+fn test_file_journal_drops_incomplete_record() {
+    let directory = journal_directory();
+    let journal = FileJournal::open(&directory).unwrap();
+    journal.append("a", vec![vec![1]]).unwrap();
+    let path = journal_file(&directory);
+    OpenOptions::new()
+        .append(true)
+        .open(path)
+        .unwrap()
+        .write_all(&[2, 0, 0])
+        .unwrap();
+
+    let reopened = FileJournal::open(&directory).unwrap();
+    assert_eq!(reopened.replay("a", 1).unwrap().len(), 1);
+    assert_eq!(reopened.append("a", vec![vec![2]]).unwrap(), 2);
+    assert_eq!(reopened.replay("a", 1).unwrap().len(), 2);
+}
+
+#[test]
+fn test_file_journal_drops_garbled_last_record() {
+    let directory = journal_directory();
+    let journal = FileJournal::open(&directory).unwrap();
+    journal.append("a", vec![vec![1]]).unwrap();
+    // A header whose checksum doesn't match, followed by its payload.
+    let mut record = vec![2, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0];
+    record.push(2);
+    OpenOptions::new()
+        .append(true)
+        .open(journal_file(&directory))
+        .unwrap()
+        .write_all(&record)
+        .unwrap();
+
+    let reopened = FileJournal::open(&directory).unwrap();
+    assert_eq!(reopened.replay("a", 1).unwrap().len(), 1);
+    assert_eq!(reopened.append("a", vec![vec![2]]).unwrap(), 2);
+}
+
+#[test]
+fn test_file_journal_detects_corrupt_record() {
+    let directory = journal_directory();
+    let journal = FileJournal::open(&directory).unwrap();
+    journal.append("a", vec![vec![1], vec![2]]).unwrap();
+    // The first record's payload follows its 16 byte header.
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(journal_file(&directory))
+        .unwrap();
+    file.seek(SeekFrom::Start(16)).unwrap();
+    file.write_all(&[9]).unwrap();
+
+    match FileJournal::open(&directory).unwrap().replay("a", 1) {
+        Err(e) => match *e.kind() {
+            ErrorKind::CorruptJournal(_, sequence_nr) => assert_eq!(sequence_nr, 1),
+            ref kind => panic!("unexpected error: {}", kind),
+        },
+        Ok(_) => panic!("replayed a corrupt record"),
+    }
+}
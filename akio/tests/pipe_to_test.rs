@@ -0,0 +1,88 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+extern crate futures;
+
+mod common;
+
+use akio::prelude::*;
+use akio::testkit::*;
+use common::*;
+use futures::sync::oneshot;
+use std::result::Result;
+use std::time::Duration;
+
+fn timeout() -> Duration {
+    Duration::from_millis(500)
+}
+
+pub struct Fetcher {
+    results: ActorRef<ProbeActor<Result<u64, ()>>>,
+}
+
+#[actor_impl]
+impl Fetcher {
+    pub fn new(results: ActorRef<ProbeActor<Result<u64, ()>>>) -> Self {
+        Self {
+            results: results,
+        }
+    }
+
+    #[actor_api]
+    pub fn fetch(&mut self, value: oneshot::Receiver<u64>) {
+        self.pipe_to_self(value, FetcherMessageFetched);
+        self.done()
+    }
+
+    #[actor_api]
+    pub fn fetched(&mut self, result: Result<u64, oneshot::Canceled>) {
+        self.results.send(result.map_err(|_| ()));
+        self.done()
+    }
+}
+
+#[test]
+fn test_pipe_success_to_self() {
+    let result = with_actor_system_local(|_| {
+        let probe = TestProbe::new();
+        let fetcher = Fetcher::new(probe.actor_ref()).start();
+        let (sender, receiver) = oneshot::channel();
+        fetcher.send_fetch(receiver);
+        sender.send(5).unwrap();
+        probe
+            .expect_msg(timeout())
+            .map_err(|e| panic!("{}", e))
+    });
+    assert_eq!(result, Ok(5));
+}
+
+#[test]
+fn test_pipe_failure_to_self() {
+    let result = with_actor_system_local(|_| {
+        let probe = TestProbe::new();
+        let fetcher = Fetcher::new(probe.actor_ref()).start();
+        let (_, receiver) = oneshot::channel();
+        fetcher.send_fetch(receiver);
+        probe
+            .expect_msg(timeout())
+            .map_err(|e| panic!("{}", e))
+    });
+    assert_eq!(result, Err(()));
+}
+
+#[test]
+fn test_pipe_is_cancelled_when_target_stops() {
+    with_actor_system_local(|_| {
+        let probe = TestProbe::<Result<u64, ()>>::new();
+        let (guard, dropped) = oneshot::channel::<()>();
+        future::empty::<u64, ()>()
+            .map(move |value| {
+                let _ = &guard;
+                value
+            })
+            .pipe_to(&probe.actor_ref(), |result| result);
+        probe.actor_ref().stop().and_then(move |_| {
+            within(dropped.then(|_| Ok::<_, Error>(())), timeout()).map_err(|e| panic!("{}", e))
+        })
+    })
+}
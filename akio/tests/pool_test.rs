@@ -0,0 +1,111 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+extern crate futures;
+extern crate tokio_core;
+
+mod common;
+
+use akio::RoundRobin;
+use akio::metrics::MetricValue;
+use akio::prelude::*;
+use common::*;
+use futures::sync::oneshot;
+use std::time::Duration;
+use tokio_core::reactor::Timeout;
+
+pub struct Worker;
+
+#[actor_impl]
+impl Worker {
+    #[actor_api]
+    pub fn hold(&mut self, release: oneshot::Receiver<()>) {
+        self.respond_async(release, |worker, _| worker.done())
+    }
+
+    #[actor_api]
+    pub fn work(&mut self) {
+        self.done()
+    }
+}
+
+fn pool(config: PoolConfig) -> Pool<Worker, RoundRobin> {
+    Pool::start(config, RoundRobin::new(), || Worker)
+}
+
+fn sleep(millis: u64) -> impl Future<Item = (), Error = ()> {
+    Timeout::new(Duration::from_millis(millis), &context::handle())
+        .expect("failed to create timeout")
+        .map_err(|_| ())
+}
+
+fn pool_size_metric(system: &ActorSystem) -> Option<MetricValue> {
+    system
+        .metrics()
+        .get("akio_pool_size", &[("actor_type", "pool_test::Worker")])
+}
+
+#[test]
+fn test_pool_starts_min_size() {
+    let (size, metric) = with_actor_system_local(|system| {
+        let pool = pool(PoolConfig::new(3, 5));
+        let router = pool.router().clone();
+        future::join_all(vec![router.work(), router.work(), router.work()])
+            .map(move |_| (pool.size(), pool_size_metric(&system)))
+    });
+    assert_eq!(size, 3);
+    assert_eq!(metric, Some(MetricValue::Gauge(3)));
+}
+
+#[test]
+fn test_pool_replaces_stopped_members() {
+    let (ids_before, ids_after) = with_actor_system_local(|_| {
+        let pool = pool(PoolConfig::new(2, 2));
+        let members = pool.router().routees();
+        let ids_before = members.iter().map(ActorRef::id).collect::<Vec<_>>();
+        members[0]
+            .stop()
+            .and_then(|_| sleep(50))
+            .map(move |_| {
+                let ids_after = pool.router()
+                    .routees()
+                    .iter()
+                    .map(ActorRef::id)
+                    .collect::<Vec<_>>();
+                (ids_before, ids_after)
+            })
+    });
+    assert_eq!(ids_after.len(), 2);
+    assert!(!ids_after.contains(&ids_before[0]));
+    assert!(ids_after.contains(&ids_before[1]));
+}
+
+#[test]
+fn test_pool_grows_under_pressure_and_shrinks_when_idle() {
+    let (grown, shrunk) = with_actor_system_local(|_| {
+        let config = PoolConfig::new(1, 3)
+            .pressure_threshold(2)
+            .resize_interval(Duration::from_millis(10));
+        let pool = pool(config);
+        let (release, released) = oneshot::channel();
+        pool.router().send_hold(released);
+        (0..10).for_each(|_| pool.router().send_work());
+        sleep(100)
+            .map(move |_| {
+                let _ = release.send(());
+                pool
+            })
+            .and_then(|pool| {
+                let grown = pool.size();
+                sleep(200).map(move |_| (grown, pool.size()))
+            })
+    });
+    assert_eq!(grown, 3);
+    assert_eq!(shrunk, 1);
+}
+
+#[test]
+#[should_panic(expected = "pool min_size must be at least 1")]
+fn test_pool_min_size_must_be_positive() {
+    PoolConfig::new(0, 3);
+}
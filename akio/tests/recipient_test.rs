@@ -0,0 +1,58 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+
+mod common;
+
+use akio::prelude::*;
+use common::*;
+
+struct OtherActor;
+
+impl Actor for OtherActor {}
+
+impl MessageHandler<TestActorMessageGetTestMethodCalls> for OtherActor {
+    type Response = u64;
+
+    fn handle(&mut self, _: TestActorMessageGetTestMethodCalls) -> ActorResponse<u64> {
+        self.respond(10)
+    }
+}
+
+#[test]
+fn test_recipients_fan_out() {
+    let calls = with_actor_system_async(|_| {
+        let actors = vec![TestActor::new().start(), TestActor::new().start()];
+        let recipients = actors
+            .iter()
+            .map(|actor| actor.recipient())
+            .collect::<Vec<Recipient<TestActorMessageTestMethod>>>();
+        recipients
+            .iter()
+            .for_each(|recipient| recipient.send(TestActorMessageTestMethod()));
+        let calls = actors
+            .iter()
+            .map(|actor| actor.get_test_method_calls())
+            .collect::<Vec<_>>();
+        future::join_all(calls)
+    });
+    assert_eq!(calls, vec![1, 1]);
+}
+
+#[test]
+fn test_ask_recipients_of_different_actor_types() {
+    let calls = with_actor_system_async(|_| {
+        let test_actor = TestActor::new().start();
+        let other_actor = OtherActor.start();
+        let recipients: Vec<AskRecipient<TestActorMessageGetTestMethodCalls, u64>> = vec![
+            test_actor.ask_recipient(),
+            AskRecipient::from(other_actor).clone(),
+        ];
+        let requests = recipients
+            .iter()
+            .map(|recipient| recipient.request(TestActorMessageGetTestMethodCalls()))
+            .collect::<Vec<_>>();
+        future::join_all(requests)
+    });
+    assert_eq!(calls, vec![0, 10]);
+}
@@ -0,0 +1,40 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+extern crate futures;
+
+mod common;
+
+use akio::prelude::*;
+use akio::remote::RemoteActor;
+use akio::testkit::*;
+use common::*;
+use futures::sync::mpsc;
+use std::time::Duration;
+
+fn remote_ref(system: &ActorSystem) -> ActorRef<TestActor> {
+    let (outbox, _) = mpsc::unbounded();
+    ActorRef::remote(RemoteActor::new(
+        "127.0.0.1:16700".parse().unwrap(),
+        Uuid::new_v4(),
+        outbox,
+        system.serializers(),
+    ))
+}
+
+#[test]
+fn test_stopping_remote_ref_fails() {
+    let result = with_actor_system_async(|system| remote_ref(&system).stop().then(Ok));
+    assert_eq!(result, Err(()));
+}
+
+#[test]
+fn test_remote_ref_is_never_terminated() {
+    let result = with_actor_system_local(|system| {
+        let terminated = remote_ref(&system)
+            .terminated()
+            .map_err(|_| ErrorKind::ProbeClosed.into());
+        within(terminated, Duration::from_millis(50)).then(|result| Ok(result.is_ok()))
+    });
+    assert!(!result);
+}
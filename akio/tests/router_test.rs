@@ -0,0 +1,121 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+extern crate futures;
+
+mod common;
+
+use akio::{Broadcast, ConsistentHash, RoundRobin};
+use akio::prelude::*;
+use common::*;
+
+pub struct Worker {
+    id: u64,
+    handled: u64,
+}
+
+#[actor_impl]
+impl Worker {
+    pub fn new(id: u64) -> Self {
+        Self {
+            id: id,
+            handled: 0,
+        }
+    }
+
+    #[actor_api(clone, hash_key = "key")]
+    pub fn work(&mut self, key: u64) -> (u64, u64, u64) {
+        self.handled += 1;
+        self.respond((self.id, key, self.handled))
+    }
+
+    #[actor_api(clone, hash_key = "user")]
+    pub fn greet(&mut self, user: String) -> (u64, String) {
+        self.respond((self.id, format!("hello {}", user)))
+    }
+}
+
+fn workers(count: u64) -> Vec<ActorRef<Worker>> {
+    (0..count).map(|id| Worker::new(id).start()).collect()
+}
+
+fn worker_ids<R>(router: &R, keys: Vec<u64>) -> impl Future<Item = Vec<u64>, Error = ()>
+where
+    R: WorkerRef,
+{
+    let requests = keys.into_iter()
+        .map(|key| router.work(key).map(|(id, _, _)| id))
+        .collect::<Vec<_>>();
+    future::join_all(requests)
+}
+
+#[test]
+fn test_round_robin() {
+    let ids = with_actor_system_async(|_| {
+        let router = Router::new(RoundRobin::new(), workers(2));
+        worker_ids(&router, vec![0, 0, 0, 0])
+    });
+    assert_eq!(ids, vec![0, 1, 0, 1]);
+}
+
+#[test]
+fn test_broadcast() {
+    let handled = with_actor_system_async(|_| {
+        let routees = workers(3);
+        let router = Router::new(Broadcast, routees.clone());
+        router.send_work(0);
+        let requests = routees
+            .iter()
+            .map(|routee| routee.work(0).map(|(_, _, handled)| handled))
+            .collect::<Vec<_>>();
+        future::join_all(requests)
+    });
+    assert_eq!(handled, vec![2, 2, 2]);
+}
+
+#[test]
+fn test_consistent_hash_keeps_keys_on_their_routee() {
+    let (before, after) = with_actor_system_async(|_| {
+        let router = Router::new(ConsistentHash, workers(3));
+        worker_ids(&router, vec![7, 7, 7]).and_then(move |before| {
+            let owner = before[0];
+            let other = router
+                .routees()
+                .into_iter()
+                .zip(0..)
+                .find(|&(_, id)| id != owner)
+                .map(|(routee, _)| routee.id())
+                .unwrap();
+            router.remove_routee(&other);
+            worker_ids(&router, vec![7]).map(move |after| (before, after))
+        })
+    });
+    assert_eq!(before, vec![before[0]; 3]);
+    assert_eq!(after, vec![before[0]]);
+}
+
+#[test]
+fn test_consistent_hash_by_string_argument() {
+    let replies = with_actor_system_async(|_| {
+        let router = Router::new(ConsistentHash, workers(3));
+        let requests = (0..3)
+            .map(|_| router.greet("ada".to_owned()))
+            .collect::<Vec<_>>();
+        future::join_all(requests)
+    });
+    let owner = replies[0].0;
+    assert_eq!(replies, vec![(owner, "hello ada".to_owned()); 3]);
+}
+
+#[test]
+fn test_routees_can_be_added() {
+    let ids = with_actor_system_async(|_| {
+        let router = Router::new(RoundRobin::new(), Vec::new());
+        router.work(0).then(move |result| {
+            assert!(result.is_err());
+            router.add_routee(Worker::new(5).start());
+            worker_ids(&router, vec![0])
+        })
+    });
+    assert_eq!(ids, vec![5]);
+}
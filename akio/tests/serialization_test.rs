@@ -0,0 +1,122 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+#[macro_use]
+extern crate serde_derive;
+
+mod common;
+
+use akio::errors::ErrorKind;
+use akio::prelude::*;
+use akio::serialization::SerializedMessage;
+use common::*;
+
+pub struct Account {
+    balance: u64,
+    references: Vec<String>,
+}
+
+#[actor_impl(serializable)]
+impl Account {
+    pub fn new() -> Self {
+        Self {
+            balance: 0,
+            references: Vec::new(),
+        }
+    }
+
+    #[actor_api]
+    pub fn deposit(&mut self, amount: u64, reference: String) -> u64 {
+        self.references.push(reference);
+        self.balance += amount;
+        self.respond(self.balance)
+    }
+
+    #[actor_api]
+    pub fn reset(&mut self) {
+        self.balance = 0;
+        self.done()
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Deposit(u64);
+
+impl ActorMessage for Deposit {
+    type Response = u64;
+}
+
+impl SerializableMessage for Deposit {
+    fn message_type_id() -> &'static str {
+        "Account.deposit"
+    }
+}
+
+#[test]
+fn test_message_and_response_round_trip() {
+    let balance = with_actor_system_async(|system| {
+        Account::register_messages(&system).unwrap();
+        let serializers = system.serializers();
+        let message = serializers
+            .serialize(&AccountMessageDeposit(5, "first".to_string()))
+            .unwrap();
+        assert_eq!(message.message_type_id, "Account.deposit");
+        let message = serializers
+            .deserialize(&message)
+            .unwrap()
+            .downcast::<AccountMessageDeposit>()
+            .unwrap();
+        let account = Account::new().start();
+        account.deposit(message.0, message.1).map(move |balance| {
+            let payload = serializers
+                .serialize_response("Account.deposit", &balance)
+                .unwrap();
+            *serializers
+                .deserialize_response("Account.deposit", &payload)
+                .unwrap()
+                .downcast::<u64>()
+                .unwrap()
+        })
+    });
+    assert_eq!(balance, 5);
+}
+
+#[test]
+fn test_registering_is_idempotent() {
+    with_actor_system(|system| {
+        Account::register_messages(&system).unwrap();
+        Account::register_messages(&system).unwrap();
+        assert!(system.serializers().is_registered("Account.reset"));
+    });
+}
+
+#[test]
+fn test_duplicate_message_type_id() {
+    with_actor_system(|system| {
+        Account::register_messages(&system).unwrap();
+        match system.register_message::<Deposit>() {
+            Err(e) => match *e.kind() {
+                ErrorKind::DuplicateMessageType(id, _, _) => assert_eq!(id, "Account.deposit"),
+                ref kind => panic!("unexpected error: {}", kind),
+            },
+            Ok(_) => panic!("registered two messages as 'Account.deposit'"),
+        }
+    });
+}
+
+#[test]
+fn test_unknown_message_type_id() {
+    with_actor_system(|system| {
+        let message = SerializedMessage {
+            message_type_id: "Account.withdraw".to_string(),
+            payload: Vec::new(),
+        };
+        match system.serializers().deserialize(&message) {
+            Err(e) => match *e.kind() {
+                ErrorKind::UnknownMessageType(ref id) => assert_eq!(id, "Account.withdraw"),
+                ref kind => panic!("unexpected error: {}", kind),
+            },
+            Ok(_) => panic!("decoded a message with an unregistered type id"),
+        }
+    });
+}
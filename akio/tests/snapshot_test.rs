@@ -0,0 +1,164 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+#[macro_use]
+extern crate serde_derive;
+
+mod common;
+
+use akio::persistence::*;
+use akio::prelude::*;
+use common::*;
+use std::env;
+use std::path::PathBuf;
+
+#[derive(Deserialize, Serialize)]
+pub struct Added(u64);
+
+#[derive(Deserialize, Serialize)]
+pub struct TotalSnapshot(u64);
+
+pub struct Total {
+    total: u64,
+    restored: bool,
+}
+
+#[actor_impl]
+impl Total {
+    pub fn new() -> Self {
+        Self {
+            total: 0,
+            restored: false,
+        }
+    }
+
+    #[actor_api]
+    pub fn add(&mut self, amount: u64) {
+        self.persist(Added(amount)).expect("failed to persist");
+        self.done()
+    }
+
+    #[actor_api]
+    pub fn snapshot_now(&mut self) {
+        self.save_snapshot().expect("failed to snapshot");
+        self.done()
+    }
+
+    #[actor_api]
+    pub fn state(&mut self) -> (u64, bool) {
+        self.respond((self.total, self.restored))
+    }
+}
+
+impl PersistentActor for Total {
+    type Event = Added;
+
+    fn persistence_id(&self) -> String {
+        "total".to_string()
+    }
+
+    fn apply(&mut self, event: &Added) {
+        self.total += event.0;
+    }
+}
+
+impl SnapshotActor for Total {
+    type Snapshot = TotalSnapshot;
+
+    fn snapshot(&self) -> TotalSnapshot {
+        TotalSnapshot(self.total)
+    }
+
+    fn restore(&mut self, snapshot: TotalSnapshot) {
+        self.total = snapshot.0;
+        self.restored = true;
+    }
+}
+
+fn temp_directory(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("akio-{}-{}", name, Uuid::new_v4()))
+}
+
+fn add_then_restart(
+    total: ActorRef<Total>,
+    amounts: Vec<u64>,
+) -> impl Future<Item = (u64, bool), Error = ()> {
+    let adds = amounts
+        .into_iter()
+        .map(|amount| total.add(amount))
+        .collect::<Vec<_>>();
+    future::join_all(adds)
+        .and_then(move |_| total.stop())
+        .and_then(|_| Total::new().start().state())
+}
+
+#[test]
+fn test_recovers_from_snapshot_and_later_events() {
+    let store = FileSnapshotStore::open(temp_directory("snapshots")).unwrap();
+    let recovery_store = store.clone();
+    let state = with_actor_system_async(move |system| {
+        system.register_journal::<Total, _>(MemoryJournal::new());
+        system.register_snapshot_store::<Total, _>(store, SnapshotPolicy::new().every(3));
+        add_then_restart(Total::new().start(), vec![1, 2, 3, 4, 5, 6, 7])
+    });
+    assert_eq!(state, (28, true));
+    assert_eq!(recovery_store.sequence_nrs("total").unwrap(), vec![3, 6]);
+}
+
+#[test]
+fn test_snapshot_on_demand() {
+    let store = FileSnapshotStore::open(temp_directory("snapshots")).unwrap();
+    let recovery_store = store.clone();
+    let state = with_actor_system_async(move |system| {
+        system.register_journal::<Total, _>(MemoryJournal::new());
+        system.register_snapshot_store::<Total, _>(store, SnapshotPolicy::new());
+        let total = Total::new().start();
+        total
+            .add(5)
+            .and_then(move |_| {
+                total.send_snapshot_now();
+                add_then_restart(total, vec![1])
+            })
+    });
+    assert_eq!(state, (6, true));
+    assert_eq!(recovery_store.sequence_nrs("total").unwrap(), vec![1]);
+}
+
+#[test]
+fn test_retention_deletes_old_snapshots_and_events() {
+    let store = FileSnapshotStore::open(temp_directory("snapshots")).unwrap();
+    let journal = FileJournal::open(temp_directory("journal")).unwrap();
+    let (recovery_store, recovery_journal) = (store.clone(), journal.clone());
+    let state = with_actor_system_async(move |system| {
+        let policy = SnapshotPolicy::new()
+            .every(2)
+            .keep(1)
+            .delete_events(true);
+        system.register_journal::<Total, _>(journal);
+        system.register_snapshot_store::<Total, _>(store, policy);
+        add_then_restart(Total::new().start(), vec![1, 2, 3, 4, 5])
+    });
+    assert_eq!(state, (15, true));
+    assert_eq!(recovery_store.sequence_nrs("total").unwrap(), vec![4]);
+    let remaining = recovery_journal
+        .replay("total", 1)
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.sequence_nr)
+        .collect::<Vec<_>>();
+    assert_eq!(remaining, vec![5]);
+    assert_eq!(recovery_journal.highest_sequence_nr("total").unwrap(), 5);
+}
+
+#[test]
+fn test_file_journal_keeps_sequence_after_deleting_every_event() {
+    let directory = temp_directory("journal");
+    let journal = FileJournal::open(&directory).unwrap();
+    journal.append("a", vec![vec![1], vec![2]]).unwrap();
+    journal.delete_to("a", 2).unwrap();
+
+    let reopened = FileJournal::open(&directory).unwrap();
+    assert!(reopened.replay("a", 1).unwrap().is_empty());
+    assert_eq!(reopened.highest_sequence_nr("a").unwrap(), 2);
+    assert_eq!(reopened.append("a", vec![vec![3]]).unwrap(), 3);
+}
@@ -0,0 +1,37 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+
+mod common;
+
+use akio::Span;
+use akio::prelude::*;
+use common::*;
+
+struct SpanActor;
+
+#[actor_impl]
+impl SpanActor {
+    #[actor_api]
+    pub fn current_span(&mut self) -> Option<Span> {
+        self.respond(Span::current())
+    }
+}
+
+#[test]
+fn test_handler_runs_in_actor_span() {
+    let (id, span) = with_actor_system_async(|_| {
+        let actor_ref = SpanActor.start();
+        let id = actor_ref.id();
+        actor_ref.current_span().map(move |span| (id, span))
+    });
+    let span = span.expect("no span while handling a message");
+    assert_eq!(span.actor_id, id);
+    assert!(span.actor_type.ends_with("SpanActor"));
+    assert!(span.message_type.ends_with("SpanActorMessageCurrentSpan"));
+}
+
+#[test]
+fn test_no_span_outside_actor() {
+    assert!(Span::current().is_none());
+}
@@ -0,0 +1,95 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+extern crate futures;
+
+mod common;
+
+use akio::prelude::*;
+use akio::testkit::*;
+use common::*;
+use futures::stream;
+use futures::sync::{mpsc, oneshot};
+use std::time::Duration;
+
+fn timeout() -> Duration {
+    Duration::from_millis(500)
+}
+
+pub struct Listener {
+    events: ActorRef<ProbeActor<String>>,
+}
+
+#[actor_impl]
+impl Listener {
+    pub fn new(events: ActorRef<ProbeActor<String>>) -> Self {
+        Self {
+            events: events,
+        }
+    }
+
+    #[actor_api]
+    pub fn listen(&mut self, stream: Box<Stream<Item = u64, Error = ()> + Send>) {
+        self.add_stream(stream);
+        self.done()
+    }
+}
+
+impl StreamHandler<u64> for Listener {
+    fn handle_item(&mut self, item: u64) {
+        self.events.send(format!("item {}", item));
+    }
+
+    fn started(&mut self) {
+        self.events.send("started".to_string());
+    }
+
+    fn finished(&mut self) {
+        self.events.send("finished".to_string());
+    }
+}
+
+fn expect_events(
+    probe: TestProbe<String>,
+    count: usize,
+) -> impl Future<Item = Vec<String>, Error = ()> {
+    stream::iter_ok(0..count)
+        .and_then(move |_| probe.expect_msg(timeout()))
+        .collect()
+        .map_err(|e| panic!("{}", e))
+}
+
+#[test]
+fn test_stream_items_are_delivered() {
+    let events = with_actor_system_local(|_| {
+        let probe = TestProbe::new();
+        let listener = Listener::new(probe.actor_ref()).start();
+        listener.add_stream(stream::iter_ok::<_, ()>(vec![1, 2]));
+        expect_events(probe, 4)
+    });
+    assert_eq!(events, vec!["started", "item 1", "item 2", "finished"]);
+}
+
+#[test]
+fn test_stream_is_dropped_when_actor_stops() {
+    with_actor_system_local(|_| {
+        let probe = TestProbe::new();
+        let listener = Listener::new(probe.actor_ref()).start();
+        let (sender, receiver) = mpsc::unbounded::<u64>();
+        let (guard, dropped) = oneshot::channel::<()>();
+        let stream = receiver.map(move |item| {
+            let _ = &guard;
+            item
+        });
+        listener.send_listen(Box::new(stream));
+        probe
+            .expect_msg(timeout())
+            .map_err(|e| panic!("{}", e))
+            .and_then(move |_| listener.stop())
+            .and_then(move |_| {
+                within(dropped.then(|_| Ok::<_, Error>(())), timeout())
+                    .map(move |_| drop(sender))
+                    .map_err(|e| panic!("{}", e))
+            })
+    })
+}
@@ -0,0 +1,64 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+extern crate futures;
+
+mod common;
+
+use akio::prelude::*;
+use common::*;
+use futures::stream;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct Table {
+    rows: Vec<u64>,
+}
+
+#[actor_impl]
+impl Table {
+    pub fn new(rows: Vec<u64>) -> Self {
+        Self {
+            rows: rows,
+        }
+    }
+
+    #[actor_api]
+    pub fn scan(&mut self, limit: usize) -> ActorStream<u64> {
+        let rows = self.rows.iter().cloned().take(limit).collect::<Vec<u64>>();
+        self.respond_stream(stream::iter_ok::<_, ()>(rows))
+    }
+
+    #[actor_api]
+    pub fn count_forever(&mut self, produced: Arc<AtomicUsize>) -> ActorStream<usize> {
+        self.respond_stream(stream::unfold(0, move |n| {
+            produced.fetch_add(1, Ordering::SeqCst);
+            Some(Ok::<_, ()>((n, n + 1)))
+        }))
+    }
+}
+
+#[test]
+fn test_stream_response() {
+    let rows = with_actor_system_async(|_| {
+        let table = Table::new(vec![1, 2, 3, 4]).start();
+        table.scan(3).collect()
+    });
+    assert_eq!(rows, vec![1, 2, 3]);
+}
+
+#[test]
+fn test_dropped_stream_stops_producer() {
+    let produced = Arc::new(AtomicUsize::new(0));
+    let produced_clone = produced.clone();
+    let counts = with_actor_system_async(|_| {
+        let table = Table::new(Vec::new()).start();
+        table
+            .count_forever(produced_clone)
+            .take(5)
+            .collect()
+            .and_then(move |counts| table.scan(0).collect().map(|_| counts))
+    });
+    assert_eq!(counts, vec![0, 1, 2, 3, 4]);
+    assert!(produced.load(Ordering::SeqCst) <= 5 + 16 + 2);
+}
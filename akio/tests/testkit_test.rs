@@ -0,0 +1,78 @@
+#![feature(conservative_impl_trait)]
+#![feature(proc_macro)]
+extern crate akio;
+
+mod common;
+
+use akio::prelude::*;
+use akio::testkit::*;
+use common::*;
+use std::time::Duration;
+
+fn timeout() -> Duration {
+    Duration::from_millis(500)
+}
+
+#[test]
+fn test_probe_expect_msg() {
+    let message = with_actor_system_local(|_| {
+        let probe = TestProbe::<u64>::new();
+        probe.actor_ref().send(5);
+        probe
+            .expect_msg(timeout())
+            .map_err(|e| panic!("{}", e))
+    });
+    assert_eq!(message, 5);
+}
+
+#[test]
+fn test_probe_expect_no_msg() {
+    with_actor_system_local(|_| {
+        let probe = TestProbe::<u64>::new();
+        probe
+            .expect_no_msg(Duration::from_millis(50))
+            .map_err(|e| panic!("{}", e))
+    })
+}
+
+#[test]
+fn test_stubbed_actor_api() {
+    let calls = with_actor_system_local(|_| {
+        let stub = ActorStub::new()
+            .on(|_: TestActorMessageGetTestMethodCalls| 42)
+            .start();
+        stub.get_test_method_calls()
+    });
+    assert_eq!(calls, 42);
+}
+
+#[test]
+fn test_expect_terminated() {
+    with_actor_system_local(|_| {
+        let actor_ref = TestActor::new().start();
+        let stopped = actor_ref.stop();
+        expect_terminated(&actor_ref, timeout())
+            .map_err(|e| panic!("{}", e))
+            .join(stopped)
+            .map(|_| ())
+    })
+}
+
+#[test]
+fn test_dead_letter_after_stop() {
+    let (id, dead_letter) = with_actor_system_local(|system| {
+        let probe = DeadLetterProbe::new(&system);
+        let actor_ref = TestActor::new().start();
+        let id = actor_ref.id();
+        actor_ref
+            .stop()
+            .and_then(move |_| {
+                actor_ref.send_test_method();
+                probe
+                    .expect_dead_letter(timeout())
+                    .map_err(|e| panic!("{}", e))
+            })
+            .map(move |dead_letter| (id, dead_letter))
+    });
+    assert_eq!(dead_letter.recipient, id);
+}
@@ -3,76 +3,56 @@
 
 extern crate akio;
 
+use akio::Span;
 use akio::prelude::*;
 
 struct OrganizationActor {
-    name: String,
-    owner: String,
+    name: Option<String>,
+    owner: Option<String>,
 }
 
 #[actor_impl]
 impl OrganizationActor {
-    pub fn new(name: String, owner: String) -> Self {
+    pub fn new() -> Self {
         Self {
-            name: name,
-            owner: owner,
+            name: None,
+            owner: None,
         }
     }
 
+    #[actor_api]
+    pub fn update(&mut self, name: String, owner: String) {
+        self.name = Some(name);
+        self.owner = Some(owner);
+        self.done()
+    }
+
     #[actor_api]
     pub fn debug(&mut self) {
         println!(
-            "id: {}, name: {}, owner: {}",
-            self.id(),
+            "id: {}, name: {:?}, owner: {:?}",
+            Span::current().unwrap().actor_id,
             self.name,
             self.owner
-        )
-    }
-}
-
-struct OrganizationsActor {}
-
-#[actor_impl]
-impl OrganizationsActor {
-    pub fn new() -> Self {
-        Self {}
-    }
-
-    #[actor_api]
-    pub fn add(&mut self, org_id: Uuid, name: String, owner: String) {
-        println!("Add {}", org_id);
-        let org_actor = OrganizationActor::new(name, owner);
-        let org_ref = spawn(org_id, org_actor);
-        org_ref.debug()
-    }
-
-    #[actor_api]
-    pub fn count(&mut self) {
-        let org_count = self.with_children(|children| children.iter().len());
-        println!("org count: {}", org_count)
+        );
+        self.done()
     }
 }
 
 pub fn main() {
     let mut system = ActorSystem::new();
+    system.register_factory(|_| OrganizationActor::new());
     system.on_startup(|| {
-        let orgs = spawn(Uuid::new_v4(), OrganizationsActor::new());
-        orgs.add(
-            Uuid::new_v4(),
-            "org 1".to_string(),
-            "kphelps@salsify.com".to_string(),
-        );
-        orgs.add(
-            Uuid::new_v4(),
-            "org 2".to_string(),
-            "kylep91@gmail.com".to_string(),
-        );
-        orgs.add(
-            Uuid::new_v4(),
-            "org 3".to_string(),
-            "meh@meh.meh".to_string(),
-        );
-        orgs.count();
+        let orgs = [
+            ("org-1", "kphelps@salsify.com"),
+            ("org-2", "kylep91@gmail.com"),
+            ("org-3", "meh@meh.meh"),
+        ];
+        for &(name, owner) in orgs.iter() {
+            let org = context::system().grain::<OrganizationActor>(&name);
+            org.send_update(name.to_string(), owner.to_string());
+            org.send_debug();
+        }
     });
     system.start();
 }